@@ -0,0 +1,190 @@
+use super::channel::Channel;
+use super::effects::EffectSlot;
+use rodio::Source;
+use rodio::mixer::{self, Mixer, MixerSource};
+use rodio::source::Zero;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+//frames processed at once, effects run per block and the volume is ramped over it
+const BLOCK_FRAMES: usize = 256;
+
+//kept in both output states so the volumes, effects etc. survive disabling/enabling the output
+pub(crate) struct BusSettings {
+    //None only for the master
+    pub(crate) parent: Option<Channel>,
+    pub(crate) volume: f32,
+    pub(crate) paused: bool,
+    pub(crate) muted: bool,
+    pub(crate) solo: bool,
+    pub(crate) effects: Arc<Mutex<Vec<EffectSlot>>>,
+}
+
+impl BusSettings {
+    pub(crate) fn with_parent(parent: Option<Channel>) -> Self {
+        Self {
+            parent,
+            volume: 1.0,
+            paused: false,
+            muted: false,
+            solo: false,
+            effects: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl Default for BusSettings {
+    fn default() -> Self {
+        Self::with_parent(Some(Channel::Master))
+    }
+}
+
+struct BusControls {
+    //f32 bits
+    volume: AtomicU32,
+    paused: AtomicBool,
+    //muted, or silenced by another bus' solo
+    silent: AtomicBool,
+    effects: Arc<Mutex<Vec<EffectSlot>>>,
+}
+
+//pulls the bus' mixer and runs the effects, volume and pausing over the result
+struct BusSource {
+    inner: MixerSource,
+    controls: Arc<BusControls>,
+    channels: u16,
+    sample_rate: u32,
+    buffer: Vec<f32>,
+    position: usize,
+    gain: f32,
+}
+
+impl BusSource {
+    fn fill_block(&mut self) {
+        let len = BLOCK_FRAMES * self.channels as usize;
+        self.buffer.clear();
+        self.position = 0;
+
+        //a paused bus doesn't pull its sounds, so they continue where they stopped
+        if self.controls.paused.load(Ordering::Relaxed) {
+            self.buffer.resize(len, 0.0);
+            return;
+        }
+        for _ in 0..len {
+            self.buffer.push(self.inner.next().unwrap_or(0.0));
+        }
+
+        if let Ok(mut effects) = self.controls.effects.lock() {
+            for slot in effects.iter_mut().filter(|slot| slot.enabled) {
+                slot.effect
+                    .process(&mut self.buffer, self.channels, self.sample_rate);
+            }
+        }
+
+        let target = if self.controls.silent.load(Ordering::Relaxed) {
+            0.0
+        } else {
+            f32::from_bits(self.controls.volume.load(Ordering::Relaxed))
+        };
+        //ramped, jumping volumes click
+        let step = (target - self.gain) / BLOCK_FRAMES as f32;
+        for frame in self.buffer.chunks_mut(self.channels as usize) {
+            self.gain += step;
+            for sample in frame {
+                *sample *= self.gain;
+            }
+        }
+        self.gain = target;
+    }
+}
+
+impl Iterator for BusSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.buffer.len() {
+            self.fill_block();
+        }
+        let sample = self.buffer[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for BusSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+//the live part of a bus, only exists while the output is enabled
+//sounds and child buses are added to the mixer, the result goes into the parent's mixer
+pub(crate) struct BusOutput {
+    mixer: Mixer,
+    controls: Arc<BusControls>,
+}
+
+impl BusOutput {
+    pub(crate) fn new(
+        parent: &Mixer,
+        channels: u16,
+        sample_rate: u32,
+        settings: &BusSettings,
+        silent: bool,
+    ) -> Self {
+        let (mixer, source) = mixer::mixer(channels, sample_rate);
+        //an empty mixer ends and would be dropped by the parent, silence keeps it running forever
+        mixer.add(Zero::new(channels, sample_rate));
+
+        if let Ok(mut effects) = settings.effects.lock() {
+            for slot in effects.iter_mut() {
+                slot.effect.prepare(channels, sample_rate);
+                slot.effect.reset();
+            }
+        }
+        let controls = Arc::new(BusControls {
+            volume: AtomicU32::new(settings.volume.to_bits()),
+            paused: AtomicBool::new(settings.paused),
+            silent: AtomicBool::new(silent),
+            effects: settings.effects.clone(),
+        });
+        parent.add(BusSource {
+            inner: source,
+            controls: controls.clone(),
+            channels,
+            sample_rate,
+            buffer: Vec::with_capacity(BLOCK_FRAMES * channels as usize),
+            position: 0,
+            gain: if silent { 0.0 } else { settings.volume },
+        });
+
+        Self { mixer, controls }
+    }
+
+    pub(crate) fn apply(&self, settings: &BusSettings, silent: bool) {
+        self.controls
+            .volume
+            .store(settings.volume.to_bits(), Ordering::Relaxed);
+        self.controls
+            .paused
+            .store(settings.paused, Ordering::Relaxed);
+        self.controls.silent.store(silent, Ordering::Relaxed);
+    }
+
+    pub(crate) fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+}
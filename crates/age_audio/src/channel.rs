@@ -0,0 +1,16 @@
+//named buses sounds are played on, every channel has its own volume, pause state and effects
+//channels can be nested, everything ends up in Master
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Master,
+    Music,
+    Sfx,
+    Voice,
+    Ui,
+    Custom(String),
+}
+
+impl Channel {
+    //always there, directly below Master
+    pub const DEFAULTS: [Channel; 4] = [Channel::Music, Channel::Sfx, Channel::Voice, Channel::Ui];
+}
@@ -0,0 +1,109 @@
+use super::errors::AudioError;
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::cpal::{self, SampleFormat, SupportedBufferSize};
+
+//one entry of what a device can do, a device usually has a few of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    //None if the platform doesn't tell
+    pub buffer_size: Option<(u32, u32)>,
+    pub sample_format: SampleFormat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    //empty if the device didn't answer, e.g. because it was just unplugged
+    pub configs: Vec<ConfigRange>,
+}
+
+//devices are picked by name, open one with OutputBackend::Device
+pub fn list_output_devices() -> Result<Vec<DeviceInfo>, AudioError> {
+    let host = cpal::default_host();
+    let default_name = host
+        .default_output_device()
+        .and_then(|device| device.name().ok());
+    let devices = host.output_devices().map_err(AudioError::DevicesError)?;
+    Ok(collect_devices(devices, default_name, |device| {
+        device
+            .supported_output_configs()
+            .map(|configs| configs.collect())
+            .unwrap_or_default()
+    }))
+}
+
+//microphones etc., open one with InputSource::Device
+pub fn list_input_devices() -> Result<Vec<DeviceInfo>, AudioError> {
+    let host = cpal::default_host();
+    let default_name = host
+        .default_input_device()
+        .and_then(|device| device.name().ok());
+    let devices = host.input_devices().map_err(AudioError::DevicesError)?;
+    Ok(collect_devices(devices, default_name, |device| {
+        device
+            .supported_input_configs()
+            .map(|configs| configs.collect())
+            .unwrap_or_default()
+    }))
+}
+
+fn collect_devices<D, F>(devices: D, default_name: Option<String>, configs: F) -> Vec<DeviceInfo>
+where
+    D: Iterator<Item = cpal::Device>,
+    F: Fn(&cpal::Device) -> Vec<cpal::SupportedStreamConfigRange>,
+{
+    let mut result = Vec::new();
+    for device in devices {
+        //devices without a name can't be opened again later, so they are left out
+        let Ok(name) = device.name() else {
+            continue;
+        };
+        let configs = configs(&device)
+            .into_iter()
+            .map(|config| ConfigRange {
+                channels: config.channels(),
+                min_sample_rate: config.min_sample_rate().0,
+                max_sample_rate: config.max_sample_rate().0,
+                buffer_size: match config.buffer_size() {
+                    SupportedBufferSize::Range { min, max } => Some((*min, *max)),
+                    SupportedBufferSize::Unknown => None,
+                },
+                sample_format: config.sample_format(),
+            })
+            .collect();
+        result.push(DeviceInfo {
+            is_default: default_name.as_ref() == Some(&name),
+            name,
+            configs,
+        });
+    }
+    result
+}
+
+pub(crate) fn find_output_device(name: &str) -> Result<cpal::Device, AudioError> {
+    let host = cpal::default_host();
+    let mut devices = host.output_devices().map_err(AudioError::DevicesError)?;
+    devices
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| AudioError::DeviceNotFound(name.to_string()))
+}
+
+pub(crate) fn find_input_device(name: &str) -> Result<cpal::Device, AudioError> {
+    let host = cpal::default_host();
+    let mut devices = host.input_devices().map_err(AudioError::DevicesError)?;
+    devices
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| AudioError::DeviceNotFound(name.to_string()))
+}
+
+pub(crate) fn default_output_device() -> Result<cpal::Device, AudioError> {
+    cpal::default_host()
+        .default_output_device()
+        .ok_or(AudioError::OutputStreamBuilderError(
+            rodio::StreamError::NoDevice,
+        ))
+}
@@ -0,0 +1,251 @@
+use super::channel::Channel;
+use super::errors::{AudioError, describe};
+use super::output_handle::OutputHandle;
+use super::output_handle::output_markers::OutputEnabled;
+use super::sound::SoundHandle;
+use super::source::StreamingSource;
+use super::spatial::{Emitter, Listener};
+use bevy_ecs::lifecycle::HookContext;
+use bevy_ecs::prelude::*;
+use bevy_ecs::world::DeferredWorld;
+use std::path::PathBuf;
+
+//the resource the systems play on, insert it with world.insert_resource(handle.activate_output()?)
+//the systems do nothing without it
+pub type AudioOutput = OutputHandle<OutputEnabled>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioClip {
+    //a name given to OutputHandle::load_file
+    Loaded(String),
+    //decoded from disk while playing
+    File(PathBuf),
+    //decoded from disk on its own thread, for music
+    Streamed(PathBuf),
+}
+
+//spawning it plays the sound, removing it (or despawning the entity) stops it
+//changes to volume, speed, looping and paused are applied to the playing sound
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct AudioSource {
+    pub clip: AudioClip,
+    pub channel: Channel,
+    pub volume: f32,
+    pub speed: f32,
+    pub looping: bool,
+    pub paused: bool,
+    //for one-shots, the entity is despawned once the sound is over
+    pub despawn_when_done: bool,
+}
+
+impl AudioSource {
+    pub fn new(clip: AudioClip) -> Self {
+        Self {
+            clip,
+            channel: Channel::Sfx,
+            volume: 1.0,
+            speed: 1.0,
+            looping: false,
+            paused: false,
+            despawn_when_done: false,
+        }
+    }
+
+    pub fn loaded<S: Into<String>>(name: S) -> Self {
+        Self::new(AudioClip::Loaded(name.into()))
+    }
+
+    pub fn file<P: Into<PathBuf>>(path: P) -> Self {
+        Self::new(AudioClip::File(path.into()))
+    }
+
+    pub fn streamed<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            channel: Channel::Music,
+            ..Self::new(AudioClip::Streamed(path.into()))
+        }
+    }
+
+    pub fn on(mut self, channel: Channel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn one_shot(mut self) -> Self {
+        self.despawn_when_done = true;
+        self
+    }
+}
+
+//makes the entity's AudioSource 3D, move it by changing the emitter's position
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct AudioEmitter(pub Emitter);
+
+//only one entity should have it, usually the camera
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct AudioListener(pub Listener);
+
+//added by the systems once the AudioSource plays
+#[derive(Component, Clone)]
+#[component(on_remove = stop_playing_sound)]
+pub struct PlayingSound(SoundHandle);
+
+impl PlayingSound {
+    pub fn handle(&self) -> &SoundHandle {
+        &self.0
+    }
+}
+
+fn stop_playing_sound(world: DeferredWorld, context: HookContext) {
+    if let Some(playing) = world.get::<PlayingSound>(context.entity) {
+        playing.0.stop();
+    }
+}
+
+//added instead of PlayingSound when the sound couldn't be played, it isn't tried again
+//AudioError isn't Sync, so only its description is kept
+#[derive(Component, Debug, Clone)]
+pub struct AudioFailed(pub String);
+
+//the systems add_audio_systems adds, a schedule doesn't run systems in the order they were added
+//so gameplay systems that spawn or move sources have to be ordered with .before(AudioSet)
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AudioSet;
+
+//run them in this order every frame, after gameplay moved things
+pub fn add_audio_systems(schedule: &mut Schedule) {
+    schedule.add_systems(
+        (
+            stop_removed_sources,
+            start_audio_sources,
+            update_audio_sources,
+            update_spatial_audio,
+            finish_audio_sources,
+        )
+            .chain()
+            .in_set(AudioSet),
+    );
+}
+
+//AudioSources that weren't tried yet
+type NewSources<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static AudioSource, Option<&'static AudioEmitter>),
+    (Without<PlayingSound>, Without<AudioFailed>),
+>;
+
+pub fn start_audio_sources(
+    mut commands: Commands,
+    output: Option<ResMut<AudioOutput>>,
+    sources: NewSources,
+) {
+    let Some(mut output) = output else {
+        return;
+    };
+    for (entity, source, emitter) in &sources {
+        match play(&mut output, source, emitter.map(|emitter| emitter.0)) {
+            Ok(handle) => {
+                apply(source, &handle);
+                commands.entity(entity).insert(PlayingSound(handle));
+            }
+            Err(error) => {
+                commands
+                    .entity(entity)
+                    .insert(AudioFailed(describe(&error)));
+            }
+        }
+    }
+}
+
+fn play(
+    output: &mut AudioOutput,
+    source: &AudioSource,
+    emitter: Option<Emitter>,
+) -> Result<SoundHandle, AudioError> {
+    let channel = source.channel.clone();
+    let handle = match (&source.clip, emitter) {
+        (AudioClip::Loaded(name), Some(emitter)) => {
+            output.play_loaded_at(channel, name.clone(), emitter)?
+        }
+        (AudioClip::Loaded(name), None) => output.play_loaded_on(channel, name.clone())?,
+        (AudioClip::File(path), Some(emitter)) => {
+            output.play_from_file_at(channel, path, emitter)?
+        }
+        (AudioClip::File(path), None) => output.play_from_file_on(channel, path)?,
+        (AudioClip::Streamed(path), Some(emitter)) => {
+            output.play_source_at(channel, StreamingSource::open(path)?, emitter)
+        }
+        (AudioClip::Streamed(path), None) => output.play_streamed_on(channel, path)?,
+    };
+    Ok(handle)
+}
+
+fn apply(source: &AudioSource, handle: &SoundHandle) {
+    handle.set_volume(source.volume);
+    handle.set_speed(source.speed);
+    handle.set_looping(source.looping);
+    if source.paused {
+        handle.pause();
+    } else {
+        handle.resume();
+    }
+}
+
+pub fn update_audio_sources(sources: Query<(&AudioSource, &PlayingSound), Changed<AudioSource>>) {
+    for (source, playing) in &sources {
+        apply(source, &playing.0);
+    }
+}
+
+pub fn update_spatial_audio(
+    output: Option<ResMut<AudioOutput>>,
+    listeners: Query<&AudioListener, Changed<AudioListener>>,
+    emitters: Query<(&AudioEmitter, &PlayingSound), Changed<AudioEmitter>>,
+) {
+    let Some(mut output) = output else {
+        return;
+    };
+    let mut changed = false;
+    for (emitter, playing) in &emitters {
+        playing.0.set_emitter(Some(emitter.0));
+        changed = true;
+    }
+    match listeners.iter().next() {
+        //updates every sound anyway
+        Some(listener) => output.set_listener(listener.0),
+        None if changed => output.update_spatial(),
+        None => {}
+    }
+}
+
+pub fn finish_audio_sources(
+    mut commands: Commands,
+    sources: Query<(Entity, &AudioSource, &PlayingSound)>,
+) {
+    for (entity, source, playing) in &sources {
+        if source.despawn_when_done && playing.0.is_finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+//the AudioSource was removed but the entity lives on
+pub fn stop_removed_sources(
+    mut commands: Commands,
+    sounds: Query<Entity, (With<PlayingSound>, Without<AudioSource>)>,
+) {
+    for entity in &sounds {
+        commands.entity(entity).remove::<PlayingSound>();
+    }
+}
@@ -0,0 +1,470 @@
+use std::any::Any;
+use std::f32::consts::PI;
+
+//runs on the audio thread, so no allocations or locks in process()
+//samples are interleaved, `channels` samples make one frame
+pub trait Effect: Any + Send {
+    fn process(&mut self, samples: &mut [f32], channels: u16, sample_rate: u32);
+
+    //called off the audio thread before process() sees this format, allocate buffers here
+    fn prepare(&mut self, _channels: u16, _sample_rate: u32) {}
+
+    //forget the state (delay lines, filter memory), e.g. after the output changed
+    fn reset(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EffectId(pub(crate) u32);
+
+pub(crate) struct EffectSlot {
+    pub(crate) id: EffectId,
+    pub(crate) enabled: bool,
+    pub(crate) effect: Box<dyn Effect>,
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-9).log10()
+}
+
+//per channel memory of a biquad
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FilterKind {
+    LowPass,
+    HighPass,
+}
+
+//RBJ cookbook filter, shared by LowPass and HighPass
+#[derive(Debug, Clone)]
+struct Biquad {
+    kind: FilterKind,
+    //coefficients are only recalculated when these change
+    computed_for: Option<(f32, f32, u32)>,
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    states: Vec<BiquadState>,
+}
+
+impl Biquad {
+    fn new(kind: FilterKind) -> Self {
+        Self {
+            kind,
+            computed_for: None,
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            states: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, cutoff: f32, q: f32, sample_rate: u32) {
+        if self.computed_for == Some((cutoff, q, sample_rate)) {
+            return;
+        }
+        self.computed_for = Some((cutoff, q, sample_rate));
+
+        let cutoff = cutoff.clamp(10.0, sample_rate as f32 * 0.49);
+        let omega = 2.0 * PI * cutoff / sample_rate as f32;
+        let (sin, cos) = omega.sin_cos();
+        let alpha = sin / (2.0 * q.max(0.01));
+        let a0 = 1.0 + alpha;
+        let (b0, b1, b2) = match self.kind {
+            FilterKind::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0),
+            FilterKind::HighPass => ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0),
+        };
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = -2.0 * cos / a0;
+        self.a2 = (1.0 - alpha) / a0;
+    }
+
+    fn prepare(&mut self, channels: u16) {
+        self.states
+            .resize(channels.max(1) as usize, BiquadState::default());
+    }
+
+    //channels without a state (not prepared for them) pass through untouched
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        let channels = channels.max(1) as usize;
+        for frame in samples.chunks_mut(channels) {
+            for (sample, state) in frame.iter_mut().zip(self.states.iter_mut()) {
+                let x = *sample;
+                let y = self.b0 * x + self.b1 * state.x1 + self.b2 * state.x2
+                    - self.a1 * state.y1
+                    - self.a2 * state.y2;
+                state.x2 = state.x1;
+                state.x1 = x;
+                state.y2 = state.y1;
+                state.y1 = y;
+                *sample = y;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.states.fill(BiquadState::default());
+    }
+}
+
+//removes everything above the cutoff, e.g. "muffled" sound in the pause menu or under water
+pub struct LowPass {
+    pub cutoff: f32,
+    //0.707 = no resonance
+    pub q: f32,
+    filter: Biquad,
+}
+
+impl LowPass {
+    pub fn new(cutoff: f32) -> Self {
+        Self {
+            cutoff,
+            q: std::f32::consts::FRAC_1_SQRT_2,
+            filter: Biquad::new(FilterKind::LowPass),
+        }
+    }
+}
+
+impl Effect for LowPass {
+    fn process(&mut self, samples: &mut [f32], channels: u16, sample_rate: u32) {
+        self.filter.update(self.cutoff, self.q, sample_rate);
+        self.filter.process(samples, channels);
+    }
+
+    fn prepare(&mut self, channels: u16, _sample_rate: u32) {
+        self.filter.prepare(channels);
+    }
+
+    fn reset(&mut self) {
+        self.filter.reset();
+    }
+}
+
+//removes everything below the cutoff, e.g. radio/telephone voices
+pub struct HighPass {
+    pub cutoff: f32,
+    pub q: f32,
+    filter: Biquad,
+}
+
+impl HighPass {
+    pub fn new(cutoff: f32) -> Self {
+        Self {
+            cutoff,
+            q: std::f32::consts::FRAC_1_SQRT_2,
+            filter: Biquad::new(FilterKind::HighPass),
+        }
+    }
+}
+
+impl Effect for HighPass {
+    fn process(&mut self, samples: &mut [f32], channels: u16, sample_rate: u32) {
+        self.filter.update(self.cutoff, self.q, sample_rate);
+        self.filter.process(samples, channels);
+    }
+
+    fn prepare(&mut self, channels: u16, _sample_rate: u32) {
+        self.filter.prepare(channels);
+    }
+
+    fn reset(&mut self) {
+        self.filter.reset();
+    }
+}
+
+//echo
+pub struct Delay {
+    //seconds, at most max_time
+    pub time: f32,
+    //0..1, how much of the echo is fed back into the delay
+    pub feedback: f32,
+    //0 = dry only, 1 = wet only
+    pub mix: f32,
+    max_time: f32,
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl Delay {
+    pub fn new(time: f32, feedback: f32, mix: f32) -> Self {
+        Self::with_max_time(time, feedback, mix, time.max(2.0))
+    }
+
+    //the delay line is allocated for max_time, so time can be changed up to that without allocating
+    pub fn with_max_time(time: f32, feedback: f32, mix: f32, max_time: f32) -> Self {
+        Self {
+            time,
+            feedback,
+            mix,
+            max_time: max_time.max(0.001),
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+
+    fn max_frames(&self, sample_rate: u32) -> usize {
+        (self.max_time * sample_rate as f32) as usize + 1
+    }
+}
+
+impl Effect for Delay {
+    fn process(&mut self, samples: &mut [f32], channels: u16, sample_rate: u32) {
+        let channels = channels.max(1) as usize;
+        let max_frames = self.max_frames(sample_rate);
+        //not prepared for this format, stay dry instead of allocating here
+        if self.buffer.len() != max_frames * channels {
+            return;
+        }
+        let delay_frames = ((self.time.clamp(0.0, self.max_time) * sample_rate as f32) as usize)
+            .clamp(1, max_frames);
+        let feedback = self.feedback.clamp(0.0, 0.99);
+        let mix = self.mix.clamp(0.0, 1.0);
+
+        for frame in samples.chunks_mut(channels) {
+            let read = (self.position + max_frames - delay_frames) % max_frames;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let delayed = self.buffer[read * channels + channel];
+                self.buffer[self.position * channels + channel] = *sample + delayed * feedback;
+                *sample = *sample * (1.0 - mix) + delayed * mix;
+            }
+            self.position = (self.position + 1) % max_frames;
+        }
+    }
+
+    fn prepare(&mut self, channels: u16, sample_rate: u32) {
+        let len = self.max_frames(sample_rate) * channels.max(1) as usize;
+        if self.buffer.len() != len {
+            self.buffer = vec![0.0; len];
+            self.position = 0;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.position = 0;
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Comb {
+    buffer: Vec<f32>,
+    position: usize,
+    filter_store: f32,
+}
+
+impl Comb {
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.position];
+        self.filter_store = output * (1.0 - damping) + self.filter_store * damping;
+        self.buffer[self.position] = input + self.filter_store * feedback;
+        self.position = (self.position + 1) % self.buffer.len();
+        output
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AllPass {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl AllPass {
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.position];
+        self.buffer[self.position] = input + buffered * 0.5;
+        self.position = (self.position + 1) % self.buffer.len();
+        buffered - input
+    }
+}
+
+//freeverb tunings at 44.1kHz, scaled to the real sample rate
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALL_PASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+//every channel gets slightly different lengths so stereo sounds wide
+const STEREO_SPREAD: usize = 23;
+
+//a small freeverb, good enough for rooms and caves
+pub struct Reverb {
+    //0..1
+    pub room_size: f32,
+    //0..1, higher = darker tail
+    pub damping: f32,
+    pub mix: f32,
+    prepared_for: Option<(u16, u32)>,
+    combs: Vec<Vec<Comb>>,
+    all_passes: Vec<Vec<AllPass>>,
+}
+
+impl Reverb {
+    pub fn new(room_size: f32, damping: f32, mix: f32) -> Self {
+        Self {
+            room_size,
+            damping,
+            mix,
+            prepared_for: None,
+            combs: Vec::new(),
+            all_passes: Vec::new(),
+        }
+    }
+}
+
+impl Effect for Reverb {
+    fn process(&mut self, samples: &mut [f32], channels: u16, sample_rate: u32) {
+        let channels = channels.max(1);
+        if self.prepared_for != Some((channels, sample_rate)) {
+            return;
+        }
+        let feedback = 0.7 + self.room_size.clamp(0.0, 1.0) * 0.28;
+        let damping = self.damping.clamp(0.0, 1.0) * 0.4;
+        let mix = self.mix.clamp(0.0, 1.0);
+
+        for frame in samples.chunks_mut(channels as usize) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                //the input gain of freeverb, keeps the sum of the combs in range
+                let input = *sample * 0.015;
+                let mut wet = 0.0;
+                for comb in &mut self.combs[channel] {
+                    wet += comb.process(input, feedback, damping);
+                }
+                for all_pass in &mut self.all_passes[channel] {
+                    wet = all_pass.process(wet);
+                }
+                *sample = *sample * (1.0 - mix) + wet * mix * 3.0;
+            }
+        }
+    }
+
+    fn prepare(&mut self, channels: u16, sample_rate: u32) {
+        let channels = channels.max(1);
+        if self.prepared_for == Some((channels, sample_rate)) {
+            return;
+        }
+        self.prepared_for = Some((channels, sample_rate));
+        let scale = sample_rate as f32 / 44100.0;
+        let length = |tuning: usize, channel: usize| {
+            (((tuning + channel * STEREO_SPREAD) as f32 * scale) as usize).max(1)
+        };
+        self.combs = (0..channels as usize)
+            .map(|channel| {
+                COMB_TUNINGS
+                    .iter()
+                    .map(|&tuning| Comb {
+                        buffer: vec![0.0; length(tuning, channel)],
+                        position: 0,
+                        filter_store: 0.0,
+                    })
+                    .collect()
+            })
+            .collect();
+        self.all_passes = (0..channels as usize)
+            .map(|channel| {
+                ALL_PASS_TUNINGS
+                    .iter()
+                    .map(|&tuning| AllPass {
+                        buffer: vec![0.0; length(tuning, channel)],
+                        position: 0,
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+
+    fn reset(&mut self) {
+        for comb in self.combs.iter_mut().flatten() {
+            comb.buffer.fill(0.0);
+            comb.position = 0;
+            comb.filter_store = 0.0;
+        }
+        for all_pass in self.all_passes.iter_mut().flatten() {
+            all_pass.buffer.fill(0.0);
+            all_pass.position = 0;
+        }
+    }
+}
+
+//turns loud parts down, a limiter is a compressor with a huge ratio and a fast attack
+pub struct Compressor {
+    pub threshold_db: f32,
+    //4.0 = 4 dB over the threshold become 1 dB
+    pub ratio: f32,
+    //seconds
+    pub attack: f32,
+    pub release: f32,
+    pub makeup_db: f32,
+    envelope: f32,
+}
+
+impl Compressor {
+    pub fn new(threshold_db: f32, ratio: f32) -> Self {
+        Self {
+            threshold_db,
+            ratio,
+            attack: 0.01,
+            release: 0.1,
+            makeup_db: 0.0,
+            envelope: 0.0,
+        }
+    }
+
+    //keeps the master from clipping
+    pub fn limiter(threshold_db: f32) -> Self {
+        Self {
+            attack: 0.001,
+            release: 0.05,
+            ..Self::new(threshold_db, 100.0)
+        }
+    }
+}
+
+impl Effect for Compressor {
+    fn process(&mut self, samples: &mut [f32], channels: u16, sample_rate: u32) {
+        let coefficient = |time: f32| (-1.0 / (time.max(0.0001) * sample_rate as f32)).exp();
+        let attack = coefficient(self.attack);
+        let release = coefficient(self.release);
+        let ratio = self.ratio.max(1.0);
+        let makeup = db_to_gain(self.makeup_db);
+
+        //all channels get the same gain, otherwise the stereo image moves around
+        for frame in samples.chunks_mut(channels.max(1) as usize) {
+            let peak = frame
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            let coefficient = if peak > self.envelope {
+                attack
+            } else {
+                release
+            };
+            self.envelope = coefficient * self.envelope + (1.0 - coefficient) * peak;
+
+            let over = gain_to_db(self.envelope) - self.threshold_db;
+            let reduction = if over > 0.0 {
+                db_to_gain(-over * (1.0 - 1.0 / ratio))
+            } else {
+                1.0
+            };
+            for sample in frame {
+                *sample *= reduction * makeup;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+}
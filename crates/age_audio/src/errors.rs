@@ -1,10 +1,133 @@
 //use map_err here
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum AudioError {
-    IoError(std::io::Error),
-    DecoderError(rodio::decoder::DecoderError),
+    IoError(PathBuf, std::io::Error),
+    DecoderError(PathBuf, rodio::decoder::DecoderError),
     OutputStreamBuilderError(rodio::StreamError),
     StreamError(rodio::StreamError),
     FileNotLoaded(String),
+    SeekError(rodio::source::SeekError),
+    ChannelAlreadyExists(crate::channel::Channel),
+    ChannelNotFound(crate::channel::Channel),
+    EffectNotFound(crate::effects::EffectId),
+    WavError(hound::Error),
+    NotOffline,
+    DevicesError(rodio::DevicesError),
+    DeviceNotFound(String),
+    //name of the lost device and why the default device didn't work either
+    DeviceMigrationFailed(String, Box<AudioError>),
+    NoInputDevice,
+    InputConfigError(rodio::cpal::DefaultStreamConfigError),
+    BuildInputStreamError(rodio::cpal::BuildStreamError),
+    PlayInputStreamError(rodio::cpal::PlayStreamError),
+    PauseInputStreamError(rodio::cpal::PauseStreamError),
+    UnsupportedSampleFormat,
+    TrackNotFound(usize),
+    EmptyTrack,
+    //the stem that differs from the first one in channels or sample rate
+    LayerFormatMismatch(PathBuf),
+    SpawnThreadError(std::io::Error),
+}
+
+impl Error for AudioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AudioError::IoError(_, err) => Some(err),
+            AudioError::DecoderError(_, err) => Some(err),
+            AudioError::OutputStreamBuilderError(err) => Some(err),
+            AudioError::StreamError(err) => Some(err),
+            AudioError::SeekError(err) => Some(err),
+            AudioError::WavError(err) => Some(err),
+            AudioError::DevicesError(err) => Some(err),
+            AudioError::DeviceMigrationFailed(_, err) => Some(err.as_ref()),
+            AudioError::InputConfigError(err) => Some(err),
+            AudioError::BuildInputStreamError(err) => Some(err),
+            AudioError::PlayInputStreamError(err) => Some(err),
+            AudioError::PauseInputStreamError(err) => Some(err),
+            AudioError::SpawnThreadError(err) => Some(err),
+            AudioError::FileNotLoaded(_)
+            | AudioError::ChannelAlreadyExists(_)
+            | AudioError::ChannelNotFound(_)
+            | AudioError::EffectNotFound(_)
+            | AudioError::NotOffline
+            | AudioError::DeviceNotFound(_)
+            | AudioError::NoInputDevice
+            | AudioError::UnsupportedSampleFormat
+            | AudioError::TrackNotFound(_)
+            | AudioError::EmptyTrack
+            | AudioError::LayerFormatMismatch(_) => None,
+        }
+    }
+}
+
+impl Display for AudioError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AudioError::IoError(path, _) => {
+                write!(f, "couldn't read sound file {}", path.display())
+            }
+            AudioError::DecoderError(path, _) => {
+                write!(f, "couldn't decode sound {}", path.display())
+            }
+            AudioError::OutputStreamBuilderError(_) => {
+                write!(f, "couldn't open the output device")
+            }
+            AudioError::StreamError(_) => write!(f, "couldn't open the output stream"),
+            AudioError::FileNotLoaded(name) => write!(f, "no sound loaded as {name:?}"),
+            AudioError::SeekError(_) => write!(f, "couldn't seek"),
+            AudioError::ChannelAlreadyExists(channel) => {
+                write!(f, "channel {channel:?} already exists")
+            }
+            AudioError::ChannelNotFound(channel) => write!(f, "no channel {channel:?}"),
+            AudioError::EffectNotFound(id) => write!(f, "no effect {id:?} on the channel"),
+            AudioError::WavError(_) => write!(f, "couldn't write WAV file"),
+            AudioError::NotOffline => write!(f, "only offline outputs can be rendered manually"),
+            AudioError::DevicesError(_) => write!(f, "couldn't list audio devices"),
+            AudioError::DeviceNotFound(name) => write!(f, "no audio device called {name:?}"),
+            AudioError::DeviceMigrationFailed(name, _) => write!(
+                f,
+                "lost output device {name:?} and couldn't switch to the default one"
+            ),
+            AudioError::NoInputDevice => write!(f, "no input device available"),
+            AudioError::InputConfigError(_) => {
+                write!(f, "couldn't get the input device's config")
+            }
+            AudioError::BuildInputStreamError(_) => {
+                write!(f, "couldn't open the input stream")
+            }
+            AudioError::PlayInputStreamError(_) => {
+                write!(f, "couldn't start the input stream")
+            }
+            AudioError::PauseInputStreamError(_) => {
+                write!(f, "couldn't pause the input stream")
+            }
+            AudioError::UnsupportedSampleFormat => {
+                write!(f, "the input device's sample format isn't supported")
+            }
+            AudioError::TrackNotFound(index) => write!(f, "no track {index} in the playlist"),
+            AudioError::EmptyTrack => write!(f, "track has no layers"),
+            AudioError::LayerFormatMismatch(path) => write!(
+                f,
+                "layer {} differs from the track's first layer in channels or sample rate",
+                path.display()
+            ),
+            AudioError::SpawnThreadError(_) => write!(f, "couldn't start the streaming thread"),
+        }
+    }
+}
+
+//the error and everything it was caused by on one line, for logs and string-only errors
+pub fn describe(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+    message
 }
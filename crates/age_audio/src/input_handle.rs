@@ -0,0 +1,409 @@
+use super::device;
+use super::errors::AudioError;
+use super::input_handle::input_markers::{InputDisabled, InputEnabled};
+use super::traits::marker::InputHandlerState;
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use rodio::cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rodio::cpal::{self, FromSample, SampleFormat, SizedSample};
+use std::fs::File;
+use std::io::BufWriter;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub mod input_markers {
+    pub struct InputDisabled;
+    pub struct InputEnabled;
+}
+
+//where the samples come from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum InputSource {
+    #[default]
+    DefaultDevice,
+    //names come from device::list_input_devices
+    Device(String),
+    //what an output device plays, None = default output
+    //only works where the platform supports it (WASAPI on Windows), the others give a BuildInputStreamError
+    Loopback(Option<String>),
+}
+
+pub type InputCallback = Box<dyn FnMut(&[f32]) + Send>;
+
+//peak and rms of the last block the device delivered, for level meters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputLevel {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+//everything the capture thread writes to
+struct InputShared {
+    //f32 bits
+    peak: AtomicU32,
+    rms: AtomicU32,
+    //samples thrown away because nobody read the ring buffer
+    overflowed: AtomicUsize,
+    //set by the stream's error callback when the device is gone
+    lost: AtomicBool,
+    recording: Mutex<Option<hound::WavWriter<BufWriter<File>>>>,
+    callback: Mutex<Option<InputCallback>>,
+}
+
+impl InputShared {
+    //runs on the capture thread for every block
+    fn process(&self, samples: &[f32], producer: &mut HeapProd<f32>) {
+        let pushed = producer.push_slice(samples);
+        if pushed < samples.len() {
+            self.overflowed
+                .fetch_add(samples.len() - pushed, Ordering::Relaxed);
+        }
+
+        let peak = samples
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let rms = if samples.is_empty() {
+            0.0
+        } else {
+            (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32)
+                .sqrt()
+        };
+        self.peak.store(peak.to_bits(), Ordering::Relaxed);
+        self.rms.store(rms.to_bits(), Ordering::Relaxed);
+
+        //try_lock: never wait for the game thread in here, it's only busy while starting/stopping
+        if let Ok(mut recording) = self.recording.try_lock()
+            && let Some(writer) = recording.as_mut()
+        {
+            for &sample in samples {
+                //a failed write shows up as a broken file, nothing to report it to from here
+                let _ = writer.write_sample(sample);
+            }
+        }
+        if let Ok(mut callback) = self.callback.try_lock()
+            && let Some(callback) = callback.as_mut()
+        {
+            callback(samples);
+        }
+    }
+}
+
+pub struct InputHandle<I> {
+    stream: Option<cpal::Stream>,
+    consumer: Option<HeapCons<f32>>,
+    shared: Arc<InputShared>,
+    //only known while enabled
+    channels: u16,
+    sample_rate: u32,
+    device_name: String,
+    //size of the ring buffer
+    buffer_duration: Duration,
+    _marker: PhantomData<I>,
+}
+
+impl Default for InputHandle<InputDisabled> {
+    fn default() -> InputHandle<InputDisabled> {
+        InputHandle {
+            stream: None,
+            consumer: None,
+            shared: Arc::new(InputShared {
+                peak: AtomicU32::new(0),
+                rms: AtomicU32::new(0),
+                overflowed: AtomicUsize::new(0),
+                lost: AtomicBool::new(false),
+                recording: Mutex::new(None),
+                callback: Mutex::new(None),
+            }),
+            channels: 0,
+            sample_rate: 0,
+            device_name: String::new(),
+            buffer_duration: Duration::from_secs(2),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I: InputHandlerState> InputHandle<I> {
+    //gets every block right on the capture thread, keep it short (no locks, no allocations)
+    //voice chat encoders etc. should go here instead of polling read()
+    pub fn set_callback<F: FnMut(&[f32]) + Send + 'static>(&mut self, callback: F) {
+        *self
+            .shared
+            .callback
+            .lock()
+            .expect("Only panics if poisoned") = Some(Box::new(callback));
+    }
+
+    pub fn clear_callback(&mut self) {
+        *self
+            .shared
+            .callback
+            .lock()
+            .expect("Only panics if poisoned") = None;
+    }
+
+    pub fn buffer_duration(&self) -> Duration {
+        self.buffer_duration
+    }
+}
+
+impl InputHandle<InputDisabled> {
+    pub fn new() -> InputHandle<InputDisabled> {
+        Default::default()
+    }
+
+    //how much the ring buffer can hold before the oldest samples are lost, used on activation
+    pub fn set_buffer_duration(&mut self, duration: Duration) {
+        self.buffer_duration = duration;
+    }
+
+    pub fn activate_input(self) -> Result<InputHandle<InputEnabled>, AudioError> {
+        self.activate_with(InputSource::DefaultDevice)
+    }
+
+    pub fn activate_with(
+        self,
+        source: InputSource,
+    ) -> Result<InputHandle<InputEnabled>, AudioError> {
+        let (device, config) = match &source {
+            InputSource::DefaultDevice => {
+                let device = cpal::default_host()
+                    .default_input_device()
+                    .ok_or(AudioError::NoInputDevice)?;
+                let config = device
+                    .default_input_config()
+                    .map_err(AudioError::InputConfigError)?;
+                (device, config)
+            }
+            InputSource::Device(name) => {
+                let device = device::find_input_device(name)?;
+                let config = device
+                    .default_input_config()
+                    .map_err(AudioError::InputConfigError)?;
+                (device, config)
+            }
+            //an output device opened as input
+            InputSource::Loopback(name) => {
+                let device = match name {
+                    Some(name) => device::find_output_device(name)?,
+                    None => device::default_output_device()?,
+                };
+                let config = device
+                    .default_output_config()
+                    .map_err(AudioError::InputConfigError)?;
+                (device, config)
+            }
+        };
+        let device_name = device.name().unwrap_or_default();
+        let channels = config.channels();
+        let sample_rate = config.sample_rate().0;
+
+        let capacity =
+            (self.buffer_duration.as_secs_f32() * sample_rate as f32) as usize * channels as usize;
+        let (producer, consumer) = HeapRb::<f32>::new(capacity.max(1)).split();
+
+        let stream_config = config.config();
+        self.shared.lost.store(false, Ordering::Relaxed);
+        let shared = self.shared.clone();
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, shared, producer),
+            SampleFormat::F64 => build_stream::<f64>(&device, &stream_config, shared, producer),
+            SampleFormat::I8 => build_stream::<i8>(&device, &stream_config, shared, producer),
+            SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, shared, producer),
+            SampleFormat::I32 => build_stream::<i32>(&device, &stream_config, shared, producer),
+            SampleFormat::U8 => build_stream::<u8>(&device, &stream_config, shared, producer),
+            SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, shared, producer),
+            SampleFormat::U32 => build_stream::<u32>(&device, &stream_config, shared, producer),
+            _ => return Err(AudioError::UnsupportedSampleFormat),
+        }?;
+        stream.play().map_err(AudioError::PlayInputStreamError)?;
+
+        Ok(InputHandle::<InputEnabled> {
+            stream: Some(stream),
+            consumer: Some(consumer),
+            shared: self.shared,
+            channels,
+            sample_rate,
+            device_name,
+            buffer_duration: self.buffer_duration,
+            _marker: PhantomData,
+        })
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    shared: Arc<InputShared>,
+    mut producer: HeapProd<f32>,
+) -> Result<cpal::Stream, AudioError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    //reused, the callback shouldn't allocate
+    let mut converted = Vec::new();
+    let error_shared = shared.clone();
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                converted.clear();
+                converted.extend(data.iter().map(|sample| sample.to_sample::<f32>()));
+                shared.process(&converted, &mut producer);
+            },
+            move |error| match error {
+                cpal::StreamError::DeviceNotAvailable => {
+                    error_shared.lost.store(true, Ordering::Relaxed)
+                }
+                error => log::error!("audio input stream error: {error}"),
+            },
+            None,
+        )
+        .map_err(AudioError::BuildInputStreamError)
+}
+
+impl InputHandle<InputEnabled> {
+    //a running recording is finished
+    pub fn disable_input(mut self) -> InputHandle<InputDisabled> {
+        //stop the capture thread before touching the recording
+        self.stream = None;
+        let _ = self.stop_recording_inner();
+        InputHandle::<InputDisabled> {
+            stream: None,
+            consumer: None,
+            shared: self.shared,
+            channels: 0,
+            sample_rate: 0,
+            device_name: String::new(),
+            buffer_duration: self.buffer_duration,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    pub fn channel_count(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    //the capture device disappeared (unplugged etc.), nothing new arrives in read()
+    //disable and activate again to pick another device
+    pub fn is_device_lost(&self) -> bool {
+        self.shared.lost.load(Ordering::Relaxed)
+    }
+
+    pub fn pause(&mut self) -> Result<(), AudioError> {
+        self.stream
+            .as_ref()
+            .expect("Basically never 'None' if input is enabled")
+            .pause()
+            .map_err(AudioError::PauseInputStreamError)
+    }
+
+    pub fn play(&mut self) -> Result<(), AudioError> {
+        self.stream
+            .as_ref()
+            .expect("Basically never 'None' if input is enabled")
+            .play()
+            .map_err(AudioError::PlayInputStreamError)
+    }
+
+    //samples waiting in the ring buffer, interleaved
+    pub fn available(&self) -> usize {
+        self.consumer
+            .as_ref()
+            .expect("Basically never 'None' if input is enabled")
+            .occupied_len()
+    }
+
+    //fills as much of `samples` as there is, returns how much that was
+    pub fn read(&mut self, samples: &mut [f32]) -> usize {
+        self.consumer
+            .as_mut()
+            .expect("Basically never 'None' if input is enabled")
+            .pop_slice(samples)
+    }
+
+    pub fn read_all(&mut self) -> Vec<f32> {
+        let mut result = vec![0.0; self.available()];
+        let read = self.read(&mut result);
+        result.truncate(read);
+        result
+    }
+
+    //throws away what was captured so far, e.g. when push to talk starts
+    pub fn clear(&mut self) {
+        self.consumer
+            .as_mut()
+            .expect("Basically never 'None' if input is enabled")
+            .clear();
+    }
+
+    //samples lost since the last call because the ring buffer was full
+    pub fn take_overflowed(&self) -> usize {
+        self.shared.overflowed.swap(0, Ordering::Relaxed)
+    }
+
+    pub fn level(&self) -> InputLevel {
+        InputLevel {
+            peak: f32::from_bits(self.shared.peak.load(Ordering::Relaxed)),
+            rms: f32::from_bits(self.shared.rms.load(Ordering::Relaxed)),
+        }
+    }
+
+    //32 bit float WAV, independent of the ring buffer, so read() still works while recording
+    pub fn start_recording<P: AsRef<Path>>(&mut self, path: P) -> Result<(), AudioError> {
+        let spec = hound::WavSpec {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let writer = hound::WavWriter::create(path, spec).map_err(AudioError::WavError)?;
+        let previous = self
+            .shared
+            .recording
+            .lock()
+            .expect("Only panics if poisoned")
+            .replace(writer);
+        if let Some(previous) = previous {
+            previous.finalize().map_err(AudioError::WavError)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.shared
+            .recording
+            .lock()
+            .expect("Only panics if poisoned")
+            .is_some()
+    }
+
+    pub fn stop_recording(&mut self) -> Result<(), AudioError> {
+        self.stop_recording_inner()
+    }
+
+    fn stop_recording_inner(&self) -> Result<(), AudioError> {
+        let writer = self
+            .shared
+            .recording
+            .lock()
+            .expect("Only panics if poisoned")
+            .take();
+        match writer {
+            Some(writer) => writer.finalize().map_err(AudioError::WavError),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,6 +1,19 @@
-//TODO: make audio ECS-ready
-
 //maybe not everything needs to be pub in the end, idk
+mod bus;
+pub mod channel;
+pub mod device;
+#[cfg(feature = "ecs")]
+pub mod ecs;
+pub mod effects;
 pub mod errors;
+pub mod input_handle;
+pub mod music;
+pub mod output;
 pub mod output_handle;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod sound;
+pub mod source;
+pub mod spatial;
+pub mod synth;
 pub mod traits;
@@ -0,0 +1,549 @@
+use super::channel::Channel;
+use super::errors::AudioError;
+use super::output_handle::OutputHandle;
+use super::output_handle::output_markers::OutputEnabled;
+use super::sound::SoundHandle;
+use super::source::{LoopPoints, StreamingSource};
+use rodio::Source;
+use rodio::source::SeekError;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+//how often (in frames) the layer volumes are read
+const LAYER_UPDATE_INTERVAL: u32 = 64;
+
+//one stem of a track, all stems of a track play in sync
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub path: PathBuf,
+    //intensity where the layer starts to fade in
+    pub fade_in_at: f32,
+    //intensity where it's at full volume
+    pub full_at: f32,
+}
+
+impl Layer {
+    //always at full volume
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            fade_in_at: f32::NEG_INFINITY,
+            full_at: f32::NEG_INFINITY,
+        }
+    }
+
+    //e.g. drums from 0.3 to 0.5, fully there above 0.5
+    pub fn with_intensity(mut self, fade_in_at: f32, full_at: f32) -> Self {
+        self.fade_in_at = fade_in_at;
+        self.full_at = full_at;
+        self
+    }
+
+    fn gain(&self, intensity: f32) -> f32 {
+        if self.full_at <= self.fade_in_at {
+            return if intensity >= self.fade_in_at {
+                1.0
+            } else {
+                0.0
+            };
+        }
+        ((intensity - self.fade_in_at) / (self.full_at - self.fade_in_at)).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    //at least one, stems need the same channels and sample rate
+    pub layers: Vec<Layer>,
+    //None = played once, looped tracks only end with next()/stop()
+    pub loop_points: Option<LoopPoints>,
+}
+
+impl Track {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self::layered(vec![Layer::new(path)])
+    }
+
+    pub fn layered(layers: Vec<Layer>) -> Self {
+        Self {
+            layers,
+            loop_points: None,
+        }
+    }
+
+    pub fn with_loop(mut self, loop_points: LoopPoints) -> Self {
+        self.loop_points = Some(loop_points);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Repeat {
+    //stops after the last track
+    #[default]
+    Off,
+    All,
+    One,
+}
+
+//plays the stems of a track as one sound, so they can't drift apart
+struct LayeredSource {
+    layers: Vec<StreamingSource>,
+    gains: Vec<f32>,
+    //f32 bits, written by the MusicPlayer
+    targets: Arc<[AtomicU32]>,
+    //per frame
+    gain_step: f32,
+    channels: u16,
+    sample_rate: u32,
+    frame_position: u16,
+    frames_until_update: u32,
+}
+
+impl LayeredSource {
+    fn open(track: &Track, intensity: f32, layer_fade: Duration) -> Result<Self, AudioError> {
+        if track.layers.is_empty() {
+            return Err(AudioError::EmptyTrack);
+        }
+        let mut layers = Vec::with_capacity(track.layers.len());
+        for layer in &track.layers {
+            let source = StreamingSource::open_with_loop(&layer.path, track.loop_points)?;
+            if let Some(first) = layers.first()
+                && !same_format(first, &source)
+            {
+                return Err(AudioError::LayerFormatMismatch(layer.path.clone()));
+            }
+            layers.push(source);
+        }
+        let channels = layers[0].channels();
+        let sample_rate = layers[0].sample_rate();
+        let gains: Vec<f32> = track
+            .layers
+            .iter()
+            .map(|layer| layer.gain(intensity))
+            .collect();
+        Ok(Self {
+            targets: gains
+                .iter()
+                .map(|gain| AtomicU32::new(gain.to_bits()))
+                .collect(),
+            gains,
+            layers,
+            gain_step: 1.0 / (layer_fade.as_secs_f32() * sample_rate as f32).max(1.0),
+            channels,
+            sample_rate,
+            frame_position: 0,
+            frames_until_update: 0,
+        })
+    }
+
+    fn update_gains(&mut self) {
+        for (gain, target) in self.gains.iter_mut().zip(self.targets.iter()) {
+            let target = f32::from_bits(target.load(Ordering::Relaxed));
+            let step = self.gain_step * LAYER_UPDATE_INTERVAL as f32;
+            *gain = if *gain < target {
+                (*gain + step).min(target)
+            } else {
+                (*gain - step).max(target)
+            };
+        }
+    }
+}
+
+fn same_format(a: &StreamingSource, b: &StreamingSource) -> bool {
+    a.channels() == b.channels() && a.sample_rate() == b.sample_rate()
+}
+
+impl Iterator for LayeredSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_position == 0 {
+            if self.frames_until_update == 0 {
+                self.update_gains();
+                self.frames_until_update = LAYER_UPDATE_INTERVAL;
+            }
+            self.frames_until_update -= 1;
+        }
+
+        //silent layers are still pulled, otherwise they'd fall behind
+        let mut sum = 0.0;
+        let mut any = false;
+        for (layer, gain) in self.layers.iter_mut().zip(&self.gains) {
+            if let Some(sample) = layer.next() {
+                sum += sample * gain;
+                any = true;
+            }
+        }
+        if !any {
+            return None;
+        }
+        self.frame_position = (self.frame_position + 1) % self.channels;
+        Some(sum)
+    }
+}
+
+impl Source for LayeredSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.layers
+            .iter()
+            .map(|layer| layer.total_duration())
+            .max()
+            .flatten()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        for layer in &mut self.layers {
+            layer.try_seek(pos)?;
+        }
+        self.frame_position = 0;
+        Ok(())
+    }
+}
+
+struct CurrentTrack {
+    //index into tracks, None once set_playlist replaced the playlist it came from
+    index: Option<usize>,
+    layers: Vec<Layer>,
+    sound: SoundHandle,
+    //None for looped tracks
+    duration: Option<Duration>,
+    targets: Arc<[AtomicU32]>,
+}
+
+//playlists with crossfades, looped sections and intensity driven stems
+//call update() every frame, that's where the next track is started
+pub struct MusicPlayer {
+    channel: Channel,
+    tracks: Vec<Track>,
+    //indices into tracks, shuffled or not
+    order: Vec<usize>,
+    //index into order
+    position: usize,
+    current: Option<CurrentTrack>,
+    crossfade: Duration,
+    repeat: Repeat,
+    shuffle: bool,
+    intensity: f32,
+    layer_fade: Duration,
+    rng: u64,
+}
+
+impl Default for MusicPlayer {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+        Self {
+            channel: Channel::Music,
+            tracks: Vec::new(),
+            order: Vec::new(),
+            position: 0,
+            current: None,
+            crossfade: Duration::from_secs(2),
+            repeat: Repeat::Off,
+            shuffle: false,
+            intensity: 0.0,
+            layer_fade: Duration::from_secs(1),
+            //xorshift breaks on 0
+            rng: seed | 1,
+        }
+    }
+}
+
+impl MusicPlayer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    //doesn't touch what's playing, if the new playlist has the same track it goes on from there
+    //otherwise the new playlist starts from the beginning once the current track ends
+    pub fn set_playlist(&mut self, tracks: Vec<Track>) {
+        self.tracks = tracks;
+        self.position = 0;
+        self.rebuild_order();
+        if let Some(current) = &mut self.current {
+            current.index = self
+                .tracks
+                .iter()
+                .position(|track| track.layers == current.layers);
+            if let Some(index) = current.index
+                && let Some(position) = self.order.iter().position(|&track| track == index)
+            {
+                //shuffled, so the rest of the new playlist comes after it
+                if self.shuffle {
+                    self.order.swap(0, position);
+                } else {
+                    self.position = position;
+                }
+            }
+        }
+    }
+
+    //shuffled playlists get it somewhere after the current track
+    pub fn add_track(&mut self, track: Track) {
+        self.tracks.push(track);
+        let index = self.tracks.len() - 1;
+        if !self.shuffle {
+            self.order.push(index);
+            return;
+        }
+        let first = if self.current.is_some() {
+            (self.position + 1).min(self.order.len())
+        } else {
+            0
+        };
+        let position =
+            first + (self.next_random() % (self.order.len() - first + 1) as u64) as usize;
+        self.order.insert(position, index);
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    //index into tracks()
+    //None while a track from a replaced playlist plays out
+    pub fn current_track(&self) -> Option<usize> {
+        self.current.as_ref().and_then(|current| current.index)
+    }
+
+    pub fn current_sound(&self) -> Option<&SoundHandle> {
+        self.current.as_ref().map(|current| &current.sound)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|current| !current.sound.is_finished())
+    }
+
+    //zero = hard cuts
+    pub fn set_crossfade(&mut self, crossfade: Duration) {
+        self.crossfade = crossfade;
+    }
+
+    pub fn crossfade(&self) -> Duration {
+        self.crossfade
+    }
+
+    pub fn set_repeat(&mut self, repeat: Repeat) {
+        self.repeat = repeat;
+    }
+
+    pub fn repeat(&self) -> Repeat {
+        self.repeat
+    }
+
+    //the current track keeps playing, it's moved to the front of the new order
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle = shuffle;
+        let current = self.order.get(self.position).copied();
+        self.rebuild_order();
+        if let Some(current) = current
+            && let Some(position) = self.order.iter().position(|&index| index == current)
+        {
+            self.order.swap(0, position);
+            self.position = 0;
+        }
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle
+    }
+
+    //usually 0.0 (calm) to 1.0 (combat), the layers fade to their new volume over layer_fade
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity;
+        if let Some(current) = &self.current {
+            for (layer, target) in current.layers.iter().zip(current.targets.iter()) {
+                target.store(layer.gain(intensity).to_bits(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    //used for tracks started after this
+    pub fn set_layer_fade(&mut self, layer_fade: Duration) {
+        self.layer_fade = layer_fade;
+    }
+
+    //starts the playlist from the beginning
+    pub fn play(&mut self, output: &mut OutputHandle<OutputEnabled>) -> Result<(), AudioError> {
+        self.start(output, 0)
+    }
+
+    //index into tracks()
+    pub fn play_track(
+        &mut self,
+        output: &mut OutputHandle<OutputEnabled>,
+        index: usize,
+    ) -> Result<(), AudioError> {
+        let Some(position) = self.order.iter().position(|&track| track == index) else {
+            return Err(AudioError::TrackNotFound(index));
+        };
+        self.start(output, position)
+    }
+
+    //crossfades even when the current track has a loop
+    pub fn next(&mut self, output: &mut OutputHandle<OutputEnabled>) -> Result<(), AudioError> {
+        match self.next_position(true) {
+            Some(position) => self.start(output, position),
+            None => {
+                self.stop();
+                Ok(())
+            }
+        }
+    }
+
+    pub fn previous(&mut self, output: &mut OutputHandle<OutputEnabled>) -> Result<(), AudioError> {
+        let position = match self.position.checked_sub(1) {
+            Some(position) => position,
+            None if self.repeat == Repeat::All => self.order.len().saturating_sub(1),
+            None => 0,
+        };
+        self.start(output, position)
+    }
+
+    //fades out over the crossfade time
+    pub fn stop(&mut self) {
+        if let Some(current) = self.current.take() {
+            fade_out(&current.sound, self.crossfade);
+        }
+    }
+
+    //starts the next track when the current one is about to end
+    pub fn update(&mut self, output: &mut OutputHandle<OutputEnabled>) -> Result<(), AudioError> {
+        let Some(current) = &self.current else {
+            return Ok(());
+        };
+        let ending = match current.duration {
+            Some(duration) => current.sound.position() + self.crossfade >= duration,
+            None => false,
+        };
+        if !ending && !current.sound.is_finished() {
+            return Ok(());
+        }
+        match self.next_position(false) {
+            Some(position) => self.start(output, position),
+            None => {
+                //let it play out
+                self.current = None;
+                Ok(())
+            }
+        }
+    }
+
+    fn next_position(&mut self, skip: bool) -> Option<usize> {
+        //the playlist was replaced, it starts from the beginning
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|current| current.index.is_none())
+        {
+            return (!self.order.is_empty()).then_some(0);
+        }
+        if self.repeat == Repeat::One && !skip {
+            return Some(self.position);
+        }
+        if self.position + 1 < self.order.len() {
+            return Some(self.position + 1);
+        }
+        match self.repeat {
+            Repeat::Off => None,
+            Repeat::All | Repeat::One => {
+                //a new order every time around
+                if self.shuffle {
+                    self.rebuild_order();
+                }
+                (!self.order.is_empty()).then_some(0)
+            }
+        }
+    }
+
+    fn start(
+        &mut self,
+        output: &mut OutputHandle<OutputEnabled>,
+        position: usize,
+    ) -> Result<(), AudioError> {
+        let Some(&index) = self.order.get(position) else {
+            return Err(AudioError::TrackNotFound(position));
+        };
+        let source = LayeredSource::open(&self.tracks[index], self.intensity, self.layer_fade)?;
+        let duration = source.total_duration();
+        let targets = source.targets.clone();
+        let previous = self.current.take();
+        let sound = match &previous {
+            Some(_) if !self.crossfade.is_zero() => {
+                output.play_source_faded_in_on(self.channel.clone(), source, self.crossfade)
+            }
+            _ => output.play_source_on(self.channel.clone(), source),
+        };
+        if let Some(previous) = previous {
+            fade_out(&previous.sound, self.crossfade);
+        }
+        self.position = position;
+        self.current = Some(CurrentTrack {
+            index: Some(index),
+            layers: self.tracks[index].layers.clone(),
+            sound,
+            duration,
+            targets,
+        });
+        Ok(())
+    }
+
+    fn rebuild_order(&mut self) {
+        self.order = (0..self.tracks.len()).collect();
+        if self.shuffle {
+            //fisher-yates
+            for i in (1..self.order.len()).rev() {
+                let j = (self.next_random() % (i as u64 + 1)) as usize;
+                self.order.swap(i, j);
+            }
+        }
+    }
+
+    //xorshift, good enough for shuffling songs
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+}
+
+fn fade_out(sound: &SoundHandle, duration: Duration) {
+    if duration.is_zero() {
+        sound.stop();
+    } else {
+        sound.fade_out(duration);
+    }
+}
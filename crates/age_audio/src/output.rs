@@ -0,0 +1,310 @@
+use super::device;
+use super::errors::AudioError;
+use rodio::mixer::{self, Mixer, MixerSource};
+use rodio::source::Zero;
+use rodio::{OutputStream, OutputStreamBuilder, Source, cpal};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+//samples the device tap takes from the root mixer at once
+const TAP_BLOCK: usize = 512;
+
+//None = whatever the device prefers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceConfig {
+    //None = default device, names come from device::list_output_devices
+    pub name: Option<String>,
+    pub sample_rate: Option<u32>,
+    //in frames
+    pub buffer_size: Option<u32>,
+}
+
+impl DeviceConfig {
+    pub fn named<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Default::default()
+        }
+    }
+}
+
+//where an enabled OutputHandle sends its sound
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputBackend {
+    DefaultDevice,
+    Device(DeviceConfig),
+    //nothing is played, OutputHandle::render mixes the next samples into memory
+    //for tests and headless servers
+    Null {
+        channels: u16,
+        sample_rate: u32,
+    },
+    //like Null, but everything rendered is also written into a 32 bit float WAV file
+    Wav {
+        path: PathBuf,
+        channels: u16,
+        sample_rate: u32,
+    },
+}
+
+impl OutputBackend {
+    pub fn null() -> Self {
+        OutputBackend::Null {
+            channels: 2,
+            sample_rate: 44100,
+        }
+    }
+
+    pub fn wav<P: Into<PathBuf>>(path: P) -> Self {
+        OutputBackend::Wav {
+            path: path.into(),
+            channels: 2,
+            sample_rate: 44100,
+        }
+    }
+}
+
+//hands the root mixer to a device stream, when the device changes only the tap is replaced
+//and everything that's playing just continues on the new device
+struct RootTap {
+    root: Arc<Mutex<MixerSource>>,
+    channels: u16,
+    sample_rate: u32,
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl Iterator for RootTap {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.buffer.len() {
+            self.buffer.clear();
+            self.position = 0;
+            //block wise, locking for every sample would be way too slow
+            let mut root = self.root.lock().ok()?;
+            for _ in 0..TAP_BLOCK {
+                self.buffer.push(root.next().unwrap_or(0.0));
+            }
+        }
+        let sample = self.buffer[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for RootTap {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+struct DeviceOutput {
+    stream: OutputStream,
+    name: String,
+    //set by the stream's error callback when the device is gone
+    lost: Arc<AtomicBool>,
+}
+
+impl DeviceOutput {
+    fn open(config: &DeviceConfig) -> Result<Self, AudioError> {
+        let device = match &config.name {
+            Some(name) => device::find_output_device(name)?,
+            None => device::default_output_device()?,
+        };
+        let name = rodio::DeviceTrait::name(&device).unwrap_or_default();
+        let mut builder = OutputStreamBuilder::from_device(device)
+            .map_err(AudioError::OutputStreamBuilderError)?;
+        if let Some(sample_rate) = config.sample_rate {
+            builder = builder.with_sample_rate(sample_rate);
+        }
+        if let Some(buffer_size) = config.buffer_size {
+            builder = builder.with_buffer_size(cpal::BufferSize::Fixed(buffer_size));
+        }
+
+        let lost = Arc::new(AtomicBool::new(false));
+        let lost_flag = lost.clone();
+        let stream = builder
+            .with_error_callback(move |error| match error {
+                cpal::StreamError::DeviceNotAvailable => lost_flag.store(true, Ordering::Relaxed),
+                error => log::error!("audio stream error: {error}"),
+            })
+            .open_stream()
+            .map_err(AudioError::StreamError)?;
+        Ok(Self { stream, name, lost })
+    }
+}
+
+struct OfflineOutput {
+    //reused between renders
+    buffer: Vec<f32>,
+    wav: Option<hound::WavWriter<BufWriter<File>>>,
+}
+
+enum OutputKind {
+    Device(DeviceOutput),
+    Offline(OfflineOutput),
+}
+
+//everything plays into the root mixer, which is either pulled by a device or by render()
+pub(crate) struct Output {
+    root: Mixer,
+    root_source: Arc<Mutex<MixerSource>>,
+    channels: u16,
+    sample_rate: u32,
+    kind: OutputKind,
+}
+
+impl Output {
+    pub(crate) fn open(backend: OutputBackend) -> Result<Self, AudioError> {
+        match backend {
+            OutputBackend::DefaultDevice => Self::open_device(&DeviceConfig::default()),
+            OutputBackend::Device(config) => Self::open_device(&config),
+            OutputBackend::Null {
+                channels,
+                sample_rate,
+            } => Self::open_offline(channels, sample_rate, None),
+            OutputBackend::Wav {
+                path,
+                channels,
+                sample_rate,
+            } => Self::open_offline(channels, sample_rate, Some(path)),
+        }
+    }
+
+    fn open_device(config: &DeviceConfig) -> Result<Self, AudioError> {
+        let device = DeviceOutput::open(config)?;
+        let channels = device.stream.config().channel_count();
+        let sample_rate = device.stream.config().sample_rate();
+        let mut result = Self::with_root(channels, sample_rate, OutputKind::Device(device));
+        result.attach_tap();
+        Ok(result)
+    }
+
+    fn open_offline(
+        channels: u16,
+        sample_rate: u32,
+        wav: Option<PathBuf>,
+    ) -> Result<Self, AudioError> {
+        let wav = match wav {
+            Some(path) => {
+                let spec = hound::WavSpec {
+                    channels,
+                    sample_rate,
+                    bits_per_sample: 32,
+                    sample_format: hound::SampleFormat::Float,
+                };
+                Some(hound::WavWriter::create(path, spec).map_err(AudioError::WavError)?)
+            }
+            None => None,
+        };
+        let offline = OfflineOutput {
+            buffer: Vec::new(),
+            wav,
+        };
+        Ok(Self::with_root(
+            channels,
+            sample_rate,
+            OutputKind::Offline(offline),
+        ))
+    }
+
+    fn with_root(channels: u16, sample_rate: u32, kind: OutputKind) -> Self {
+        let (root, root_source) = mixer::mixer(channels, sample_rate);
+        //same as the buses, an empty mixer would end
+        root.add(Zero::new(channels, sample_rate));
+        Self {
+            root,
+            root_source: Arc::new(Mutex::new(root_source)),
+            channels,
+            sample_rate,
+            kind,
+        }
+    }
+
+    fn attach_tap(&mut self) {
+        if let OutputKind::Device(device) = &self.kind {
+            device.stream.mixer().add(RootTap {
+                root: self.root_source.clone(),
+                channels: self.channels,
+                sample_rate: self.sample_rate,
+                buffer: Vec::with_capacity(TAP_BLOCK),
+                position: 0,
+            });
+        }
+    }
+
+    //the root keeps its channels and sample rate, rodio converts if the new device differs
+    pub(crate) fn switch_device(&mut self, config: &DeviceConfig) -> Result<(), AudioError> {
+        let device = DeviceOutput::open(config)?;
+        //dropping the old stream stops it and its tap
+        self.kind = OutputKind::Device(device);
+        self.attach_tap();
+        Ok(())
+    }
+
+    pub(crate) fn device_name(&self) -> Option<&str> {
+        match &self.kind {
+            OutputKind::Device(device) => Some(&device.name),
+            OutputKind::Offline(_) => None,
+        }
+    }
+
+    pub(crate) fn is_device_lost(&self) -> bool {
+        match &self.kind {
+            OutputKind::Device(device) => device.lost.load(Ordering::Relaxed),
+            OutputKind::Offline(_) => false,
+        }
+    }
+
+    pub(crate) fn render(&mut self, frames: usize) -> Result<&[f32], AudioError> {
+        let OutputKind::Offline(offline) = &mut self.kind else {
+            return Err(AudioError::NotOffline);
+        };
+        offline.buffer.clear();
+        {
+            let mut root = self.root_source.lock().expect("Only panics if poisoned");
+            for _ in 0..frames * self.channels as usize {
+                offline.buffer.push(root.next().unwrap_or(0.0));
+            }
+        }
+        if let Some(wav) = offline.wav.as_mut() {
+            for &sample in &offline.buffer {
+                wav.write_sample(sample).map_err(AudioError::WavError)?;
+            }
+        }
+        Ok(&offline.buffer)
+    }
+
+    pub(crate) fn mixer(&self) -> &Mixer {
+        &self.root
+    }
+
+    pub(crate) fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub(crate) fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub(crate) fn is_offline(&self) -> bool {
+        matches!(self.kind, OutputKind::Offline(_))
+    }
+}
@@ -1,34 +1,51 @@
+use super::bus::{BusOutput, BusSettings};
+use super::channel::Channel;
+use super::effects::{Effect, EffectId, EffectSlot};
 use super::errors::AudioError;
+use super::output::{DeviceConfig, Output, OutputBackend};
 use super::output_handle::output_markers::{OutputDisabled, OutputEnabled};
+use super::sound::SoundHandle;
+use super::source::{LoadMode, LoadedFile, SoundData, StreamingSource, decode_file};
+use super::spatial::{Emitter, Listener};
+use super::synth::Patch;
 use super::traits::marker::OutputHandlerState;
-use rodio::{Decoder, OutputStream, OutputStreamBuilder, Sink};
+use rodio::{Sink, Source};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufReader, Cursor, Read};
 use std::marker::PhantomData;
 use std::path::Path;
-
-#[cfg(feature = "python")]
-use pyo3::prelude::*;
+use std::time::Duration;
 
 pub mod output_markers {
     pub struct OutputDisabled;
     pub struct OutputEnabled;
 }
 
-#[cfg_attr(feature = "python", pyclass)]
+#[cfg_attr(feature = "ecs", derive(bevy_ecs::resource::Resource))]
 pub struct OutputHandle<O> {
-    pub(super) stream: Option<OutputStream>,
-    pub(super) sink: Option<Sink>,
-    pub(super) loaded_files: HashMap<String, Cursor<Vec<u8>>>,
+    pub(super) output: Option<Output>,
+    pub(super) channels: HashMap<Channel, BusOutput>,
+    pub(super) channel_settings: HashMap<Channel, BusSettings>,
+    pub(super) next_effect_id: u32,
+    //keeps the sounds alive when the returned handles get dropped
+    pub(super) sounds: Vec<SoundHandle>,
+    pub(super) listener: Listener,
+    pub(super) loaded_files: HashMap<String, LoadedFile>,
     _marker: PhantomData<O>,
 }
 
 impl Default for OutputHandle<OutputDisabled> {
     fn default() -> OutputHandle<OutputDisabled> {
         OutputHandle {
-            stream: None,
-            sink: None,
+            output: None,
+            channels: HashMap::new(),
+            channel_settings: Channel::DEFAULTS
+                .into_iter()
+                .map(|channel| (channel, BusSettings::default()))
+                .chain([(Channel::Master, BusSettings::with_parent(None))])
+                .collect(),
+            next_effect_id: 0,
+            sounds: Vec::new(),
+            listener: Listener::default(),
             loaded_files: HashMap::new(),
             _marker: PhantomData,
         }
@@ -42,12 +59,19 @@ impl<O: OutputHandlerState> OutputHandle<O> {
         path: P,
         new_name: String,
     ) -> Result<(), AudioError> {
-        //capacity: 50 Kilobytes, every sound effect has at least this size...
-        let mut buffer = Vec::with_capacity(1024 * 50);
-        let mut file = File::open(path).map_err(AudioError::IoError)?;
-        file.read_to_end(&mut buffer).map_err(AudioError::IoError)?;
-        let data = Cursor::new(buffer);
-        self.loaded_files.insert(new_name, data);
+        self.load_file_with(path, new_name, LoadMode::Encoded)
+    }
+
+    //every play shares the loaded data, nothing is copied
+    //long music shouldn't be loaded at all, use play_streamed for it
+    pub fn load_file_with<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        new_name: String,
+        mode: LoadMode,
+    ) -> Result<(), AudioError> {
+        let file = LoadedFile::load(path, mode)?;
+        self.loaded_files.insert(new_name, file);
         Ok(())
     }
 
@@ -61,6 +85,225 @@ impl<O: OutputHandlerState> OutputHandle<O> {
     pub fn get_all_loaded_files(&self) -> Vec<&String> {
         self.loaded_files.keys().collect::<Vec<&String>>()
     }
+
+    //channel settings work in both states, a disabled handle just remembers them
+    pub fn get_all_channels(&self) -> Vec<&Channel> {
+        self.channel_settings.keys().collect::<Vec<&Channel>>()
+    }
+
+    pub fn channel_volume(&self, channel: &Channel) -> f32 {
+        self.channel_settings
+            .get(channel)
+            .map_or(1.0, |settings| settings.volume)
+    }
+
+    pub fn set_channel_volume(&mut self, channel: Channel, volume: f32) {
+        self.update_channel(channel, |settings| settings.volume = volume.max(0.0));
+    }
+
+    pub fn is_channel_paused(&self, channel: &Channel) -> bool {
+        self.channel_settings
+            .get(channel)
+            .is_some_and(|settings| settings.paused)
+    }
+
+    pub fn pause_channel(&mut self, channel: Channel) {
+        self.update_channel(channel, |settings| settings.paused = true);
+    }
+
+    pub fn resume_channel(&mut self, channel: Channel) {
+        self.update_channel(channel, |settings| settings.paused = false);
+    }
+
+    //nested channels, e.g. Footsteps below Sfx, the parent has to exist already
+    //channels used without adding them end up directly below Master
+    pub fn add_channel(&mut self, channel: Channel, parent: Channel) -> Result<(), AudioError> {
+        if self.channel_settings.contains_key(&channel) {
+            return Err(AudioError::ChannelAlreadyExists(channel));
+        }
+        if !self.channel_settings.contains_key(&parent) {
+            return Err(AudioError::ChannelNotFound(parent));
+        }
+        self.channel_settings
+            .insert(channel, BusSettings::with_parent(Some(parent)));
+        Ok(())
+    }
+
+    pub fn channel_parent(&self, channel: &Channel) -> Option<&Channel> {
+        self.channel_settings
+            .get(channel)
+            .and_then(|settings| settings.parent.as_ref())
+    }
+
+    pub fn is_channel_muted(&self, channel: &Channel) -> bool {
+        self.channel_settings
+            .get(channel)
+            .is_some_and(|settings| settings.muted)
+    }
+
+    pub fn set_channel_muted(&mut self, channel: Channel, muted: bool) {
+        self.update_channel(channel, |settings| settings.muted = muted);
+    }
+
+    pub fn is_channel_solo(&self, channel: &Channel) -> bool {
+        self.channel_settings
+            .get(channel)
+            .is_some_and(|settings| settings.solo)
+    }
+
+    //while any channel is solo, only solo channels (and their children/parents) are heard
+    pub fn set_channel_solo(&mut self, channel: Channel, solo: bool) {
+        self.update_channel(channel, |settings| settings.solo = solo);
+    }
+
+    //effects run in the order they were added
+    pub fn add_effect<E: Effect>(&mut self, channel: Channel, mut effect: E) -> EffectId {
+        //buses created later prepare their effects themselves
+        if let Some(output) = &self.output {
+            effect.prepare(output.channels(), output.sample_rate());
+        }
+        let id = EffectId(self.next_effect_id);
+        self.next_effect_id += 1;
+        let settings = self.channel_settings.entry(channel).or_default();
+        settings
+            .effects
+            .lock()
+            .expect("Only panics if poisoned")
+            .push(EffectSlot {
+                id,
+                enabled: true,
+                effect: Box::new(effect),
+            });
+        id
+    }
+
+    pub fn remove_effect(&mut self, channel: &Channel, id: EffectId) -> Result<(), AudioError> {
+        let settings = self
+            .channel_settings
+            .get(channel)
+            .ok_or_else(|| AudioError::ChannelNotFound(channel.clone()))?;
+        let mut effects = settings.effects.lock().expect("Only panics if poisoned");
+        let index = effects
+            .iter()
+            .position(|slot| slot.id == id)
+            .ok_or(AudioError::EffectNotFound(id))?;
+        effects.remove(index);
+        Ok(())
+    }
+
+    //bypass without losing the effect and its settings
+    pub fn set_effect_enabled(
+        &mut self,
+        channel: &Channel,
+        id: EffectId,
+        enabled: bool,
+    ) -> Result<(), AudioError> {
+        self.with_effect_slot(channel, id, |slot| {
+            slot.enabled = enabled;
+            Ok(())
+        })
+    }
+
+    //change parameters while it's playing: update_effect(&channel, id, |low_pass: &mut LowPass| low_pass.cutoff = 500.0)
+    //EffectNotFound if the id belongs to another type of effect
+    pub fn update_effect<E: Effect, F: FnOnce(&mut E)>(
+        &mut self,
+        channel: &Channel,
+        id: EffectId,
+        f: F,
+    ) -> Result<(), AudioError> {
+        self.with_effect_slot(channel, id, |slot| {
+            let effect: &mut dyn std::any::Any = slot.effect.as_mut();
+            let effect = effect
+                .downcast_mut::<E>()
+                .ok_or(AudioError::EffectNotFound(id))?;
+            f(effect);
+            Ok(())
+        })
+    }
+
+    fn with_effect_slot<F: FnOnce(&mut EffectSlot) -> Result<(), AudioError>>(
+        &mut self,
+        channel: &Channel,
+        id: EffectId,
+        f: F,
+    ) -> Result<(), AudioError> {
+        let settings = self
+            .channel_settings
+            .get(channel)
+            .ok_or_else(|| AudioError::ChannelNotFound(channel.clone()))?;
+        let mut effects = settings.effects.lock().expect("Only panics if poisoned");
+        let slot = effects
+            .iter_mut()
+            .find(|slot| slot.id == id)
+            .ok_or(AudioError::EffectNotFound(id))?;
+        f(slot)
+    }
+
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    //call this every frame with the camera pose, it also picks up moved emitters
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+        self.update_spatial();
+    }
+
+    pub fn update_spatial(&mut self) {
+        //2D sounds too, they might have been spatial before
+        for sound in &self.sounds {
+            sound.update_spatial(&self.listener);
+        }
+    }
+
+    fn update_channel<F: FnOnce(&mut BusSettings)>(&mut self, channel: Channel, f: F) {
+        f(self.channel_settings.entry(channel).or_default());
+        //solo changes can affect every channel, so just apply all of them
+        //only has an effect if the output is enabled
+        for (channel, output) in &self.channels {
+            output.apply(&self.channel_settings[channel], self.is_silent(channel));
+        }
+    }
+
+    fn ancestors(&self, channel: &Channel) -> Vec<&Channel> {
+        let mut result = Vec::new();
+        let mut current = self.channel_parent(channel);
+        while let Some(parent) = current {
+            //can't loop as parents have to exist first, but better safe than a frozen game
+            if result.contains(&parent) {
+                break;
+            }
+            result.push(parent);
+            current = self.channel_parent(parent);
+        }
+        result
+    }
+
+    //muted, or another channel is solo and this one isn't related to it
+    fn is_silent(&self, channel: &Channel) -> bool {
+        if self.is_channel_muted(channel) {
+            return true;
+        }
+        let solos = self
+            .channel_settings
+            .iter()
+            .filter(|(_, settings)| settings.solo)
+            .map(|(channel, _)| channel)
+            .collect::<Vec<_>>();
+        if solos.is_empty() {
+            return false;
+        }
+        let below_solo = solos.contains(&channel)
+            || self
+                .ancestors(channel)
+                .iter()
+                .any(|ancestor| solos.contains(ancestor));
+        let above_solo = solos
+            .iter()
+            .any(|solo| self.ancestors(solo).contains(&channel));
+        !below_solo && !above_solo
+    }
 }
 
 impl OutputHandle<OutputDisabled> {
@@ -71,78 +314,299 @@ impl OutputHandle<OutputDisabled> {
     }
 
     pub fn activate_output(self) -> Result<OutputHandle<OutputEnabled>, AudioError> {
-        let builder = OutputStreamBuilder::from_default_device()
-            .map_err(AudioError::OutputStreamBuilderError)?;
-        let stream = builder.open_stream().map_err(AudioError::StreamError)?;
+        self.activate_with(OutputBackend::DefaultDevice)
+    }
+
+    pub fn activate_with(
+        self,
+        backend: OutputBackend,
+    ) -> Result<OutputHandle<OutputEnabled>, AudioError> {
+        self.try_activate_with(backend).map_err(|(_, error)| error)
+    }
+
+    //gives the disabled handle back on errors, so nothing loaded is lost
+    pub(crate) fn try_activate_with(
+        self,
+        backend: OutputBackend,
+    ) -> Result<OutputHandle<OutputEnabled>, (Box<OutputHandle<OutputDisabled>>, AudioError)> {
+        let output = match Output::open(backend) {
+            Ok(output) => output,
+            Err(error) => return Err((Box::new(self), error)),
+        };
+
         let mut result = OutputHandle::<OutputEnabled> {
-            stream: Some(stream),
-            sink: None,
+            output: Some(output),
+            channels: HashMap::new(),
+            channel_settings: self.channel_settings,
+            next_effect_id: self.next_effect_id,
+            sounds: Vec::new(),
+            listener: self.listener,
             loaded_files: self.loaded_files,
             _marker: PhantomData,
         };
-        let stream_ref = result.stream.as_ref().expect("Just set it to Some(_)");
-        let mixer = stream_ref.mixer();
-
-        result.sink = Some(Sink::connect_new(mixer));
+        let known_channels = result.channel_settings.keys().cloned().collect::<Vec<_>>();
+        for channel in known_channels {
+            result.channel_output(&channel);
+        }
         Ok(result)
     }
 }
 
 //important!
-//NO get_device (but get_device_name) because it instantly gives an error if you try to use a device that just can't work for whatever reason
-//meaning you directly get an error
+//NO get_device (but device_name) because a cpal device instantly gives an error if it just can't work for whatever reason
+//devices are picked by name instead (device::list_output_devices) and only opened in here
 impl OutputHandle<OutputEnabled> {
     pub fn disable_output(self) -> OutputHandle<OutputDisabled> {
         OutputHandle::<OutputDisabled> {
-            stream: None,
-            sink: None,
+            output: None,
+            channels: HashMap::new(),
+            channel_settings: self.channel_settings,
+            next_effect_id: self.next_effect_id,
+            sounds: Vec::new(),
+            listener: self.listener,
             loaded_files: self.loaded_files,
             _marker: PhantomData,
         }
     }
 
-    pub fn play_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), AudioError> {
-        let reader = BufReader::new(File::open(path).map_err(AudioError::IoError)?);
-        let decoder = Decoder::new(reader).map_err(AudioError::DecoderError)?;
-        self.sink
-            .as_mut()
-            .expect("Not 'None' if the output is activated")
-            .append(decoder);
-        Ok(())
+    //one-shot on the SFX channel, starts right away and overlaps with everything else
+    pub fn play_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<SoundHandle, AudioError> {
+        self.play_from_file_on(Channel::Sfx, path)
     }
 
-    pub fn play_loaded(&mut self, name: String) -> Result<(), AudioError> {
-        let data = match self.loaded_files.get(&name) {
-            Some(data) => data.clone(),
+    pub fn play_loaded(&mut self, name: String) -> Result<SoundHandle, AudioError> {
+        self.play_loaded_on(Channel::Sfx, name)
+    }
+
+    pub fn play_from_file_on<P: AsRef<Path>>(
+        &mut self,
+        channel: Channel,
+        path: P,
+    ) -> Result<SoundHandle, AudioError> {
+        let decoder = decode_file(path)?;
+        Ok(self.play_source_on(channel, decoder))
+    }
+
+    pub fn play_loaded_on(
+        &mut self,
+        channel: Channel,
+        name: String,
+    ) -> Result<SoundHandle, AudioError> {
+        let source = match self.loaded_files.get(&name) {
+            Some(file) => file.source()?,
             None => return Err(AudioError::FileNotLoaded(name)),
         };
-        let decoder = Decoder::new(data).map_err(AudioError::DecoderError)?;
-        self.sink
-            .as_mut()
-            .expect("Basically never 'None' if output is enabled")
-            .append(decoder);
-        Ok(())
+        Ok(self.play_source_on(channel, source))
     }
 
-    pub fn pause(&mut self) {
-        self.sink
+    //like play_loaded_on for data that was loaded somewhere else
+    pub fn play_data_on(
+        &mut self,
+        channel: Channel,
+        data: &SoundData,
+    ) -> Result<SoundHandle, AudioError> {
+        let source = data.0.source()?;
+        Ok(self.play_source_on(channel, source))
+    }
+
+    //decoded from disk in chunks on its own thread, on the music channel
+    pub fn play_streamed<P: AsRef<Path>>(&mut self, path: P) -> Result<SoundHandle, AudioError> {
+        self.play_streamed_on(Channel::Music, path)
+    }
+
+    pub fn play_streamed_on<P: AsRef<Path>>(
+        &mut self,
+        channel: Channel,
+        path: P,
+    ) -> Result<SoundHandle, AudioError> {
+        let source = StreamingSource::open(path)?;
+        Ok(self.play_source_on(channel, source))
+    }
+
+    //for anything that isn't a file, e.g. generated sounds
+    pub fn play_source_on<S: Source + Send + 'static>(
+        &mut self,
+        channel: Channel,
+        source: S,
+    ) -> SoundHandle {
+        self.play_source_with(channel, source, None, None)
+    }
+
+    //starts silent, fading in right away would let the first few samples through at full volume
+    pub fn play_source_faded_in_on<S: Source + Send + 'static>(
+        &mut self,
+        channel: Channel,
+        source: S,
+        duration: Duration,
+    ) -> SoundHandle {
+        self.play_source_with(channel, source, None, Some(duration))
+    }
+
+    //generated at the output's sample rate, so nothing is resampled
+    pub fn play_patch_on(&mut self, channel: Channel, patch: &Patch) -> SoundHandle {
+        let source = patch.source_with_rate(self.output().sample_rate());
+        self.play_source_on(channel, source)
+    }
+
+    //3D versions, move them with SoundHandle::set_position
+    pub fn play_from_file_at<P: AsRef<Path>>(
+        &mut self,
+        channel: Channel,
+        path: P,
+        emitter: Emitter,
+    ) -> Result<SoundHandle, AudioError> {
+        let decoder = decode_file(path)?;
+        Ok(self.play_source_with(channel, decoder, Some(emitter), None))
+    }
+
+    pub fn play_loaded_at(
+        &mut self,
+        channel: Channel,
+        name: String,
+        emitter: Emitter,
+    ) -> Result<SoundHandle, AudioError> {
+        let source = match self.loaded_files.get(&name) {
+            Some(file) => file.source()?,
+            None => return Err(AudioError::FileNotLoaded(name)),
+        };
+        Ok(self.play_source_with(channel, source, Some(emitter), None))
+    }
+
+    pub fn play_source_at<S: Source + Send + 'static>(
+        &mut self,
+        channel: Channel,
+        source: S,
+        emitter: Emitter,
+    ) -> SoundHandle {
+        self.play_source_with(channel, source, Some(emitter), None)
+    }
+
+    fn play_source_with<S: Source + Send + 'static>(
+        &mut self,
+        channel: Channel,
+        source: S,
+        emitter: Option<Emitter>,
+        fade_in: Option<Duration>,
+    ) -> SoundHandle {
+        //good moment to forget the sounds that are done
+        self.sounds.retain(|sound| !sound.is_finished());
+
+        let sink = Sink::connect_new(self.channel_output(&channel).mixer());
+        let handle = SoundHandle::new(sink, self.output().is_offline());
+        if emitter.is_some() {
+            handle.set_emitter(emitter);
+            handle.update_spatial(&self.listener);
+        }
+        if let Some(duration) = fade_in {
+            handle.fade_in(duration);
+        }
+        handle.start(source);
+        self.sounds.push(handle.clone());
+        handle
+    }
+
+    pub fn stop_all(&mut self) {
+        for sound in self.sounds.drain(..) {
+            sound.stop();
+        }
+    }
+
+    pub fn channel_count(&self) -> u16 {
+        self.output().channels()
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.output().sample_rate()
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.output().is_offline()
+    }
+
+    //only for the Null/Wav backends: mixes the next frames and returns the interleaved samples
+    //nothing moves forward on these backends without calling this, so sounds never end otherwise
+    pub fn render(&mut self, frames: usize) -> Result<&[f32], AudioError> {
+        self.output
             .as_mut()
             .expect("Basically never 'None' if output is enabled")
-            .pause();
+            .render(frames)
     }
 
-    pub fn play(&mut self) {
-        self.sink
+    //None for the Null/Wav backends
+    pub fn device_name(&self) -> Option<&str> {
+        self.output().device_name()
+    }
+
+    //moves everything that's playing to another device, the old one is closed
+    pub fn switch_device(&mut self, config: DeviceConfig) -> Result<(), AudioError> {
+        self.output
             .as_mut()
             .expect("Basically never 'None' if output is enabled")
-            .play();
+            .switch_device(&config)
     }
 
-    pub fn is_paused(&self) -> bool {
-        //again, match to destructure without taking ownership
-        self.sink
+    pub fn is_device_lost(&self) -> bool {
+        self.output().is_device_lost()
+    }
+
+    //call this once per frame: if the device disappeared (unplugged headphones etc.)
+    //everything moves to the current default device, Ok(true) if that happened
+    //on error the dead device stays and the next call tries again
+    pub fn check_device(&mut self) -> Result<bool, AudioError> {
+        if !self.is_device_lost() {
+            return Ok(false);
+        }
+        let lost_name = self.device_name().unwrap_or_default().to_string();
+        self.switch_device(DeviceConfig::default())
+            .map_err(|error| AudioError::DeviceMigrationFailed(lost_name, Box::new(error)))?;
+        Ok(true)
+    }
+
+    fn output(&self) -> &Output {
+        self.output
             .as_ref()
             .expect("Basically never 'None' if output is enabled")
-            .is_paused()
+    }
+
+    //the Master channel, pauses everything while the pause state of the other channels stays as it is
+    pub fn pause(&mut self) {
+        self.pause_channel(Channel::Master);
+    }
+
+    pub fn play(&mut self) {
+        self.resume_channel(Channel::Master);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_channel_paused(&Channel::Master)
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.channel_volume(&Channel::Master)
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.set_channel_volume(Channel::Master, volume);
+    }
+
+    //custom channels are created the first time something plays on them
+    fn channel_output(&mut self, channel: &Channel) -> &BusOutput {
+        if !self.channels.contains_key(channel) {
+            self.channel_settings.entry(channel.clone()).or_default();
+            //parents first, their mixer is where this channel goes
+            let parent_mixer = match self.channel_settings[channel].parent.clone() {
+                Some(parent) => self.channel_output(&parent).mixer().clone(),
+                None => self.output().mixer().clone(),
+            };
+            let output = BusOutput::new(
+                &parent_mixer,
+                self.output().channels(),
+                self.output().sample_rate(),
+                &self.channel_settings[channel],
+                self.is_silent(channel),
+            );
+            self.channels.insert(channel.clone(), output);
+        }
+        &self.channels[channel]
     }
 }
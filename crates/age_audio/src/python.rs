@@ -0,0 +1,367 @@
+//the Python side of age_audio, OutputHandle's typestate becomes a runtime state there
+//exposed through age_engine's module, see register()
+use super::channel::Channel;
+use super::errors::{AudioError, describe};
+use super::output::{DeviceConfig, OutputBackend};
+use super::output_handle::OutputHandle;
+use super::output_handle::output_markers::{OutputDisabled, OutputEnabled};
+use super::sound::SoundHandle;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyOSError, PyValueError};
+use pyo3::prelude::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+create_exception!(age_engine, AudioException, PyException);
+//load_file wasn't called for the name
+create_exception!(age_engine, FileNotLoadedError, AudioException);
+create_exception!(age_engine, DecoderError, AudioException);
+//no device, a device was lost, the stream couldn't be built etc.
+create_exception!(age_engine, OutputDeviceError, AudioException);
+//called something that needs an enabled output on a disabled one, or the other way around
+create_exception!(age_engine, OutputStateError, AudioException);
+
+impl From<AudioError> for PyErr {
+    fn from(error: AudioError) -> PyErr {
+        let message = describe(&error);
+        match error {
+            AudioError::IoError(path, error) => PyOSError::new_err((
+                error.raw_os_error().unwrap_or(0),
+                message,
+                path.display().to_string(),
+            )),
+            AudioError::FileNotLoaded(name) => FileNotLoadedError::new_err(name),
+            AudioError::DecoderError(_, _) => DecoderError::new_err(message),
+            AudioError::OutputStreamBuilderError(_)
+            | AudioError::StreamError(_)
+            | AudioError::DevicesError(_)
+            | AudioError::DeviceNotFound(_)
+            | AudioError::DeviceMigrationFailed(_, _) => OutputDeviceError::new_err(message),
+            AudioError::NotOffline => OutputStateError::new_err(message),
+            _ => AudioException::new_err(message),
+        }
+    }
+}
+
+//"master", "music", "sfx", "voice", "ui", anything else is a custom channel
+fn channel_from_name(name: &str) -> Channel {
+    match name.to_lowercase().as_str() {
+        "master" => Channel::Master,
+        "music" => Channel::Music,
+        "sfx" => Channel::Sfx,
+        "voice" => Channel::Voice,
+        "ui" => Channel::Ui,
+        _ => Channel::Custom(name.to_string()),
+    }
+}
+
+enum HandleState {
+    Disabled(OutputHandle<OutputDisabled>),
+    Enabled(OutputHandle<OutputEnabled>),
+    //only while switching
+    Switching,
+}
+
+//OutputHandle for Python, starts disabled
+//unsendable: the device stream must stay on the thread that opened it
+#[pyclass(name = "OutputHandle", unsendable)]
+pub struct PyOutputHandle {
+    state: HandleState,
+}
+
+impl PyOutputHandle {
+    fn enabled(&mut self) -> PyResult<&mut OutputHandle<OutputEnabled>> {
+        match &mut self.state {
+            HandleState::Enabled(handle) => Ok(handle),
+            _ => Err(OutputStateError::new_err(
+                "the output is disabled, call activate() first",
+            )),
+        }
+    }
+}
+
+#[pymethods]
+impl PyOutputHandle {
+    #[new]
+    fn new() -> Self {
+        Self {
+            state: HandleState::Disabled(OutputHandle::new()),
+        }
+    }
+
+    //backend: "default", "device" (needs device), "null" or "wav" (needs path)
+    #[pyo3(signature = (backend = "default", *, device = None, path = None, channels = 2, sample_rate = 44100))]
+    fn activate(
+        &mut self,
+        backend: &str,
+        device: Option<String>,
+        path: Option<PathBuf>,
+        channels: u16,
+        sample_rate: u32,
+    ) -> PyResult<()> {
+        let backend = match (backend, device, path) {
+            ("default", _, _) => OutputBackend::DefaultDevice,
+            ("device", Some(name), _) => OutputBackend::Device(DeviceConfig::named(name)),
+            ("null", _, _) => OutputBackend::Null {
+                channels,
+                sample_rate,
+            },
+            ("wav", _, Some(path)) => OutputBackend::Wav {
+                path,
+                channels,
+                sample_rate,
+            },
+            ("device", None, _) => {
+                return Err(PyValueError::new_err("the device backend needs device="));
+            }
+            ("wav", _, None) => {
+                return Err(PyValueError::new_err("the wav backend needs path="));
+            }
+            (backend, _, _) => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown backend {backend:?}"
+                )));
+            }
+        };
+        match std::mem::replace(&mut self.state, HandleState::Switching) {
+            HandleState::Disabled(handle) => match handle.try_activate_with(backend) {
+                Ok(handle) => {
+                    self.state = HandleState::Enabled(handle);
+                    Ok(())
+                }
+                Err((handle, error)) => {
+                    self.state = HandleState::Disabled(*handle);
+                    Err(error.into())
+                }
+            },
+            state => {
+                self.state = state;
+                Err(OutputStateError::new_err("the output is already enabled"))
+            }
+        }
+    }
+
+    fn disable(&mut self) -> PyResult<()> {
+        match std::mem::replace(&mut self.state, HandleState::Switching) {
+            HandleState::Enabled(handle) => {
+                self.state = HandleState::Disabled(handle.disable_output());
+                Ok(())
+            }
+            state => {
+                self.state = state;
+                Err(OutputStateError::new_err("the output is already disabled"))
+            }
+        }
+    }
+
+    #[getter]
+    fn is_enabled(&self) -> bool {
+        matches!(self.state, HandleState::Enabled(_))
+    }
+
+    fn load_file(&mut self, path: PathBuf, name: String) -> PyResult<()> {
+        match &mut self.state {
+            HandleState::Disabled(handle) => handle.load_file(path, name)?,
+            HandleState::Enabled(handle) => handle.load_file(path, name)?,
+            HandleState::Switching => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn unload_file(&mut self, name: String) -> PyResult<()> {
+        match &mut self.state {
+            HandleState::Disabled(handle) => handle.unload_file(name)?,
+            HandleState::Enabled(handle) => handle.unload_file(name)?,
+            HandleState::Switching => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn loaded_files(&self) -> Vec<String> {
+        let files = match &self.state {
+            HandleState::Disabled(handle) => handle.get_all_loaded_files(),
+            HandleState::Enabled(handle) => handle.get_all_loaded_files(),
+            HandleState::Switching => unreachable!(),
+        };
+        files.into_iter().cloned().collect()
+    }
+
+    #[pyo3(signature = (path, channel = "sfx"))]
+    fn play_file(&mut self, path: PathBuf, channel: &str) -> PyResult<PySoundHandle> {
+        let sound = self
+            .enabled()?
+            .play_from_file_on(channel_from_name(channel), path)?;
+        Ok(PySoundHandle { sound })
+    }
+
+    #[pyo3(signature = (name, channel = "sfx"))]
+    fn play_loaded(&mut self, name: String, channel: &str) -> PyResult<PySoundHandle> {
+        let sound = self
+            .enabled()?
+            .play_loaded_on(channel_from_name(channel), name)?;
+        Ok(PySoundHandle { sound })
+    }
+
+    #[pyo3(signature = (path, channel = "music"))]
+    fn play_streamed(&mut self, path: PathBuf, channel: &str) -> PyResult<PySoundHandle> {
+        let sound = self
+            .enabled()?
+            .play_streamed_on(channel_from_name(channel), path)?;
+        Ok(PySoundHandle { sound })
+    }
+
+    fn stop_all(&mut self) -> PyResult<()> {
+        self.enabled()?.stop_all();
+        Ok(())
+    }
+
+    //pauses/resumes everything (the master channel)
+    fn pause(&mut self) -> PyResult<()> {
+        self.enabled()?.pause();
+        Ok(())
+    }
+
+    fn play(&mut self) -> PyResult<()> {
+        self.enabled()?.play();
+        Ok(())
+    }
+
+    #[getter]
+    fn is_paused(&mut self) -> PyResult<bool> {
+        Ok(self.enabled()?.is_paused())
+    }
+
+    #[getter]
+    fn volume(&mut self) -> PyResult<f32> {
+        Ok(self.enabled()?.volume())
+    }
+
+    #[setter]
+    fn set_volume(&mut self, volume: f32) -> PyResult<()> {
+        self.enabled()?.set_volume(volume);
+        Ok(())
+    }
+
+    fn channel_volume(&self, channel: &str) -> f32 {
+        let channel = channel_from_name(channel);
+        match &self.state {
+            HandleState::Disabled(handle) => handle.channel_volume(&channel),
+            HandleState::Enabled(handle) => handle.channel_volume(&channel),
+            HandleState::Switching => unreachable!(),
+        }
+    }
+
+    fn set_channel_volume(&mut self, channel: &str, volume: f32) {
+        let channel = channel_from_name(channel);
+        match &mut self.state {
+            HandleState::Disabled(handle) => handle.set_channel_volume(channel, volume),
+            HandleState::Enabled(handle) => handle.set_channel_volume(channel, volume),
+            HandleState::Switching => unreachable!(),
+        }
+    }
+
+    //null/wav backends only, interleaved samples of the next `frames` frames
+    fn render(&mut self, frames: usize) -> PyResult<Vec<f32>> {
+        Ok(self.enabled()?.render(frames)?.to_vec())
+    }
+
+    #[getter]
+    fn channel_count(&mut self) -> PyResult<u16> {
+        Ok(self.enabled()?.channel_count())
+    }
+
+    #[getter]
+    fn sample_rate(&mut self) -> PyResult<u32> {
+        Ok(self.enabled()?.sample_rate())
+    }
+}
+
+//what the play methods return, dropping it doesn't stop the sound
+#[pyclass(name = "SoundHandle", unsendable)]
+pub struct PySoundHandle {
+    sound: SoundHandle,
+}
+
+#[pymethods]
+impl PySoundHandle {
+    fn stop(&self) {
+        self.sound.stop();
+    }
+
+    fn pause(&self) {
+        self.sound.pause();
+    }
+
+    fn resume(&self) {
+        self.sound.resume();
+    }
+
+    #[getter]
+    fn is_paused(&self) -> bool {
+        self.sound.is_paused()
+    }
+
+    #[getter]
+    fn is_playing(&self) -> bool {
+        self.sound.is_playing()
+    }
+
+    #[getter]
+    fn is_finished(&self) -> bool {
+        self.sound.is_finished()
+    }
+
+    #[getter]
+    fn volume(&self) -> f32 {
+        self.sound.volume()
+    }
+
+    #[setter]
+    fn set_volume(&self, volume: f32) {
+        self.sound.set_volume(volume);
+    }
+
+    #[getter]
+    fn looping(&self) -> bool {
+        self.sound.is_looping()
+    }
+
+    #[setter]
+    fn set_looping(&self, looping: bool) {
+        self.sound.set_looping(looping);
+    }
+
+    #[getter]
+    fn pan(&self) -> f32 {
+        self.sound.pan()
+    }
+
+    #[setter]
+    fn set_pan(&self, pan: f32) {
+        self.sound.set_pan(pan);
+    }
+
+    //in seconds
+    fn fade_in(&self, seconds: f32) {
+        self.sound
+            .fade_in(Duration::from_secs_f32(seconds.max(0.0)));
+    }
+
+    fn fade_out(&self, seconds: f32) {
+        self.sound
+            .fade_out(Duration::from_secs_f32(seconds.max(0.0)));
+    }
+}
+
+//adds the classes and exceptions to the given module
+pub fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = module.py();
+    module.add_class::<PyOutputHandle>()?;
+    module.add_class::<PySoundHandle>()?;
+    module.add("AudioException", py.get_type::<AudioException>())?;
+    module.add("FileNotLoadedError", py.get_type::<FileNotLoadedError>())?;
+    module.add("DecoderError", py.get_type::<DecoderError>())?;
+    module.add("OutputDeviceError", py.get_type::<OutputDeviceError>())?;
+    module.add("OutputStateError", py.get_type::<OutputStateError>())?;
+    Ok(())
+}
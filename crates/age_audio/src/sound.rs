@@ -0,0 +1,444 @@
+use super::errors::AudioError;
+use super::spatial::{Emitter, Listener, SpatialParams, Vec3};
+use rodio::source::SeekError;
+use rodio::{Sink, Source};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+//how often (in frames) the audio thread looks at the controls
+const CONTROL_INTERVAL: u32 = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct Fade {
+    from: Option<f32>,
+    to: f32,
+    duration: Duration,
+    stop_at_end: bool,
+}
+
+//everything the Sink can't do for us, shared with the audio thread
+struct SoundControls {
+    //f32 bits, -1 = left, 1 = right
+    pan: AtomicU32,
+    looping: AtomicBool,
+    //written by the OutputHandle from the emitter and listener, on top of pan and volume
+    spatial_gain: AtomicU32,
+    spatial_pan: AtomicU32,
+    fade_requested: AtomicBool,
+    fade: Mutex<Option<Fade>>,
+    //offline outputs only, see SoundHandle::seek
+    seek: Mutex<Option<Duration>>,
+}
+
+impl SoundControls {
+    fn new() -> Self {
+        Self {
+            pan: AtomicU32::new(0.0f32.to_bits()),
+            looping: AtomicBool::new(false),
+            spatial_gain: AtomicU32::new(1.0f32.to_bits()),
+            spatial_pan: AtomicU32::new(0.0f32.to_bits()),
+            fade_requested: AtomicBool::new(false),
+            fade: Mutex::new(None),
+            seek: Mutex::new(None),
+        }
+    }
+
+    fn request_fade(&self, fade: Fade) {
+        if let Ok(mut current) = self.fade.lock() {
+            *current = Some(fade);
+            self.fade_requested.store(true, Ordering::Release);
+        }
+    }
+}
+
+//wraps every played sound: looping, fading and panning
+//mono sounds are turned into stereo, otherwise panning would do nothing
+struct SoundSource<S> {
+    inner: S,
+    controls: Arc<SoundControls>,
+    mono: bool,
+    channels: u16,
+    sample_rate: u32,
+    frame_position: u16,
+    //right side of an upmixed mono frame
+    pending: Option<f32>,
+    left_gain: f32,
+    right_gain: f32,
+    spatial_gain: f32,
+    gain: f32,
+    gain_target: f32,
+    gain_step: f32,
+    stop_at_target: bool,
+    frames_until_update: u32,
+}
+
+impl<S: Source> SoundSource<S> {
+    fn new(inner: S, controls: Arc<SoundControls>) -> Self {
+        let mono = inner.channels() == 1;
+        let channels = if mono { 2 } else { inner.channels() };
+        let sample_rate = inner.sample_rate();
+        let mut result = Self {
+            inner,
+            controls,
+            mono,
+            channels,
+            sample_rate,
+            frame_position: 0,
+            pending: None,
+            left_gain: 1.0,
+            right_gain: 1.0,
+            spatial_gain: 1.0,
+            gain: 1.0,
+            gain_target: 1.0,
+            gain_step: 0.0,
+            stop_at_target: false,
+            frames_until_update: 0,
+        };
+        result.update_controls();
+        result
+    }
+
+    fn update_controls(&mut self) {
+        let pan = f32::from_bits(self.controls.pan.load(Ordering::Relaxed))
+            + f32::from_bits(self.controls.spatial_pan.load(Ordering::Relaxed));
+        let pan = pan.clamp(-1.0, 1.0);
+        self.left_gain = (1.0 - pan).min(1.0);
+        self.right_gain = (1.0 + pan).min(1.0);
+        self.spatial_gain = f32::from_bits(self.controls.spatial_gain.load(Ordering::Relaxed));
+
+        let seek = self
+            .controls
+            .seek
+            .lock()
+            .ok()
+            .and_then(|mut seek| seek.take());
+        if let Some(position) = seek {
+            //nobody is waiting for the result
+            let _ = self.try_seek(position);
+        }
+
+        if self.controls.fade_requested.swap(false, Ordering::Acquire) {
+            let fade = self
+                .controls
+                .fade
+                .lock()
+                .ok()
+                .and_then(|mut fade| fade.take());
+            if let Some(fade) = fade {
+                if let Some(from) = fade.from {
+                    self.gain = from;
+                }
+                let frames = (fade.duration.as_secs_f32() * self.sample_rate as f32).max(1.0);
+                self.gain_target = fade.to;
+                self.gain_step = (fade.to - self.gain) / frames;
+                self.stop_at_target = fade.stop_at_end;
+            }
+        }
+    }
+
+    //returns false once a fade out is done
+    fn next_frame(&mut self) -> bool {
+        if self.frames_until_update == 0 {
+            self.update_controls();
+            self.frames_until_update = CONTROL_INTERVAL;
+        }
+        self.frames_until_update -= 1;
+
+        if self.gain_step != 0.0 {
+            self.gain += self.gain_step;
+            let reached = (self.gain_step > 0.0 && self.gain >= self.gain_target)
+                || (self.gain_step < 0.0 && self.gain <= self.gain_target);
+            if reached {
+                self.gain = self.gain_target;
+                self.gain_step = 0.0;
+            }
+        }
+        !(self.stop_at_target && self.gain_step == 0.0 && self.gain == self.gain_target)
+    }
+
+    fn next_input(&mut self) -> Option<f32> {
+        match self.inner.next() {
+            Some(sample) => Some(sample),
+            None if self.controls.looping.load(Ordering::Relaxed) => {
+                //not every source can seek, those just end
+                self.inner.try_seek(Duration::ZERO).ok()?;
+                self.inner.next()
+            }
+            None => None,
+        }
+    }
+}
+
+impl<S: Source> Iterator for SoundSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(sample) = self.pending.take() {
+            return Some(sample);
+        }
+        if self.frame_position == 0 && !self.next_frame() {
+            return None;
+        }
+
+        let sample = self.next_input()? * self.gain * self.spatial_gain;
+        if self.mono {
+            self.pending = Some(sample * self.right_gain);
+            return Some(sample * self.left_gain);
+        }
+
+        let result = match self.frame_position {
+            0 => sample * self.left_gain,
+            1 => sample * self.right_gain,
+            _ => sample,
+        };
+        self.frame_position = (self.frame_position + 1) % self.channels;
+        Some(result)
+    }
+}
+
+impl<S: Source> Source for SoundSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        let len = self.inner.current_span_len()?;
+        if self.mono {
+            Some(len * 2 + self.pending.is_some() as usize)
+        } else {
+            Some(len)
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.controls.looping.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+        self.pending = None;
+        self.frame_position = 0;
+        Ok(())
+    }
+}
+
+pub(crate) struct Sound {
+    sink: Sink,
+    controls: Arc<SoundControls>,
+    //the sink speed is speed * doppler
+    speed: Mutex<f32>,
+    doppler: Mutex<f32>,
+    emitter: Mutex<Option<Emitter>>,
+    //the Sink's seek waits for the audio thread, offline outputs only move on render()
+    deferred_seek: bool,
+}
+
+//cheap to clone, the OutputHandle keeps its own copy until the sound is done,
+//so dropping a handle does NOT stop the sound
+#[derive(Clone)]
+pub struct SoundHandle {
+    pub(crate) sound: Arc<Sound>,
+}
+
+impl SoundHandle {
+    pub(crate) fn new(sink: Sink, deferred_seek: bool) -> Self {
+        let controls = Arc::new(SoundControls::new());
+        Self {
+            sound: Arc::new(Sound {
+                sink,
+                controls,
+                speed: Mutex::new(1.0),
+                doppler: Mutex::new(1.0),
+                emitter: Mutex::new(None),
+                deferred_seek,
+            }),
+        }
+    }
+
+    //set up everything (emitter etc.) before this, the first samples should already be right
+    pub(crate) fn start<S: Source + Send + 'static>(&self, source: S) {
+        let controls = self.sound.controls.clone();
+        self.sound.sink.append(SoundSource::new(source, controls));
+    }
+
+    //called by the OutputHandle whenever the listener moves
+    pub(crate) fn update_spatial(&self, listener: &Listener) {
+        let emitter = *self.sound.emitter.lock().expect("Only panics if poisoned");
+        let params = match emitter {
+            Some(emitter) => super::spatial::compute(listener, &emitter),
+            None => SpatialParams::default(),
+        };
+        let controls = &self.sound.controls;
+        controls
+            .spatial_gain
+            .store(params.gain.to_bits(), Ordering::Relaxed);
+        controls
+            .spatial_pan
+            .store(params.pan.to_bits(), Ordering::Relaxed);
+        *self.sound.doppler.lock().expect("Only panics if poisoned") = params.doppler;
+        self.apply_speed();
+    }
+
+    fn apply_speed(&self) {
+        let speed = *self.sound.speed.lock().expect("Only panics if poisoned");
+        let doppler = *self.sound.doppler.lock().expect("Only panics if poisoned");
+        self.sound.sink.set_speed(speed * doppler);
+    }
+
+    pub fn stop(&self) {
+        self.sound.sink.stop();
+    }
+
+    pub fn pause(&self) {
+        self.sound.sink.pause();
+    }
+
+    pub fn resume(&self) {
+        self.sound.sink.play();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.sound.sink.is_paused()
+    }
+
+    //false if paused, stopped or done
+    pub fn is_playing(&self) -> bool {
+        !self.is_finished() && !self.is_paused()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.sound.sink.empty()
+    }
+
+    //blocks for a few ms until the audio thread did it
+    //on the Null/Wav backends it happens during the next render() and errors can't be reported
+    pub fn seek(&self, position: Duration) -> Result<(), AudioError> {
+        if self.sound.deferred_seek {
+            *self
+                .sound
+                .controls
+                .seek
+                .lock()
+                .expect("Only panics if poisoned") = Some(position);
+            return Ok(());
+        }
+        self.sound
+            .sink
+            .try_seek(position)
+            .map_err(AudioError::SeekError)
+    }
+
+    pub fn position(&self) -> Duration {
+        self.sound.sink.get_pos()
+    }
+
+    pub fn set_looping(&self, looping: bool) {
+        self.sound
+            .controls
+            .looping
+            .store(looping, Ordering::Relaxed);
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.sound.controls.looping.load(Ordering::Relaxed)
+    }
+
+    //fades are on top of the volume, so a faded in sound ends at set_volume's value
+    pub fn fade_in(&self, duration: Duration) {
+        self.sound.controls.request_fade(Fade {
+            from: Some(0.0),
+            to: 1.0,
+            duration,
+            stop_at_end: false,
+        });
+    }
+
+    //the sound is stopped once it is silent
+    pub fn fade_out(&self, duration: Duration) {
+        self.sound.controls.request_fade(Fade {
+            from: None,
+            to: 0.0,
+            duration,
+            stop_at_end: true,
+        });
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.sound.sink.volume()
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.sound.sink.set_volume(volume.max(0.0));
+    }
+
+    //without the doppler shift
+    pub fn speed(&self) -> f32 {
+        *self.sound.speed.lock().expect("Only panics if poisoned")
+    }
+
+    //changes the pitch too, 2.0 = twice as fast and an octave higher
+    pub fn set_speed(&self, speed: f32) {
+        *self.sound.speed.lock().expect("Only panics if poisoned") = speed.max(0.01);
+        self.apply_speed();
+    }
+
+    pub fn pan(&self) -> f32 {
+        f32::from_bits(self.sound.controls.pan.load(Ordering::Relaxed))
+    }
+
+    //-1.0 = only left, 0.0 = center, 1.0 = only right
+    pub fn set_pan(&self, pan: f32) {
+        self.sound
+            .controls
+            .pan
+            .store(pan.clamp(-1.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn is_spatial(&self) -> bool {
+        self.emitter().is_some()
+    }
+
+    pub fn emitter(&self) -> Option<Emitter> {
+        *self.sound.emitter.lock().expect("Only panics if poisoned")
+    }
+
+    //None turns it back into a normal 2D sound
+    //takes effect with the next OutputHandle::set_listener/update_spatial
+    pub fn set_emitter(&self, emitter: Option<Emitter>) {
+        *self.sound.emitter.lock().expect("Only panics if poisoned") = emitter;
+    }
+
+    //does nothing for 2D sounds
+    pub fn set_position<P: Into<Vec3>>(&self, position: P) {
+        if let Some(emitter) = self
+            .sound
+            .emitter
+            .lock()
+            .expect("Only panics if poisoned")
+            .as_mut()
+        {
+            emitter.position = position.into();
+        }
+    }
+
+    //only used for the doppler shift
+    pub fn set_velocity<V: Into<Vec3>>(&self, velocity: V) {
+        if let Some(emitter) = self
+            .sound
+            .emitter
+            .lock()
+            .expect("Only panics if poisoned")
+            .as_mut()
+        {
+            emitter.velocity = velocity.into();
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use super::errors::AudioError;
+use rodio::source::SeekError;
+use rodio::{Decoder, Source};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+//samples the streaming thread decodes at once
+const STREAM_CHUNK: usize = 4096;
+//chunks decoded ahead, ~0.75s for 44.1kHz stereo
+const STREAM_CHUNKS_AHEAD: usize = 16;
+//decoded right away, so playing (and looping to the start) doesn't wait for the thread
+const STREAM_HEAD: Duration = Duration::from_millis(250);
+
+//how OutputHandle::load_file_with keeps a file in memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadMode {
+    //the file as it is, decoded on every play
+    //small in memory, good for most sound effects
+    #[default]
+    Encoded,
+    //decoded once into f32 samples, playing it costs nothing but memory
+    //for very short sounds that are played a lot (footsteps, gunshots, UI clicks)
+    Decoded,
+}
+
+//shared between all plays of a loaded file, cloning only clones the Arc
+#[derive(Clone)]
+pub(crate) struct SharedBytes(Arc<[u8]>);
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum LoadedFile {
+    //the path is kept for decoder errors, which only happen when it's played
+    Encoded(Arc<Path>, SharedBytes),
+    Decoded(PcmSource),
+}
+
+impl LoadedFile {
+    pub(crate) fn load<P: AsRef<Path>>(path: P, mode: LoadMode) -> Result<Self, AudioError> {
+        match mode {
+            LoadMode::Encoded => {
+                let path = path.as_ref();
+                let data =
+                    std::fs::read(path).map_err(|err| AudioError::IoError(path.into(), err))?;
+                Ok(LoadedFile::Encoded(path.into(), SharedBytes(data.into())))
+            }
+            LoadMode::Decoded => {
+                let decoder = decode_file(path)?;
+                let channels = decoder.channels();
+                let sample_rate = decoder.sample_rate();
+                Ok(LoadedFile::Decoded(PcmSource {
+                    samples: decoder.collect::<Vec<f32>>().into(),
+                    channels,
+                    sample_rate,
+                    position: 0,
+                }))
+            }
+        }
+    }
+
+    pub(crate) fn source(&self) -> Result<LoadedSource, AudioError> {
+        match self {
+            LoadedFile::Encoded(path, data) => {
+                let len = data.0.len() as u64;
+                //the length is needed for seeking, which looping depends on
+                let decoder = Decoder::builder()
+                    .with_data(Cursor::new(data.clone()))
+                    .with_byte_len(len)
+                    .with_seekable(true)
+                    .build()
+                    .map_err(|err| AudioError::DecoderError(path.to_path_buf(), err))?;
+                Ok(LoadedSource::Encoded(decoder))
+            }
+            LoadedFile::Decoded(pcm) => Ok(LoadedSource::Decoded(pcm.clone())),
+        }
+    }
+}
+
+//streamed from disk, try_from takes the byte length from the file so it can seek
+pub(crate) fn decode_file<P: AsRef<Path>>(path: P) -> Result<Decoder<BufReader<File>>, AudioError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|err| AudioError::IoError(path.into(), err))?;
+    Decoder::try_from(file).map_err(|err| AudioError::DecoderError(path.into(), err))
+}
+
+//a loaded file that isn't owned by an OutputHandle, e.g. for asset servers
+//cloning it shares the data, play it with OutputHandle::play_data_on
+#[derive(Clone)]
+pub struct SoundData(pub(crate) LoadedFile);
+
+impl SoundData {
+    pub fn load<P: AsRef<Path>>(path: P, mode: LoadMode) -> Result<Self, AudioError> {
+        LoadedFile::load(path, mode).map(SoundData)
+    }
+}
+
+//already decoded samples, interleaved
+#[derive(Clone)]
+pub(crate) struct PcmSource {
+    samples: Arc<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+    position: usize,
+}
+
+impl Iterator for PcmSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.samples.get(self.position)?;
+        self.position += 1;
+        Some(*sample)
+    }
+}
+
+impl Source for PcmSource {
+    fn current_span_len(&self) -> Option<usize> {
+        Some(self.samples.len() - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        Some(Duration::from_secs_f64(
+            frames as f64 / self.sample_rate as f64,
+        ))
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let frame = (pos.as_secs_f64() * self.sample_rate as f64) as usize;
+        self.position = (frame * self.channels as usize).min(self.samples.len());
+        Ok(())
+    }
+}
+
+//what OutputHandle::play_loaded* plays
+pub(crate) enum LoadedSource {
+    Encoded(Decoder<Cursor<SharedBytes>>),
+    Decoded(PcmSource),
+}
+
+impl Iterator for LoadedSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        match self {
+            LoadedSource::Encoded(decoder) => decoder.next(),
+            LoadedSource::Decoded(pcm) => pcm.next(),
+        }
+    }
+}
+
+impl Source for LoadedSource {
+    fn current_span_len(&self) -> Option<usize> {
+        match self {
+            LoadedSource::Encoded(decoder) => decoder.current_span_len(),
+            LoadedSource::Decoded(pcm) => pcm.current_span_len(),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self {
+            LoadedSource::Encoded(decoder) => decoder.channels(),
+            LoadedSource::Decoded(pcm) => pcm.channels(),
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            LoadedSource::Encoded(decoder) => decoder.sample_rate(),
+            LoadedSource::Decoded(pcm) => pcm.sample_rate(),
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match self {
+            LoadedSource::Encoded(decoder) => decoder.total_duration(),
+            LoadedSource::Decoded(pcm) => pcm.total_duration(),
+        }
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        match self {
+            LoadedSource::Encoded(decoder) => decoder.try_seek(pos),
+            LoadedSource::Decoded(pcm) => pcm.try_seek(pos),
+        }
+    }
+}
+
+//a section that repeats forever once it's reached, everything before it is the intro
+//in frames (samples per channel) of the file, so loops are sample accurate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoopPoints {
+    pub start: u64,
+    //None = the end of the file
+    pub end: Option<u64>,
+}
+
+impl LoopPoints {
+    pub fn new(start: u64, end: Option<u64>) -> Self {
+        Self { start, end }
+    }
+
+    //the whole file, without an intro
+    pub fn whole() -> Self {
+        Self::default()
+    }
+}
+
+struct StreamChunk {
+    //chunks from before the last seek are thrown away
+    generation: u64,
+    samples: Vec<f32>,
+    //the file ended after these samples
+    end: bool,
+}
+
+//decodes on its own thread, so the audio thread never waits for the disk or the decoder
+//only the part around the current position is in memory, meant for music and ambience
+//seeking anywhere but the start can leave a short gap until the thread caught up
+pub struct StreamingSource {
+    channels: u16,
+    sample_rate: u32,
+    total_duration: Option<Duration>,
+    //the first STREAM_HEAD of the file
+    head: Arc<[f32]>,
+    //Some while playing from the head
+    head_position: Option<usize>,
+    chunks: Receiver<StreamChunk>,
+    seeks: Sender<(u64, Duration)>,
+    generation: u64,
+    current: Vec<f32>,
+    position: usize,
+    //the current chunk is the last one
+    end: bool,
+}
+
+impl StreamingSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AudioError> {
+        Self::open_with_loop(path, None)
+    }
+
+    //the loop is done by the decoding thread, so it's gapless and the source never ends
+    pub fn open_with_loop<P: AsRef<Path>>(
+        path: P,
+        loop_points: Option<LoopPoints>,
+    ) -> Result<Self, AudioError> {
+        let mut decoder = decode_file(path)?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        let total_duration = match loop_points {
+            Some(_) => None,
+            None => decoder.total_duration(),
+        };
+
+        let mut head_frames = (STREAM_HEAD.as_secs_f32() * sample_rate as f32) as u64;
+        if let Some(LoopPoints { end: Some(end), .. }) = loop_points {
+            head_frames = head_frames.min(end);
+        }
+        let head: Vec<f32> = decoder
+            .by_ref()
+            .take(head_frames as usize * channels as usize)
+            .collect();
+        let head_frames = (head.len() / channels.max(1) as usize) as u64;
+
+        let (chunk_sender, chunks) = mpsc::sync_channel(STREAM_CHUNKS_AHEAD);
+        let (seeks, seek_receiver) = mpsc::channel();
+        let thread = StreamThread {
+            decoder,
+            channels,
+            sample_rate,
+            head_frames,
+            loop_points,
+            frame: head_frames,
+            generation: 0,
+            chunks: chunk_sender,
+            seeks: seek_receiver,
+        };
+        thread::Builder::new()
+            .name("age_audio stream".to_string())
+            .spawn(move || thread.run())
+            .map_err(AudioError::SpawnThreadError)?;
+
+        Ok(Self {
+            channels,
+            sample_rate,
+            total_duration,
+            head: head.into(),
+            head_position: Some(0),
+            chunks,
+            seeks,
+            generation: 0,
+            current: Vec::new(),
+            position: 0,
+            end: false,
+        })
+    }
+
+    fn next_chunk(&mut self) -> Option<()> {
+        loop {
+            match self.chunks.try_recv() {
+                Ok(chunk) if chunk.generation != self.generation => continue,
+                Ok(chunk) => {
+                    self.current = chunk.samples;
+                    self.position = 0;
+                    self.end = chunk.end;
+                    return Some(());
+                }
+                //the thread is behind, one frame of silence keeps the channels in order
+                Err(TryRecvError::Empty) => {
+                    self.current.clear();
+                    self.current.resize(self.channels as usize, 0.0);
+                    self.position = 0;
+                    return Some(());
+                }
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+fn frames_to_duration(frames: u64, sample_rate: u32) -> Duration {
+    Duration::from_secs_f64(frames as f64 / sample_rate as f64)
+}
+
+struct StreamThread {
+    decoder: Decoder<BufReader<File>>,
+    channels: u16,
+    sample_rate: u32,
+    head_frames: u64,
+    loop_points: Option<LoopPoints>,
+    //where the decoder is
+    frame: u64,
+    generation: u64,
+    chunks: SyncSender<StreamChunk>,
+    seeks: Receiver<(u64, Duration)>,
+}
+
+impl StreamThread {
+    fn run(mut self) {
+        let mut ended = false;
+        loop {
+            //only the newest seek matters
+            let mut seek = None;
+            if ended {
+                //nothing to do until the source seeks or is dropped
+                match self.seeks.recv() {
+                    Ok(request) => seek = Some(request),
+                    Err(_) => return,
+                }
+            }
+            while let Ok(request) = self.seeks.try_recv() {
+                seek = Some(request);
+            }
+            if let Some((generation, position)) = seek {
+                self.generation = generation;
+                //the head is played from memory, decoding continues right after it
+                let frame = ((position.as_secs_f64() * self.sample_rate as f64) as u64)
+                    .max(self.head_frames);
+                ended = !self.seek(frame);
+            }
+
+            let chunk = if ended {
+                StreamChunk {
+                    generation: self.generation,
+                    samples: Vec::new(),
+                    end: true,
+                }
+            } else {
+                let samples = self.decode_chunk();
+                ended = samples.is_empty();
+                StreamChunk {
+                    generation: self.generation,
+                    end: ended,
+                    samples,
+                }
+            };
+            //blocks while enough is decoded ahead, fails once the source is dropped
+            if self.chunks.send(chunk).is_err() {
+                return;
+            }
+        }
+    }
+
+    //a failed seek (e.g. past the end) ends the stream
+    fn seek(&mut self, frame: u64) -> bool {
+        self.frame = frame;
+        self.decoder
+            .try_seek(frames_to_duration(frame, self.sample_rate))
+            .is_ok()
+    }
+
+    //empty once the file (and no loop) is over
+    fn decode_chunk(&mut self) -> Vec<f32> {
+        let channels = self.channels.max(1) as usize;
+        let mut samples = Vec::with_capacity(STREAM_CHUNK);
+        let mut looped = false;
+        //whole frames only, a loop must never split one
+        while samples.len() + channels <= STREAM_CHUNK {
+            let mut frames = ((STREAM_CHUNK - samples.len()) / channels) as u64;
+            if let Some(LoopPoints { end: Some(end), .. }) = self.loop_points {
+                frames = frames.min(end.saturating_sub(self.frame));
+            }
+            let before = samples.len();
+            samples.extend(self.decoder.by_ref().take(frames as usize * channels));
+            let decoded = ((samples.len() - before) / channels) as u64;
+            self.frame += decoded;
+
+            let Some(loop_points) = self.loop_points else {
+                break;
+            };
+            let at_end = match loop_points.end {
+                Some(end) => self.frame >= end || decoded < frames,
+                None => decoded < frames,
+            };
+            if !at_end {
+                continue;
+            }
+            //nothing decoded since the last jump, the loop is empty or broken
+            if looped && decoded == 0 {
+                break;
+            }
+            if !self.seek(loop_points.start) {
+                break;
+            }
+            looped = true;
+        }
+        samples
+    }
+}
+
+impl Iterator for StreamingSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(position) = self.head_position {
+            if let Some(sample) = self.head.get(position) {
+                self.head_position = Some(position + 1);
+                return Some(*sample);
+            }
+            self.head_position = None;
+        }
+        while self.position >= self.current.len() {
+            if self.end {
+                return None;
+            }
+            self.next_chunk()?;
+        }
+        let sample = self.current[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for StreamingSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    //None for looped files
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+
+    //never waits, the thread picks the seek up with its next chunk
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.generation += 1;
+        self.current.clear();
+        self.position = 0;
+        self.end = false;
+
+        let frame = (pos.as_secs_f64() * self.sample_rate as f64) as usize;
+        let head_frames = self.head.len() / self.channels.max(1) as usize;
+        self.head_position = if frame < head_frames {
+            Some(frame * self.channels as usize)
+        } else {
+            None
+        };
+        //the thread only ends when the source is dropped, so this can't fail here
+        let _ = self.seeks.send((self.generation, pos));
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+//plain arrays so age_audio doesn't need a math crate, cgmath's Point3/Vector3 convert into them
+pub type Vec3 = [f32; 3];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attenuation {
+    //same volume everywhere, only panning and doppler
+    None,
+    //silent at max_distance
+    Linear,
+    //like the real world, halves with double the distance (with rolloff 1)
+    Inverse,
+    Exponential,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialSettings {
+    pub attenuation: Attenuation,
+    //full volume up to here
+    pub min_distance: f32,
+    //no further attenuation after this
+    pub max_distance: f32,
+    pub rolloff: f32,
+    //0.0 disables the doppler shift
+    pub doppler_factor: f32,
+    //units per second, 343 if 1 unit = 1 meter
+    pub speed_of_sound: f32,
+}
+
+impl Default for SpatialSettings {
+    fn default() -> Self {
+        Self {
+            attenuation: Attenuation::Inverse,
+            min_distance: 1.0,
+            max_distance: 100.0,
+            rolloff: 1.0,
+            doppler_factor: 0.0,
+            speed_of_sound: 343.0,
+        }
+    }
+}
+
+impl SpatialSettings {
+    pub fn gain(&self, distance: f32) -> f32 {
+        let min = self.min_distance.max(0.0001);
+        let max = self.max_distance.max(min);
+        let distance = distance.clamp(min, max);
+        let gain = match self.attenuation {
+            Attenuation::None => 1.0,
+            Attenuation::Linear => {
+                if max == min {
+                    1.0
+                } else {
+                    1.0 - self.rolloff * (distance - min) / (max - min)
+                }
+            }
+            Attenuation::Inverse => min / (min + self.rolloff * (distance - min)),
+            Attenuation::Exponential => (distance / min).powf(-self.rolloff),
+        };
+        gain.clamp(0.0, 1.0)
+    }
+}
+
+//the "ears", usually the render camera: Listener::new(camera.position, camera.forward(), camera.up())
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Listener {
+    pub position: Vec3,
+    pub forward: Vec3,
+    pub up: Vec3,
+    pub velocity: Vec3,
+}
+
+impl Listener {
+    pub fn new<P: Into<Vec3>, F: Into<Vec3>, U: Into<Vec3>>(
+        position: P,
+        forward: F,
+        up: U,
+    ) -> Self {
+        Self {
+            position: position.into(),
+            forward: forward.into(),
+            up: up.into(),
+            velocity: [0.0; 3],
+        }
+    }
+
+    pub fn with_velocity<V: Into<Vec3>>(mut self, velocity: V) -> Self {
+        self.velocity = velocity.into();
+        self
+    }
+
+    pub fn right(&self) -> Vec3 {
+        normalize(cross(self.forward, self.up))
+    }
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self::new([0.0; 3], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emitter {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub settings: SpatialSettings,
+}
+
+impl Emitter {
+    pub fn new<P: Into<Vec3>>(position: P) -> Self {
+        Self {
+            position: position.into(),
+            velocity: [0.0; 3],
+            settings: SpatialSettings::default(),
+        }
+    }
+
+    pub fn with_velocity<V: Into<Vec3>>(mut self, velocity: V) -> Self {
+        self.velocity = velocity.into();
+        self
+    }
+
+    pub fn with_settings(mut self, settings: SpatialSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SpatialParams {
+    pub(crate) gain: f32,
+    pub(crate) pan: f32,
+    pub(crate) doppler: f32,
+}
+
+impl Default for SpatialParams {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            pan: 0.0,
+            doppler: 1.0,
+        }
+    }
+}
+
+pub(crate) fn compute(listener: &Listener, emitter: &Emitter) -> SpatialParams {
+    let offset = sub(emitter.position, listener.position);
+    let distance = length(offset);
+    //sitting right on the listener: no direction, so centered
+    if distance < 0.0001 {
+        return SpatialParams {
+            gain: emitter.settings.gain(0.0),
+            ..Default::default()
+        };
+    }
+    let direction = scale(offset, 1.0 / distance);
+
+    let settings = &emitter.settings;
+    let mut doppler = 1.0;
+    if settings.doppler_factor > 0.0 && settings.speed_of_sound > 0.0 {
+        let speed_of_sound = settings.speed_of_sound;
+        //both positive when moving towards each other
+        let listener_speed = dot(listener.velocity, direction) * settings.doppler_factor;
+        let emitter_speed = -dot(emitter.velocity, direction) * settings.doppler_factor;
+        let listener_speed = listener_speed.clamp(-speed_of_sound * 0.5, speed_of_sound * 0.5);
+        let emitter_speed = emitter_speed.clamp(-speed_of_sound * 0.5, speed_of_sound * 0.5);
+        doppler = (speed_of_sound + listener_speed) / (speed_of_sound - emitter_speed);
+    }
+
+    SpatialParams {
+        gain: settings.gain(distance),
+        pan: dot(direction, listener.right()).clamp(-1.0, 1.0),
+        doppler: doppler.clamp(0.5, 2.0),
+    }
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: Vec3, factor: f32) -> Vec3 {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn length(a: Vec3) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: Vec3) -> Vec3 {
+    let length = length(a);
+    if length == 0.0 {
+        return a;
+    }
+    scale(a, 1.0 / length)
+}
@@ -0,0 +1,515 @@
+use rodio::Source;
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+//what Patch::source uses, OutputHandle::play_patch_on uses the output's rate instead
+pub const SYNTH_SAMPLE_RATE: u32 = 44100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,
+    Saw,
+    Triangle,
+    //white noise, the frequency is ignored
+    Noise,
+}
+
+impl Waveform {
+    //phase from 0 to 1
+    fn sample(self, phase: f32, noise: &mut u32) -> f32 {
+        match self {
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Saw => 2.0 * phase - 1.0,
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Noise => {
+                //xorshift
+                *noise ^= *noise << 13;
+                *noise ^= *noise >> 17;
+                *noise ^= *noise << 5;
+                *noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+            }
+        }
+    }
+}
+
+//frequency modulation with a sine, the classic metallic/bell sounds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fm {
+    //modulator frequency = carrier frequency * ratio
+    pub ratio: f32,
+    //how strong, 0 = no modulation, above ~10 it gets noisy
+    pub index: f32,
+}
+
+//mono, never ends, limit it with an Envelope or rodio's take_duration
+#[derive(Debug, Clone)]
+pub struct Oscillator {
+    waveform: Waveform,
+    frequency: f32,
+    sample_rate: u32,
+    phase: f32,
+    fm: Option<Fm>,
+    fm_phase: f32,
+    //frequency multiplier per sample and where to stop
+    sweep: Option<(f32, f32)>,
+    noise: u32,
+}
+
+impl Oscillator {
+    pub fn new(waveform: Waveform, frequency: f32, sample_rate: u32) -> Self {
+        Self {
+            waveform,
+            frequency,
+            sample_rate,
+            phase: 0.0,
+            fm: None,
+            fm_phase: 0.0,
+            sweep: None,
+            noise: 0x9e37_79b9,
+        }
+    }
+
+    pub fn with_fm(mut self, fm: Fm) -> Self {
+        self.fm = Some(fm);
+        self
+    }
+
+    //exponential, sounds even to the ear, e.g. 880 -> 220 for a falling "pew"
+    pub fn with_sweep(mut self, to_frequency: f32, duration: Duration) -> Self {
+        let samples = (duration.as_secs_f32() * self.sample_rate as f32).max(1.0);
+        let factor = (to_frequency / self.frequency).powf(1.0 / samples);
+        self.sweep = Some((factor, to_frequency));
+        self
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+}
+
+impl Iterator for Oscillator {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let mut phase = self.phase;
+        if let Some(fm) = self.fm {
+            let modulator = (self.fm_phase * TAU).sin() * fm.index;
+            phase = (phase + modulator / TAU).rem_euclid(1.0);
+            self.fm_phase =
+                (self.fm_phase + self.frequency * fm.ratio / self.sample_rate as f32).fract();
+        }
+        let sample = self.waveform.sample(phase, &mut self.noise);
+
+        self.phase = (self.phase + self.frequency / self.sample_rate as f32).fract();
+        if let Some((factor, target)) = self.sweep {
+            self.frequency *= factor;
+            let reached = (factor > 1.0 && self.frequency >= target)
+                || (factor < 1.0 && self.frequency <= target);
+            if reached {
+                self.frequency = target;
+                self.sweep = None;
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl Source for Oscillator {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adsr {
+    pub attack: Duration,
+    pub decay: Duration,
+    //level while the note is held, 0 to 1
+    pub sustain: f32,
+    pub release: Duration,
+}
+
+impl Adsr {
+    pub fn new(attack: Duration, decay: Duration, sustain: f32, release: Duration) -> Self {
+        Self {
+            attack,
+            decay,
+            sustain,
+            release,
+        }
+    }
+
+    //for short percussive sounds
+    pub fn pluck(decay: Duration) -> Self {
+        Self::new(Duration::from_millis(2), decay, 0.0, Duration::ZERO)
+    }
+
+    //level `time` seconds after the note started, the note is released after `length` seconds
+    pub fn gain(&self, time: f32, length: f32) -> f32 {
+        if time < length {
+            return self.held_gain(time);
+        }
+        let release = self.release.as_secs_f32();
+        if release <= 0.0 {
+            return 0.0;
+        }
+        self.held_gain(length) * (1.0 - (time - length) / release).max(0.0)
+    }
+
+    fn held_gain(&self, time: f32) -> f32 {
+        let attack = self.attack.as_secs_f32();
+        if time < attack {
+            return time / attack;
+        }
+        let decay = self.decay.as_secs_f32();
+        if time < attack + decay {
+            return 1.0 - (1.0 - self.sustain) * (time - attack) / decay;
+        }
+        self.sustain
+    }
+}
+
+impl Default for Adsr {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_millis(5),
+            Duration::from_millis(50),
+            0.7,
+            Duration::from_millis(100),
+        )
+    }
+}
+
+//plays `length` of the source with the envelope on it, then the release, then ends
+pub struct Envelope<S> {
+    inner: S,
+    adsr: Adsr,
+    length: f32,
+    //in frames
+    frame: u64,
+    frame_position: u16,
+    gain: f32,
+}
+
+impl<S: Source> Envelope<S> {
+    pub fn new(inner: S, adsr: Adsr, length: Duration) -> Self {
+        Self {
+            inner,
+            adsr,
+            length: length.as_secs_f32(),
+            frame: 0,
+            frame_position: 0,
+            gain: 0.0,
+        }
+    }
+
+    fn end(&self) -> f32 {
+        self.length + self.adsr.release.as_secs_f32()
+    }
+}
+
+impl<S: Source> Iterator for Envelope<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_position == 0 {
+            let time = self.frame as f32 / self.inner.sample_rate() as f32;
+            if time >= self.end() {
+                return None;
+            }
+            self.gain = self.adsr.gain(time, self.length);
+            self.frame += 1;
+        }
+        self.frame_position = (self.frame_position + 1) % self.inner.channels();
+        Some(self.inner.next()? * self.gain)
+    }
+}
+
+impl<S: Source> Source for Envelope<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(self.end()))
+    }
+}
+
+//one note of a patch
+#[derive(Debug, Clone, PartialEq)]
+pub struct Voice {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    //over the whole voice, including the release
+    pub sweep_to: Option<f32>,
+    pub fm: Option<Fm>,
+    pub envelope: Adsr,
+    //how long the note is held, the release comes on top
+    pub length: Duration,
+    pub volume: f32,
+}
+
+impl Voice {
+    pub fn new(waveform: Waveform, frequency: f32) -> Self {
+        Self {
+            waveform,
+            frequency,
+            sweep_to: None,
+            fm: None,
+            envelope: Adsr::default(),
+            length: Duration::from_millis(100),
+            volume: 0.5,
+        }
+    }
+
+    pub fn with_sweep(mut self, to_frequency: f32) -> Self {
+        self.sweep_to = Some(to_frequency);
+        self
+    }
+
+    pub fn with_fm(mut self, ratio: f32, index: f32) -> Self {
+        self.fm = Some(Fm { ratio, index });
+        self
+    }
+
+    pub fn with_envelope(mut self, envelope: Adsr) -> Self {
+        self.envelope = envelope;
+        self
+    }
+
+    pub fn with_length(mut self, length: Duration) -> Self {
+        self.length = length;
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.length + self.envelope.release
+    }
+
+    pub fn source(&self, sample_rate: u32) -> Envelope<Oscillator> {
+        let mut oscillator = Oscillator::new(self.waveform, self.frequency, sample_rate);
+        if let Some(fm) = self.fm {
+            oscillator = oscillator.with_fm(fm);
+        }
+        if let Some(to_frequency) = self.sweep_to {
+            oscillator = oscillator.with_sweep(to_frequency, self.duration());
+        }
+        Envelope::new(oscillator, self.envelope, self.length)
+    }
+}
+
+//a few voices layered and/or after each other, e.g. the two notes of a coin pickup
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch {
+    //start offset and voice
+    voices: Vec<(Duration, Voice)>,
+}
+
+impl Patch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //starts together with the first voice
+    pub fn with(self, voice: Voice) -> Self {
+        self.at(Duration::ZERO, voice)
+    }
+
+    //starts when everything before it ended
+    pub fn then(self, voice: Voice) -> Self {
+        let start = self.duration();
+        self.at(start, voice)
+    }
+
+    pub fn at(mut self, offset: Duration, voice: Voice) -> Self {
+        self.voices.push((offset, voice));
+        self
+    }
+
+    pub fn voices(&self) -> &[(Duration, Voice)] {
+        &self.voices
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.voices
+            .iter()
+            .map(|(offset, voice)| *offset + voice.duration())
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub fn source(&self) -> PatchSource {
+        self.source_with_rate(SYNTH_SAMPLE_RATE)
+    }
+
+    pub fn source_with_rate(&self, sample_rate: u32) -> PatchSource {
+        let voices = self
+            .voices
+            .iter()
+            .map(|(offset, voice)| PatchVoice {
+                start: (offset.as_secs_f32() * sample_rate as f32) as u64,
+                volume: voice.volume,
+                source: voice.source(sample_rate),
+                done: false,
+            })
+            .collect();
+        PatchSource {
+            voices,
+            sample_rate,
+            frame: 0,
+        }
+    }
+
+    pub fn blip() -> Self {
+        Patch::new().with(
+            Voice::new(Waveform::Square, 880.0)
+                .with_envelope(Adsr::pluck(Duration::from_millis(60)))
+                .with_length(Duration::from_millis(60))
+                .with_volume(0.3),
+        )
+    }
+
+    pub fn coin() -> Self {
+        let note = |frequency, length| {
+            Voice::new(Waveform::Square, frequency)
+                .with_envelope(Adsr::new(
+                    Duration::from_millis(1),
+                    Duration::ZERO,
+                    1.0,
+                    Duration::from_millis(30),
+                ))
+                .with_length(Duration::from_millis(length))
+                .with_volume(0.3)
+        };
+        Patch::new().with(note(987.77, 60)).then(note(1318.51, 250))
+    }
+
+    pub fn laser() -> Self {
+        Patch::new().with(
+            Voice::new(Waveform::Saw, 1600.0)
+                .with_sweep(200.0)
+                .with_envelope(Adsr::pluck(Duration::from_millis(200)))
+                .with_length(Duration::from_millis(200))
+                .with_volume(0.3),
+        )
+    }
+
+    pub fn explosion() -> Self {
+        Patch::new().with(
+            Voice::new(Waveform::Noise, 0.0)
+                .with_envelope(Adsr::new(
+                    Duration::from_millis(5),
+                    Duration::from_millis(600),
+                    0.0,
+                    Duration::ZERO,
+                ))
+                .with_length(Duration::from_millis(600))
+                .with_volume(0.6),
+        )
+    }
+
+    pub fn bell() -> Self {
+        Patch::new().with(
+            Voice::new(Waveform::Sine, 660.0)
+                .with_fm(3.5, 2.0)
+                .with_envelope(Adsr::pluck(Duration::from_millis(1200)))
+                .with_length(Duration::from_millis(1200))
+                .with_volume(0.4),
+        )
+    }
+}
+
+struct PatchVoice {
+    //in frames
+    start: u64,
+    volume: f32,
+    source: Envelope<Oscillator>,
+    done: bool,
+}
+
+//mono, ends after the last voice
+pub struct PatchSource {
+    voices: Vec<PatchVoice>,
+    sample_rate: u32,
+    frame: u64,
+}
+
+impl Iterator for PatchSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.voices.iter().all(|voice| voice.done) {
+            return None;
+        }
+        let mut sum = 0.0;
+        for voice in &mut self.voices {
+            if voice.done || self.frame < voice.start {
+                continue;
+            }
+            match voice.source.next() {
+                Some(sample) => sum += sample * voice.volume,
+                None => voice.done = true,
+            }
+        }
+        self.frame += 1;
+        Some(sum)
+    }
+}
+
+impl Source for PatchSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.voices
+            .iter()
+            .filter_map(|voice| {
+                let start = Duration::from_secs_f64(voice.start as f64 / self.sample_rate as f64);
+                Some(start + voice.source.total_duration()?)
+            })
+            .max()
+    }
+}
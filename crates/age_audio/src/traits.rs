@@ -1,8 +1,13 @@
 //maybe I will remove them idk
 pub mod marker {
+    use crate::input_handle::input_markers;
     use crate::output_handle::output_markers;
 
     pub trait OutputHandlerState {}
     impl OutputHandlerState for output_markers::OutputDisabled {}
     impl OutputHandlerState for output_markers::OutputEnabled {}
+
+    pub trait InputHandlerState {}
+    impl InputHandlerState for input_markers::InputDisabled {}
+    impl InputHandlerState for input_markers::InputEnabled {}
 }
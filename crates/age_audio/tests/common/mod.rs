@@ -0,0 +1,26 @@
+//shared by the integration tests, each test binary only uses part of it
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+pub const SAMPLE_RATE: u32 = 44100;
+//what 16384 in a 16 bit file decodes to
+pub const LEVEL: f32 = 0.5;
+
+//mono, constant level, so every rendered sample can be checked directly
+//name has to be unique across the tests, the file is written to the temp directory
+pub fn write_test_wav(name: &str, seconds: f32) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("age_audio_{}_{}.wav", name, std::process::id()));
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for _ in 0..(seconds * SAMPLE_RATE as f32) as usize {
+        writer.write_sample(16384i16).unwrap();
+    }
+    writer.finalize().unwrap();
+    path
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "ecs")]
+
+use age_audio::ecs::{
+    AudioFailed, AudioOutput, AudioSet, AudioSource, PlayingSound, add_audio_systems,
+};
+use age_audio::output::OutputBackend;
+use age_audio::output_handle::OutputHandle;
+use bevy_ecs::prelude::*;
+
+mod common;
+use common::write_test_wav;
+
+fn world_with_output(name: &str) -> (World, Schedule) {
+    let mut output = OutputHandle::new();
+    output
+        .load_file(
+            write_test_wav(&format!("ecs_{name}"), 0.1),
+            "test".to_string(),
+        )
+        .unwrap();
+    let mut world = World::new();
+    world.insert_resource(output.activate_with(OutputBackend::null()).unwrap());
+    let mut schedule = Schedule::default();
+    add_audio_systems(&mut schedule);
+    (world, schedule)
+}
+
+#[test]
+fn spawned_sources_play_and_one_shots_despawn() {
+    let (mut world, mut schedule) = world_with_output("one_shot");
+    let entity = world.spawn(AudioSource::loaded("test").one_shot()).id();
+    schedule.run(&mut world);
+    assert!(world.get::<PlayingSound>(entity).is_some());
+
+    let samples = world
+        .resource_mut::<AudioOutput>()
+        .render(44100)
+        .unwrap()
+        .to_vec();
+    assert!(samples.iter().any(|sample| *sample != 0.0));
+    schedule.run(&mut world);
+    assert!(world.get_entity(entity).is_err());
+}
+
+#[test]
+fn removing_the_source_stops_the_sound() {
+    let (mut world, mut schedule) = world_with_output("remove");
+    let entity = world.spawn(AudioSource::loaded("test").looping()).id();
+    schedule.run(&mut world);
+    let handle = world.get::<PlayingSound>(entity).unwrap().handle().clone();
+    assert!(!handle.is_finished());
+
+    world.entity_mut(entity).remove::<AudioSource>();
+    schedule.run(&mut world);
+    world.resource_mut::<AudioOutput>().render(1024).unwrap();
+    assert!(handle.is_finished());
+    assert!(world.get::<PlayingSound>(entity).is_none());
+}
+
+#[test]
+fn missing_files_are_reported_once() {
+    let (mut world, mut schedule) = world_with_output("missing");
+    let entity = world.spawn(AudioSource::loaded("not loaded")).id();
+    schedule.run(&mut world);
+    assert!(world.get::<AudioFailed>(entity).is_some());
+    assert!(world.get::<PlayingSound>(entity).is_none());
+}
+
+#[derive(Resource)]
+struct Spawned(Entity);
+
+#[test]
+fn systems_before_the_audio_set_are_heard_the_same_frame() {
+    let (mut world, mut schedule) = world_with_output("same_frame");
+    schedule.add_systems(
+        (|mut commands: Commands| {
+            let entity = commands.spawn(AudioSource::loaded("test")).id();
+            commands.insert_resource(Spawned(entity));
+        })
+        .before(AudioSet),
+    );
+    schedule.run(&mut world);
+    let entity = world.resource::<Spawned>().0;
+    assert!(world.get::<PlayingSound>(entity).is_some());
+}
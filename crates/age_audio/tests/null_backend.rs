@@ -0,0 +1,527 @@
+use age_audio::channel::Channel;
+use age_audio::effects::{Delay, Effect, HighPass, LowPass, Reverb};
+use age_audio::errors::AudioError;
+use age_audio::music::{Layer, MusicPlayer, Track};
+use age_audio::output::OutputBackend;
+use age_audio::output_handle::OutputHandle;
+use age_audio::output_handle::output_markers::OutputEnabled;
+use age_audio::source::{LoadMode, LoopPoints};
+use age_audio::synth::{Adsr, Patch, Voice, Waveform};
+use std::time::Duration;
+
+mod common;
+use common::{LEVEL, SAMPLE_RATE, write_test_wav};
+
+fn null_handle(name: &str, seconds: f32) -> OutputHandle<OutputEnabled> {
+    let mut handle = OutputHandle::new();
+    handle
+        .load_file(write_test_wav(name, seconds), "test".to_string())
+        .unwrap();
+    handle
+        .activate_with(OutputBackend::Null {
+            channels: 2,
+            sample_rate: SAMPLE_RATE,
+        })
+        .unwrap()
+}
+
+fn count_near(samples: &[f32], value: f32) -> usize {
+    samples
+        .iter()
+        .filter(|sample| (**sample - value).abs() < 0.001)
+        .count()
+}
+
+#[test]
+fn play_loaded_renders_the_file() {
+    let mut handle = null_handle("plays", 0.25);
+    let sound = handle.play_loaded("test".to_string()).unwrap();
+
+    let samples = handle.render(SAMPLE_RATE as usize).unwrap().to_vec();
+    assert_eq!(samples.len(), SAMPLE_RATE as usize * 2);
+    //0.25 seconds of stereo at the level of the file, a few samples of latency are fine
+    let expected = (0.25 * SAMPLE_RATE as f32) as usize * 2;
+    let played = count_near(&samples, LEVEL);
+    assert!(played.abs_diff(expected) < 2048, "{played} vs {expected}");
+    //and silence after it
+    assert!(
+        samples[samples.len() - 1024..]
+            .iter()
+            .all(|sample| *sample == 0.0)
+    );
+    assert!(sound.is_finished());
+}
+
+#[test]
+fn nothing_plays_without_sounds() {
+    let mut handle = null_handle("silence", 0.1);
+    let samples = handle.render(4096).unwrap();
+    assert!(samples.iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn one_shots_overlap() {
+    let mut handle = null_handle("overlap", 0.5);
+    handle.play_loaded("test".to_string()).unwrap();
+    handle.play_loaded("test".to_string()).unwrap();
+
+    let samples = handle.render(SAMPLE_RATE as usize / 4).unwrap();
+    assert!(count_near(samples, LEVEL * 2.0) > SAMPLE_RATE as usize / 4);
+}
+
+#[test]
+fn channel_volume_is_applied() {
+    let mut handle = null_handle("volume", 0.5);
+    handle.set_channel_volume(Channel::Sfx, 0.5);
+    handle.set_volume(0.5);
+    handle.play_loaded("test".to_string()).unwrap();
+
+    let samples = handle.render(SAMPLE_RATE as usize / 4).unwrap();
+    assert!(count_near(samples, LEVEL * 0.25) > SAMPLE_RATE as usize / 4);
+    assert_eq!(count_near(samples, LEVEL), 0);
+}
+
+#[test]
+fn sound_volume_and_pan() {
+    let mut handle = null_handle("pan", 0.5);
+    let sound = handle.play_loaded("test".to_string()).unwrap();
+    sound.set_volume(0.5);
+    sound.set_pan(-1.0);
+
+    let samples = handle.render(SAMPLE_RATE as usize / 4).unwrap();
+    let (left, right): (Vec<f32>, Vec<f32>) =
+        samples.chunks(2).map(|frame| (frame[0], frame[1])).unzip();
+    assert!(count_near(&left, LEVEL * 0.5) > SAMPLE_RATE as usize / 8);
+    //the pan is picked up after a few frames
+    assert!(right[1024..].iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn paused_output_is_silent_and_continues() {
+    let mut handle = null_handle("pause", 0.25);
+    let sound = handle.play_loaded("test".to_string()).unwrap();
+    handle.pause();
+    assert!(handle.is_paused());
+
+    let samples = handle.render(SAMPLE_RATE as usize).unwrap();
+    assert!(samples.iter().all(|sample| *sample == 0.0));
+    assert!(!sound.is_finished());
+
+    handle.play();
+    let samples = handle.render(SAMPLE_RATE as usize).unwrap();
+    assert!(count_near(samples, LEVEL) > SAMPLE_RATE as usize / 4);
+}
+
+#[test]
+fn stopped_sound_ends() {
+    let mut handle = null_handle("stop", 1.0);
+    let sound = handle.play_loaded("test".to_string()).unwrap();
+    handle.render(1024).unwrap();
+    sound.stop();
+
+    let samples = handle.render(SAMPLE_RATE as usize / 2).unwrap();
+    assert!(
+        samples[samples.len() - 1024..]
+            .iter()
+            .all(|sample| *sample == 0.0)
+    );
+    assert!(sound.is_finished());
+}
+
+#[test]
+fn looping_sound_keeps_playing() {
+    let mut handle = null_handle("loop", 0.1);
+    let sound = handle.play_loaded("test".to_string()).unwrap();
+    sound.set_looping(true);
+
+    let samples = handle.render(SAMPLE_RATE as usize / 2).unwrap();
+    assert!(
+        samples[samples.len() - 1024..]
+            .iter()
+            .all(|sample| (*sample - LEVEL).abs() < 0.001)
+    );
+    assert!(sound.is_playing());
+}
+
+#[test]
+fn muted_and_solo_channels() {
+    let mut handle = null_handle("solo", 0.5);
+    handle.set_channel_muted(Channel::Sfx, true);
+    handle.play_loaded("test".to_string()).unwrap();
+    let samples = handle.render(SAMPLE_RATE as usize / 4).unwrap();
+    assert!(samples.iter().all(|sample| *sample == 0.0));
+
+    handle.set_channel_muted(Channel::Sfx, false);
+    handle.set_channel_solo(Channel::Music, true);
+    assert!(handle.is_channel_solo(&Channel::Music));
+    let samples = handle.render(SAMPLE_RATE as usize / 8).unwrap();
+    assert!(
+        samples[samples.len() - 1024..]
+            .iter()
+            .all(|sample| *sample == 0.0)
+    );
+}
+
+#[test]
+fn nested_channels() {
+    let mut handle = null_handle("nested", 0.5);
+    let footsteps = Channel::Custom("footsteps".to_string());
+    handle.add_channel(footsteps.clone(), Channel::Sfx).unwrap();
+    assert_eq!(handle.channel_parent(&footsteps), Some(&Channel::Sfx));
+    assert!(matches!(
+        handle.add_channel(footsteps.clone(), Channel::Sfx),
+        Err(AudioError::ChannelAlreadyExists(_))
+    ));
+
+    //the parent's volume applies to the child
+    handle.set_channel_volume(Channel::Sfx, 0.5);
+    handle
+        .play_loaded_on(footsteps, "test".to_string())
+        .unwrap();
+    let samples = handle.render(SAMPLE_RATE as usize / 4).unwrap();
+    assert!(count_near(samples, LEVEL * 0.5) > SAMPLE_RATE as usize / 4);
+}
+
+#[test]
+fn effects_can_be_changed_and_removed() {
+    let mut handle = null_handle("effects", 0.5);
+    let effect = handle.add_effect(Channel::Sfx, HighPass::new(1000.0));
+    handle.play_loaded("test".to_string()).unwrap();
+
+    //a constant level is DC, the high pass removes it
+    let samples = handle.render(SAMPLE_RATE as usize / 4).unwrap();
+    assert!(
+        samples[samples.len() - 1024..]
+            .iter()
+            .all(|sample| sample.abs() < 0.01)
+    );
+
+    handle
+        .update_effect(&Channel::Sfx, effect, |high_pass: &mut HighPass| {
+            high_pass.cutoff = 20.0
+        })
+        .unwrap();
+    assert!(matches!(
+        handle.update_effect(&Channel::Sfx, effect, |_: &mut LowPass| {}),
+        Err(AudioError::EffectNotFound(_))
+    ));
+
+    handle.remove_effect(&Channel::Sfx, effect).unwrap();
+    let samples = handle.render(SAMPLE_RATE as usize / 8).unwrap();
+    assert!(count_near(samples, LEVEL) > 1024);
+}
+
+#[test]
+fn effects_only_process_the_format_they_were_prepared_for() {
+    //wet only, so the first 10ms of a delay are silent
+    let mut delay = Delay::new(0.01, 0.0, 1.0);
+    let mut reverb = Reverb::new(0.5, 0.5, 1.0);
+    let mut samples = vec![LEVEL; 2 * 441];
+    delay.process(&mut samples, 2, SAMPLE_RATE);
+    reverb.process(&mut samples, 2, SAMPLE_RATE);
+    assert!(samples.iter().all(|sample| *sample == LEVEL));
+
+    delay.prepare(2, SAMPLE_RATE);
+    delay.process(&mut samples, 2, SAMPLE_RATE);
+    assert!(samples[..2 * 400].iter().all(|sample| *sample == 0.0));
+
+    //a bus created after add_effect prepares it too
+    let mut handle = OutputHandle::new();
+    handle.add_effect(Channel::Sfx, Delay::new(0.1, 0.0, 1.0));
+    handle
+        .load_file(write_test_wav("prepared", 0.5), "test".to_string())
+        .unwrap();
+    let mut handle = handle
+        .activate_with(OutputBackend::Null {
+            channels: 2,
+            sample_rate: SAMPLE_RATE,
+        })
+        .unwrap();
+    handle.play_loaded("test".to_string()).unwrap();
+    let samples = handle.render(SAMPLE_RATE as usize / 20).unwrap();
+    assert!(samples.iter().all(|sample| sample.abs() < 0.001));
+    let samples = handle.render(SAMPLE_RATE as usize / 10).unwrap();
+    assert!(count_near(samples, LEVEL) > 1024);
+}
+
+#[test]
+fn wav_backend_writes_what_was_rendered() {
+    let path = std::env::temp_dir().join(format!("age_audio_output_{}.wav", std::process::id()));
+    let mut handle = OutputHandle::new();
+    handle
+        .load_file(write_test_wav("wav", 0.1), "test".to_string())
+        .unwrap();
+    let mut handle = handle
+        .activate_with(OutputBackend::Wav {
+            path: path.clone(),
+            channels: 2,
+            sample_rate: SAMPLE_RATE,
+        })
+        .unwrap();
+    handle.play_loaded("test".to_string()).unwrap();
+    handle.render(SAMPLE_RATE as usize / 2).unwrap();
+    //closes the file
+    drop(handle.disable_output());
+
+    let reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().channels, 2);
+    assert_eq!(reader.spec().sample_rate, SAMPLE_RATE);
+    let samples = reader
+        .into_samples::<f32>()
+        .collect::<Result<Vec<f32>, _>>()
+        .unwrap();
+    assert_eq!(samples.len(), SAMPLE_RATE as usize);
+    assert!(count_near(&samples, LEVEL) > SAMPLE_RATE as usize / 10);
+}
+
+#[test]
+fn decoded_files_play_like_encoded_ones() {
+    let mut handle = OutputHandle::new();
+    handle
+        .load_file_with(
+            write_test_wav("decoded", 0.25),
+            "test".to_string(),
+            LoadMode::Decoded,
+        )
+        .unwrap();
+    let mut handle = handle.activate_with(OutputBackend::null()).unwrap();
+    handle.play_loaded("test".to_string()).unwrap();
+    handle.play_loaded("test".to_string()).unwrap();
+
+    let samples = handle.render(SAMPLE_RATE as usize).unwrap();
+    let expected = (0.25 * SAMPLE_RATE as f32) as usize * 2;
+    let played = count_near(samples, LEVEL * 2.0);
+    assert!(played.abs_diff(expected) < 2048, "{played} vs {expected}");
+}
+
+#[test]
+fn streamed_file_plays_completely() {
+    let path = write_test_wav("streamed", 1.0);
+    let mut handle = OutputHandle::new()
+        .activate_with(OutputBackend::null())
+        .unwrap();
+    let sound = handle.play_streamed(path).unwrap();
+
+    //small blocks with pauses, like a real device, so the streaming thread can keep up
+    let mut played = 0;
+    for _ in 0..64 {
+        played += count_near(handle.render(1024).unwrap(), LEVEL);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    let expected = SAMPLE_RATE as usize * 2;
+    assert!(played.abs_diff(expected) < 2048, "{played} vs {expected}");
+    assert!(sound.is_finished());
+}
+
+//renders in small blocks with pauses, so streamed music and MusicPlayer::update keep up
+fn render_music(
+    handle: &mut OutputHandle<OutputEnabled>,
+    player: &mut MusicPlayer,
+    seconds: f32,
+) -> Vec<f32> {
+    let mut samples = Vec::new();
+    for _ in 0..(seconds * SAMPLE_RATE as f32) as usize / 1024 {
+        player.update(handle).unwrap();
+        samples.extend_from_slice(handle.render(1024).unwrap());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    samples
+}
+
+#[test]
+fn playlist_plays_tracks_after_each_other() {
+    let mut handle = OutputHandle::new()
+        .activate_with(OutputBackend::null())
+        .unwrap();
+    let mut player = MusicPlayer::new();
+    player.set_crossfade(std::time::Duration::ZERO);
+    player.set_playlist(vec![
+        Track::new(write_test_wav("playlist_a", 0.5)),
+        Track::new(write_test_wav("playlist_b", 0.5)),
+    ]);
+    player.play(&mut handle).unwrap();
+
+    let samples = render_music(&mut handle, &mut player, 1.5);
+    let expected = SAMPLE_RATE as usize * 2;
+    let played = count_near(&samples, LEVEL);
+    assert!(played.abs_diff(expected) < 8192, "{played} vs {expected}");
+    assert_eq!(player.current_track(), None);
+}
+
+#[test]
+fn replacing_the_playlist_remaps_the_current_track() {
+    let mut handle = OutputHandle::new()
+        .activate_with(OutputBackend::null())
+        .unwrap();
+    let a = Track::new(write_test_wav("replace_a", 0.2));
+    let b = Track::new(write_test_wav("replace_b", 0.5));
+    let c = Track::new(write_test_wav("replace_c", 0.5));
+    let mut player = MusicPlayer::new();
+    player.set_crossfade(std::time::Duration::ZERO);
+    player.set_playlist(vec![a.clone(), b.clone()]);
+    player.play(&mut handle).unwrap();
+    assert_eq!(player.current_track(), Some(0));
+
+    player.set_playlist(vec![c.clone(), a.clone()]);
+    assert_eq!(player.current_track(), Some(1));
+
+    //a keeps playing, then the new playlist starts from its beginning
+    player.set_playlist(vec![b, c]);
+    assert_eq!(player.current_track(), None);
+    render_music(&mut handle, &mut player, 0.4);
+    assert_eq!(player.current_track(), Some(0));
+}
+
+#[test]
+fn added_tracks_are_shuffled_in() {
+    let mut handle = OutputHandle::new()
+        .activate_with(OutputBackend::null())
+        .unwrap();
+    let mut player = MusicPlayer::new();
+    player.set_shuffle(true);
+    for i in 0..8 {
+        player.add_track(Track::new(write_test_wav(&format!("shuffled_{i}"), 0.05)));
+    }
+    player.play(&mut handle).unwrap();
+    let mut played = vec![player.current_track().unwrap()];
+    for _ in 1..8 {
+        player.next(&mut handle).unwrap();
+        played.push(player.current_track().unwrap());
+    }
+    let mut sorted = played.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..8).collect::<Vec<_>>());
+    //1 in 40320 to fail by chance
+    assert_ne!(played, sorted);
+}
+
+#[test]
+fn looped_track_keeps_playing() {
+    let mut handle = OutputHandle::new()
+        .activate_with(OutputBackend::null())
+        .unwrap();
+    let mut player = MusicPlayer::new();
+    player.set_playlist(vec![
+        Track::new(write_test_wav("music_loop", 0.3)).with_loop(LoopPoints::new(4410, None)),
+    ]);
+    player.play(&mut handle).unwrap();
+
+    let samples = render_music(&mut handle, &mut player, 1.0);
+    //the end is still playing
+    let tail = &samples[samples.len() - 4096..];
+    assert_eq!(count_near(tail, LEVEL), tail.len());
+    assert_eq!(player.current_track(), Some(0));
+}
+
+#[test]
+fn layers_follow_the_intensity() {
+    let mut handle = OutputHandle::new()
+        .activate_with(OutputBackend::null())
+        .unwrap();
+    let mut player = MusicPlayer::new();
+    player.set_layer_fade(std::time::Duration::from_millis(10));
+    player.set_playlist(vec![
+        Track::layered(vec![
+            Layer::new(write_test_wav("layer_base", 1.0)),
+            Layer::new(write_test_wav("layer_drums", 1.0)).with_intensity(0.5, 0.5),
+        ])
+        .with_loop(LoopPoints::whole()),
+    ]);
+    player.play(&mut handle).unwrap();
+
+    let calm = render_music(&mut handle, &mut player, 0.3);
+    assert!(
+        calm[calm.len() - 1024..]
+            .iter()
+            .all(|sample| (*sample - LEVEL).abs() < 0.001)
+    );
+
+    player.set_intensity(1.0);
+    let intense = render_music(&mut handle, &mut player, 0.3);
+    let tail = &intense[intense.len() - 1024..];
+    assert_eq!(count_near(tail, LEVEL * 2.0), tail.len());
+}
+
+#[test]
+fn patches_play_for_their_duration() {
+    let mut handle = OutputHandle::new()
+        .activate_with(OutputBackend::null())
+        .unwrap();
+    let patch = Patch::new()
+        .with(
+            Voice::new(Waveform::Square, 440.0)
+                .with_envelope(Adsr::new(
+                    Duration::ZERO,
+                    Duration::ZERO,
+                    1.0,
+                    Duration::ZERO,
+                ))
+                .with_length(Duration::from_millis(100))
+                .with_volume(LEVEL),
+        )
+        .then(Voice::new(Waveform::Sine, 880.0).with_length(Duration::from_millis(100)));
+    assert_eq!(patch.duration(), Duration::from_millis(300));
+    let sound = handle.play_patch_on(Channel::Ui, &patch);
+
+    let samples = handle.render(SAMPLE_RATE as usize / 2).unwrap().to_vec();
+    //the square wave only has two levels, 0.1 seconds of stereo plus a bit of latency
+    let first = &samples[..(0.2 * SAMPLE_RATE as f32) as usize * 2];
+    let square = count_near(first, LEVEL) + count_near(first, -LEVEL);
+    let expected = (0.1 * SAMPLE_RATE as f32) as usize * 2;
+    assert!(square.abs_diff(expected) < 1024, "{square} vs {expected}");
+    //something is playing at the end of the second voice
+    let second = (0.25 * SAMPLE_RATE as f32) as usize * 2;
+    assert!(
+        samples[second..second + 512]
+            .iter()
+            .any(|sample| sample.abs() > 0.01)
+    );
+    //and silence after the release and the latency
+    let end = (0.35 * SAMPLE_RATE as f32) as usize * 2;
+    assert!(samples[end..].iter().all(|sample| *sample == 0.0));
+    assert!(sound.is_finished());
+}
+
+#[test]
+fn file_errors_name_the_file() {
+    let mut handle = OutputHandle::new();
+    let missing = std::env::temp_dir().join("age_audio_missing.wav");
+    let error = handle
+        .load_file(&missing, "missing".to_string())
+        .unwrap_err();
+    assert!(matches!(&error, AudioError::IoError(path, _) if *path == missing));
+    assert!(error.to_string().contains("age_audio_missing.wav"));
+
+    //encoded files are only decoded when played
+    let garbage =
+        std::env::temp_dir().join(format!("age_audio_garbage_{}.wav", std::process::id()));
+    std::fs::write(&garbage, b"not a sound").unwrap();
+    handle
+        .load_file_with(&garbage, "garbage".to_string(), LoadMode::Encoded)
+        .unwrap();
+    let mut handle = handle
+        .activate_with(OutputBackend::Null {
+            channels: 2,
+            sample_rate: SAMPLE_RATE,
+        })
+        .unwrap();
+    assert!(matches!(
+        handle.play_loaded("garbage".to_string()),
+        Err(AudioError::DecoderError(path, _)) if path == garbage
+    ));
+    std::fs::remove_file(garbage).unwrap();
+}
+
+#[test]
+fn faded_in_sources_start_silent() {
+    let mut handle = OutputHandle::new()
+        .activate_with(OutputBackend::null())
+        .unwrap();
+    let file = std::fs::File::open(write_test_wav("faded_in", 0.5)).unwrap();
+    let source = rodio::Decoder::try_from(file).unwrap();
+    handle.play_source_faded_in_on(Channel::Music, source, Duration::from_secs(1));
+
+    //the whole first block is already part of the fade
+    let samples = handle.render(256).unwrap();
+    assert!(samples.iter().all(|sample| sample.abs() < LEVEL * 0.01));
+}
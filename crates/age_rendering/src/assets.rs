@@ -0,0 +1,501 @@
+use crate::errors::{ModelError, TextureError, describe};
+use crate::model::Model;
+use crate::resources::{load_model_with, read_texture};
+use crate::texture::Texture;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    //the error's description
+    Failed(String),
+}
+
+enum SlotState<T> {
+    Loading,
+    Loaded(Arc<T>),
+    Failed(String),
+}
+
+struct Slot<T> {
+    path: PathBuf,
+    state: Mutex<SlotState<T>>,
+    ready: Condvar,
+    //for Ready futures
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl<T> Slot<T> {
+    //after the state changed, the state lock must be released already
+    fn notify(&self) {
+        self.ready.notify_all();
+        let wakers = std::mem::take(&mut *self.wakers.lock().expect("Only panics if poisoned"));
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+//a shared reference to an asset that may still be loading
+//the asset (and its GPU memory) is freed when the last handle is dropped
+pub struct Handle<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Handle<T> {
+    //a handle that is loaded from the start, e.g. for assets made in code
+    pub fn loaded<P: Into<PathBuf>>(path: P, asset: T) -> Self {
+        let (handle, pending) = Self::pending(path);
+        pending.finish(Ok(asset));
+        handle
+    }
+
+    //a handle in the Loading state, whoever holds the Pending finishes it
+    pub fn pending<P: Into<PathBuf>>(path: P) -> (Self, Pending<T>) {
+        let slot = Arc::new(Slot {
+            path: path.into(),
+            state: Mutex::new(SlotState::Loading),
+            ready: Condvar::new(),
+            wakers: Mutex::new(Vec::new()),
+        });
+        let pending = Pending {
+            slot: Arc::downgrade(&slot),
+            finished: false,
+        };
+        (Self { slot }, pending)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.slot.path
+    }
+
+    pub fn load_state(&self) -> LoadState {
+        match &*self.slot.state.lock().expect("Only panics if poisoned") {
+            SlotState::Loading => LoadState::Loading,
+            SlotState::Loaded(_) => LoadState::Loaded,
+            SlotState::Failed(error) => LoadState::Failed(error.clone()),
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.load_state() == LoadState::Loaded
+    }
+
+    //None while loading or after it failed
+    pub fn get(&self) -> Option<Arc<T>> {
+        match &*self.slot.state.lock().expect("Only panics if poisoned") {
+            SlotState::Loaded(asset) => Some(asset.clone()),
+            _ => None,
+        }
+    }
+
+    //blocks until loading is done
+    pub fn wait(&self) -> Result<Arc<T>, String> {
+        let mut state = self.slot.state.lock().expect("Only panics if poisoned");
+        loop {
+            match &*state {
+                SlotState::Loading => {
+                    state = self
+                        .slot
+                        .ready
+                        .wait(state)
+                        .expect("Only panics if poisoned")
+                }
+                SlotState::Loaded(asset) => return Ok(asset.clone()),
+                SlotState::Failed(error) => return Err(error.clone()),
+            }
+        }
+    }
+
+    //wait() for async code
+    pub fn ready(&self) -> Ready<T> {
+        Ready {
+            handle: self.clone(),
+        }
+    }
+
+    //swaps in a new version of the asset, every handle sees it from now on (hot reloading)
+    pub fn replace(&self, asset: T) {
+        *self.slot.state.lock().expect("Only panics if poisoned") =
+            SlotState::Loaded(Arc::new(asset));
+        self.slot.notify();
+    }
+
+    //number of handles to the same asset, including this one
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.slot)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+//handles are equal if they point at the same asset
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.slot, &other.slot)
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.slot).hash(state);
+    }
+}
+
+impl<T> Debug for Handle<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Handle")
+            .field("path", &self.slot.path)
+            .field("state", &self.load_state())
+            .finish()
+    }
+}
+
+//resolves once the handle is loaded or failed, see Handle::ready
+pub struct Ready<T> {
+    handle: Handle<T>,
+}
+
+impl<T> Future for Ready<T> {
+    type Output = Result<Arc<T>, String>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let slot = &self.handle.slot;
+        let state = slot.state.lock().expect("Only panics if poisoned");
+        match &*state {
+            //registered while the state is locked, so a finish can't slip in between
+            SlotState::Loading => {
+                let mut wakers = slot.wakers.lock().expect("Only panics if poisoned");
+                if !wakers.iter().any(|waker| waker.will_wake(context.waker())) {
+                    wakers.push(context.waker().clone());
+                }
+                Poll::Pending
+            }
+            SlotState::Loaded(asset) => Poll::Ready(Ok(asset.clone())),
+            SlotState::Failed(error) => Poll::Ready(Err(error.clone())),
+        }
+    }
+}
+
+//the loading side of a Handle, dropping it without finish() fails the handle
+pub struct Pending<T> {
+    //weak, nobody waits for an asset whose handles are all gone
+    slot: Weak<Slot<T>>,
+    finished: bool,
+}
+
+impl<T> Pending<T> {
+    pub fn finish(mut self, result: Result<T, String>) {
+        self.finished = true;
+        self.set(match result {
+            Ok(asset) => SlotState::Loaded(Arc::new(asset)),
+            Err(error) => SlotState::Failed(error),
+        });
+    }
+
+    //false once every handle was dropped, the load can be skipped then
+    pub fn is_wanted(&self) -> bool {
+        self.slot.strong_count() > 0
+    }
+
+    fn set(&self, new_state: SlotState<T>) {
+        if let Some(slot) = self.slot.upgrade() {
+            *slot.state.lock().expect("Only panics if poisoned") = new_state;
+            slot.notify();
+        }
+    }
+}
+
+impl<T> Drop for Pending<T> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.set(SlotState::Failed(
+                "the loader stopped without a result".to_string(),
+            ));
+        }
+    }
+}
+
+//deduplicates assets by path, as long as a handle to it is alive
+pub struct AssetCache<T> {
+    slots: Mutex<HashMap<PathBuf, Weak<Slot<T>>>>,
+}
+
+impl<T> AssetCache<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    //the existing handle for the path, or a new one and the Pending to load it with
+    pub fn get_or_insert<P: AsRef<Path>>(&self, path: P) -> (Handle<T>, Option<Pending<T>>) {
+        let path = path.as_ref();
+        let mut slots = self.slots.lock().expect("Only panics if poisoned");
+        if let Some(slot) = slots.get(path).and_then(Weak::upgrade) {
+            return (Handle { slot }, None);
+        }
+        //good time to forget the dropped ones
+        slots.retain(|_, slot| slot.strong_count() > 0);
+        let (handle, pending) = Handle::pending(path);
+        slots.insert(path.to_path_buf(), Arc::downgrade(&handle.slot));
+        (handle, Some(pending))
+    }
+
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<Handle<T>> {
+        let slots = self.slots.lock().expect("Only panics if poisoned");
+        slots
+            .get(path.as_ref())
+            .and_then(Weak::upgrade)
+            .map(|slot| Handle { slot })
+    }
+
+    //paths of the assets that still have handles
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let slots = self.slots.lock().expect("Only panics if poisoned");
+        slots
+            .iter()
+            .filter(|(_, slot)| slot.strong_count() > 0)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    //assets that still have handles
+    pub fn len(&self) -> usize {
+        let slots = self.slots.lock().expect("Only panics if poisoned");
+        slots
+            .values()
+            .filter(|slot| slot.strong_count() > 0)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Default for AssetCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct ServerInner {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    models: AssetCache<Model>,
+    textures: AssetCache<Texture>,
+    //model path -> the MTL and texture files it was built from, for hot reloading
+    model_files: Mutex<HashMap<PathBuf, Vec<PathBuf>>>,
+}
+
+//loads models and textures on background threads, get it from State::assets
+//loading the same path twice gives the same handle while the first one is alive,
+//textures are shared between the models that use them
+#[derive(Clone)]
+pub struct AssetServer {
+    inner: Arc<ServerInner>,
+}
+
+impl AssetServer {
+    pub(crate) fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        texture_bind_group_layout: wgpu::BindGroupLayout,
+    ) -> Self {
+        Self {
+            inner: Arc::new(ServerInner {
+                device,
+                queue,
+                texture_bind_group_layout,
+                models: AssetCache::new(),
+                textures: AssetCache::new(),
+                model_files: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    //OBJ files, the materials and textures are loaded with it
+    pub fn load_model<P: AsRef<Path>>(&self, path: P) -> Handle<Model> {
+        let (handle, pending) = self.inner.models.get_or_insert(path);
+        if let Some(pending) = pending {
+            let server = self.clone();
+            let path = handle.path().to_path_buf();
+            spawn_loader(move || {
+                if pending.is_wanted() {
+                    pending.finish(server.read_model(&path).map_err(|error| {
+                        let error = describe(&error);
+                        log::error!("couldn't load model {}: {error}", path.display());
+                        error
+                    }));
+                }
+            });
+        }
+        handle
+    }
+
+    pub fn load_texture<P: AsRef<Path>>(&self, path: P) -> Handle<Texture> {
+        let (handle, pending) = self.inner.textures.get_or_insert(path);
+        if let Some(pending) = pending {
+            let server = self.clone();
+            let path = handle.path().to_path_buf();
+            spawn_loader(move || {
+                if pending.is_wanted() {
+                    pending.finish(server.read_texture(&path).map_err(|error| {
+                        let error = describe(&error);
+                        log::error!("couldn't load texture {}: {error}", path.display());
+                        error
+                    }));
+                }
+            });
+        }
+        handle
+    }
+
+    //assets with live handles, for debugging leaks
+    pub fn model_count(&self) -> usize {
+        self.inner.models.len()
+    }
+
+    pub fn texture_count(&self) -> usize {
+        self.inner.textures.len()
+    }
+
+    fn read_texture(&self, path: &Path) -> Result<Texture, TextureError> {
+        read_texture(path, &self.inner.device, &self.inner.queue)
+    }
+
+    //textures are loaded on this thread, or waited for if another load already started them
+    fn read_model(&self, path: &Path) -> Result<Model, ModelError> {
+        let inner = &self.inner;
+        let file_name = path.to_string_lossy();
+        let mut texture_files = Vec::new();
+        let (model, mut files) = pollster::block_on(load_model_with(
+            &file_name,
+            &inner.device,
+            &inner.texture_bind_group_layout,
+            |texture_path| {
+                texture_files.push(texture_path.to_path_buf());
+                let (handle, pending) = inner.textures.get_or_insert(texture_path);
+                if let Some(pending) = pending {
+                    match self.read_texture(texture_path) {
+                        Ok(texture) => pending.finish(Ok(texture)),
+                        Err(error) => {
+                            pending.finish(Err(describe(&error)));
+                            return Err(error);
+                        }
+                    }
+                }
+                let texture = handle
+                    .wait()
+                    .map_err(|error| TextureError::LoadFailed(texture_path.to_path_buf(), error))?;
+                Ok(((*texture).clone(), Some(handle)))
+            },
+        ))?;
+        files.append(&mut texture_files);
+        inner
+            .model_files
+            .lock()
+            .expect("Only panics if poisoned")
+            .insert(path.to_path_buf(), files);
+        Ok(model)
+    }
+
+    //every file the loaded assets were read from
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = self.inner.models.paths();
+        files.extend(self.inner.textures.paths());
+        let model_files = self
+            .inner
+            .model_files
+            .lock()
+            .expect("Only panics if poisoned");
+        for path in self.inner.models.paths() {
+            if let Some(used) = model_files.get(&path) {
+                files.extend(used.iter().cloned());
+            }
+        }
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    //reloads everything that was read from the file on a background thread, the handles stay the same
+    //textures are reloaded before the models using them, if something fails the old version stays
+    pub fn reload<P: AsRef<Path>>(&self, file: P) {
+        let file = file.as_ref().to_path_buf();
+        let server = self.clone();
+        spawn_loader(move || {
+            let inner = &server.inner;
+            if let Some(handle) = inner.textures.get(&file) {
+                match server.read_texture(&file) {
+                    Ok(texture) => handle.replace(texture),
+                    Err(error) => {
+                        log::error!(
+                            "couldn't reload texture {}: {}",
+                            file.display(),
+                            describe(&error)
+                        );
+                        return;
+                    }
+                }
+            }
+            let models = {
+                let model_files = inner.model_files.lock().expect("Only panics if poisoned");
+                inner
+                    .models
+                    .paths()
+                    .into_iter()
+                    .filter(|path| {
+                        *path == file
+                            || model_files
+                                .get(path)
+                                .is_some_and(|files| files.contains(&file))
+                    })
+                    .collect::<Vec<_>>()
+            };
+            for path in models {
+                let Some(handle) = inner.models.get(&path) else {
+                    continue;
+                };
+                match server.read_model(&path) {
+                    Ok(model) => {
+                        log::info!("reloaded model {}", path.display());
+                        handle.replace(model);
+                    }
+                    Err(error) => log::error!(
+                        "couldn't reload model {}: {}",
+                        path.display(),
+                        describe(&error)
+                    ),
+                }
+            }
+        });
+    }
+}
+
+//if the thread can't be spawned the Pending is dropped with the closure, so the handle fails
+fn spawn_loader<F: FnOnce() + Send + 'static>(load: F) {
+    if let Err(error) = std::thread::Builder::new()
+        .name("age asset loader".to_string())
+        .spawn(load)
+    {
+        log::error!("unable to spawn an asset loader thread: {error}");
+    }
+}
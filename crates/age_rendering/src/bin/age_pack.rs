@@ -0,0 +1,52 @@
+//packs an asset directory into an archive for age_rendering::vfs
+//age_pack <asset dir> <archive> [--store]   --store skips compression
+//age_pack --list <archive>
+use age_rendering::vfs::{Archive, ArchiveBuilder};
+use std::fs::File;
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "usage: age_pack <asset dir> <archive> [--store]\n       age_pack --list <archive>";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let result = match args.as_slice() {
+        ["--list", archive] => list(archive),
+        [directory, archive] => pack(directory, archive, true),
+        [directory, archive, "--store"] => pack(directory, archive, false),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("age_pack: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn pack(directory: &str, archive: &str, compress: bool) -> std::io::Result<()> {
+    let mut builder = ArchiveBuilder::new().with_compression(compress);
+    builder.add_dir(directory)?;
+    builder.write(BufWriter::new(File::create(archive)?))?;
+    println!(
+        "packed {} files from {directory} into {archive}",
+        builder.len()
+    );
+    Ok(())
+}
+
+fn list(archive: &str) -> std::io::Result<()> {
+    let archive = Archive::open(archive)?;
+    let mut paths = archive.paths().collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        println!("{path}");
+    }
+    Ok(())
+}
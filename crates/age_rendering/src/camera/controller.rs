@@ -0,0 +1,394 @@
+use super::{Camera, SAFE_FRAC_PI_2};
+use crate::input::{Input, actions};
+use cgmath::*;
+use std::time::Duration;
+
+//everything that moves the camera goes through this, so the State can swap controllers at runtime
+pub trait CameraControl {
+    //called every frame before update_camera, read the actions/axes needed here
+    fn handle_input(&mut self, _input: &Input) {}
+    //for controllers that look at or follow something, ignored by the others
+    fn set_target(&mut self, _target: Point3<f32>) {}
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration);
+}
+
+//look_x/look_y only count while the look action is held, a profile without one always looks
+//(e.g. with a gamepad stick bound to the look axes)
+fn look(input: &Input) -> (f32, f32) {
+    let held = input.pressed(actions::LOOK) || !input.profile().actions.contains_key(actions::LOOK);
+    if held {
+        (input.axis(actions::LOOK_X), input.axis(actions::LOOK_Y))
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+//zoom is in scroll lines, scrolling up moves closer
+fn zoom(input: &Input) -> f32 {
+    -input.axis(actions::ZOOM) * 0.5
+}
+
+fn clamp_pitch(pitch: Rad<f32>) -> Rad<f32> {
+    Rad(pitch.0.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2))
+}
+
+fn direction(yaw: Rad<f32>, pitch: Rad<f32>) -> Vector3<f32> {
+    let (sin_pitch, cos_pitch) = pitch.0.sin_cos();
+    let (sin_yaw, cos_yaw) = yaw.0.sin_cos();
+    Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize()
+}
+
+//free fly camera, moved with the move_forward/move_right/move_up axes
+#[derive(Debug)]
+pub struct FlyController {
+    amount_forward: f32,
+    amount_right: f32,
+    amount_up: f32,
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+    scroll: f32,
+    speed: f32,
+    sensitivity: f32,
+}
+
+impl FlyController {
+    pub fn new(speed: f32, sensitivity: f32) -> Self {
+        Self {
+            amount_forward: 0.0,
+            amount_right: 0.0,
+            amount_up: 0.0,
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            scroll: 0.0,
+            speed,
+            sensitivity,
+        }
+    }
+}
+
+impl CameraControl for FlyController {
+    fn handle_input(&mut self, input: &Input) {
+        self.amount_forward = input.axis(actions::MOVE_FORWARD);
+        self.amount_right = input.axis(actions::MOVE_RIGHT);
+        self.amount_up = input.axis(actions::MOVE_UP);
+        (self.rotate_horizontal, self.rotate_vertical) = look(input);
+        self.scroll = zoom(input);
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        // Move forward/backward and left/right
+        let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
+        let forward = Vector3::new(yaw_cos, 0.0, yaw_sin).normalize();
+        let right = Vector3::new(-yaw_sin, 0.0, yaw_cos).normalize();
+        camera.position += forward * self.amount_forward * self.speed * dt;
+        camera.position += right * self.amount_right * self.speed * dt;
+
+        // Move in/out (aka. "zoom")
+        // Note: this isn't an actual zoom. The camera's position
+        // changes when zooming. I've added this to make it easier
+        // to get closer to an object you want to focus on.
+        let scrollward = direction(camera.yaw, camera.pitch);
+        camera.position += scrollward * self.scroll * self.speed * self.sensitivity * dt;
+        self.scroll = 0.0;
+
+        // Move up/down. Since we don't use roll, we can just
+        // modify the y coordinate directly.
+        camera.position.y += self.amount_up * self.speed * dt;
+
+        // Rotate
+        camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
+        camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity * dt;
+
+        // If handle_input isn't called every frame, these values
+        // will not get set to zero, and the camera will rotate
+        // when moving in a non cardinal direction.
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+
+        // Keep the camera's angle from going too high/low.
+        camera.pitch = clamp_pitch(camera.pitch);
+    }
+}
+
+//rotates around a target point, mouse to orbit, scroll to zoom
+#[derive(Debug)]
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    yaw: Rad<f32>,
+    pitch: Rad<f32>,
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+    scroll: f32,
+    pub sensitivity: f32,
+    //distance change per scroll line
+    pub zoom_speed: f32,
+}
+
+impl OrbitController {
+    pub fn new<P: Into<Point3<f32>>>(target: P, distance: f32, sensitivity: f32) -> Self {
+        Self {
+            target: target.into(),
+            distance,
+            min_distance: 0.5,
+            max_distance: 100.0,
+            yaw: Rad(0.0),
+            pitch: Rad(-0.4),
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            scroll: 0.0,
+            sensitivity,
+            zoom_speed: 1.0,
+        }
+    }
+
+    //starts orbiting from where the camera currently is instead of jumping
+    pub fn from_camera<P: Into<Point3<f32>>>(camera: &Camera, target: P, sensitivity: f32) -> Self {
+        let target = target.into();
+        let mut look = Camera::new(camera.position, Rad(0.0), Rad(0.0));
+        look.look_at(target);
+        let mut controller = Self::new(target, (target - camera.position).magnitude(), sensitivity);
+        controller.yaw = look.yaw;
+        controller.pitch = look.pitch;
+        controller
+    }
+}
+
+impl CameraControl for OrbitController {
+    fn handle_input(&mut self, input: &Input) {
+        (self.rotate_horizontal, self.rotate_vertical) = look(input);
+        self.scroll = zoom(input);
+    }
+
+    fn set_target(&mut self, target: Point3<f32>) {
+        self.target = target;
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        self.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
+        self.pitch = clamp_pitch(self.pitch + Rad(-self.rotate_vertical) * self.sensitivity * dt);
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+
+        //a scroll "click" is a single event, so no dt here
+        self.distance = (self.distance + self.scroll * self.zoom_speed)
+            .clamp(self.min_distance, self.max_distance);
+        self.scroll = 0.0;
+
+        camera.position = self.target - direction(self.yaw, self.pitch) * self.distance;
+        camera.yaw = self.yaw;
+        camera.pitch = self.pitch;
+    }
+}
+
+//third person camera on a spring arm behind the target
+//no collision yet, so the arm never gets shorter on its own
+#[derive(Debug)]
+pub struct FollowController {
+    pub target: Point3<f32>,
+    //the direction the followed thing is facing
+    pub heading: Rad<f32>,
+    pub arm_length: f32,
+    pub height: f32,
+    //the camera looks this far above the target
+    pub look_height: f32,
+    //how fast the camera catches up, higher = stiffer arm, 0 = doesn't move at all
+    pub damping: f32,
+    //mouse offset around the target, added on top of the heading
+    yaw_offset: Rad<f32>,
+    rotate_horizontal: f32,
+    scroll: f32,
+    pub sensitivity: f32,
+    pub zoom_speed: f32,
+    pub min_arm_length: f32,
+    pub max_arm_length: f32,
+}
+
+impl FollowController {
+    pub fn new<P: Into<Point3<f32>>>(target: P, arm_length: f32, height: f32) -> Self {
+        Self {
+            target: target.into(),
+            heading: Rad(0.0),
+            arm_length,
+            height,
+            look_height: 1.0,
+            damping: 8.0,
+            yaw_offset: Rad(0.0),
+            rotate_horizontal: 0.0,
+            scroll: 0.0,
+            sensitivity: 0.4,
+            zoom_speed: 1.0,
+            min_arm_length: 1.0,
+            max_arm_length: 20.0,
+        }
+    }
+
+    pub fn set_heading<Y: Into<Rad<f32>>>(&mut self, heading: Y) {
+        self.heading = heading.into();
+    }
+
+    fn desired_position(&self) -> Point3<f32> {
+        let (sin_yaw, cos_yaw) = (self.heading + self.yaw_offset).0.sin_cos();
+        let behind = -Vector3::new(cos_yaw, 0.0, sin_yaw) * self.arm_length;
+        self.target + behind + Vector3::unit_y() * self.height
+    }
+}
+
+impl CameraControl for FollowController {
+    fn handle_input(&mut self, input: &Input) {
+        self.rotate_horizontal = look(input).0;
+        self.scroll = zoom(input);
+    }
+
+    fn set_target(&mut self, target: Point3<f32>) {
+        self.target = target;
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        self.yaw_offset += Rad(self.rotate_horizontal) * self.sensitivity * dt;
+        self.rotate_horizontal = 0.0;
+        self.arm_length = (self.arm_length + self.scroll * self.zoom_speed)
+            .clamp(self.min_arm_length, self.max_arm_length);
+        self.scroll = 0.0;
+
+        //framerate independent smoothing towards the end of the arm
+        let t = 1.0 - (-self.damping * dt).exp();
+        camera.position += (self.desired_position() - camera.position) * t;
+        camera.look_at(self.target + Vector3::unit_y() * self.look_height);
+    }
+}
+
+//moves along fixed points at a constant speed, for cutscenes, security cams etc.
+//a rail with a single point is just a fixed camera
+#[derive(Debug)]
+pub struct RailController {
+    points: Vec<Point3<f32>>,
+    //None = look along the rail
+    pub look_target: Option<Point3<f32>>,
+    //units per second
+    pub speed: f32,
+    pub looping: bool,
+    pub playing: bool,
+    travelled: f32,
+}
+
+impl RailController {
+    pub fn new(points: Vec<Point3<f32>>, speed: f32) -> Self {
+        Self {
+            points,
+            look_target: None,
+            speed,
+            looping: false,
+            playing: true,
+            travelled: 0.0,
+        }
+    }
+
+    pub fn fixed<P: Into<Point3<f32>>>(position: P, look_target: P) -> Self {
+        let mut rail = Self::new(vec![position.into()], 0.0);
+        rail.look_target = Some(look_target.into());
+        rail
+    }
+
+    pub fn points(&self) -> &[Point3<f32>] {
+        &self.points
+    }
+
+    pub fn set_points(&mut self, points: Vec<Point3<f32>>) {
+        self.points = points;
+        self.travelled = 0.0;
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn restart(&mut self) {
+        self.travelled = 0.0;
+        self.playing = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.travelled >= self.length()
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (Point3<f32>, Point3<f32>)> + '_ {
+        let closing = if self.looping && self.points.len() > 2 {
+            self.points
+                .last()
+                .copied()
+                .zip(self.points.first().copied())
+        } else {
+            None
+        };
+        self.points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain(closing)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.segments().map(|(a, b)| (b - a).magnitude()).sum()
+    }
+
+    //position and direction of travel at the current progress
+    fn sample(&self) -> Option<(Point3<f32>, Vector3<f32>)> {
+        let first = *self.points.first()?;
+        let mut remaining = self.travelled;
+        let mut last = (first, Vector3::zero());
+        for (a, b) in self.segments() {
+            let length = (b - a).magnitude();
+            if length == 0.0 {
+                continue;
+            }
+            let direction = (b - a) / length;
+            if remaining <= length {
+                return Some((a + direction * remaining, direction));
+            }
+            remaining -= length;
+            last = (b, direction);
+        }
+        Some(last)
+    }
+}
+
+impl CameraControl for RailController {
+    fn set_target(&mut self, target: Point3<f32>) {
+        self.look_target = Some(target);
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        let length = self.length();
+        if self.playing {
+            self.travelled += self.speed * dt.as_secs_f32();
+            if self.looping && length > 0.0 {
+                self.travelled %= length;
+            } else if self.travelled >= length {
+                self.travelled = length;
+                self.playing = false;
+            }
+        }
+
+        let Some((position, direction)) = self.sample() else {
+            return;
+        };
+        camera.position = position;
+        match self.look_target {
+            Some(target) => camera.look_at(target),
+            None if direction.magnitude2() > 0.0 => camera.look_at(position + direction),
+            None => {}
+        }
+    }
+}
@@ -1,11 +1,9 @@
+pub mod controller;
 pub mod uniform;
+pub mod view;
 
 use cgmath::*;
 use std::f32::consts::FRAC_PI_2;
-use std::time::Duration;
-use winit::dpi::PhysicalPosition;
-use winit::event::*;
-use winit::keyboard::KeyCode;
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_cols(
@@ -22,6 +20,8 @@ pub struct Camera {
     pub position: Point3<f32>,
     yaw: Rad<f32>,
     pitch: Rad<f32>,
+    //rotation around the view direction, positive = clockwise on screen
+    roll: Rad<f32>,
 }
 
 impl Camera {
@@ -34,35 +34,148 @@ impl Camera {
             position: position.into(),
             yaw: yaw.into(),
             pitch: pitch.into(),
+            roll: Rad(0.0),
         }
     }
 
-    pub fn calc_matrix(&self) -> Matrix4<f32> {
+    pub fn looking_at<V: Into<Point3<f32>>, T: Into<Point3<f32>>>(position: V, target: T) -> Self {
+        let mut camera = Self::new(position, Rad(0.0), Rad(0.0));
+        camera.look_at(target);
+        camera
+    }
+
+    pub fn yaw(&self) -> Rad<f32> {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> Rad<f32> {
+        self.pitch
+    }
+
+    pub fn roll(&self) -> Rad<f32> {
+        self.roll
+    }
+
+    pub fn set_yaw<Y: Into<Rad<f32>>>(&mut self, yaw: Y) {
+        self.yaw = yaw.into();
+    }
+
+    //clamped, looking straight up/down breaks the view matrix
+    pub fn set_pitch<P: Into<Rad<f32>>>(&mut self, pitch: P) {
+        self.pitch = Rad(pitch.into().0.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2));
+    }
+
+    pub fn set_roll<R: Into<Rad<f32>>>(&mut self, roll: R) {
+        self.roll = roll.into();
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
         let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
         let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+        Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize()
+    }
 
-        Matrix4::look_to_rh(
-            self.position,
-            Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
-            Vector3::unit_y(),
-        )
+    pub fn up(&self) -> Vector3<f32> {
+        Quaternion::from_axis_angle(self.forward(), self.roll).rotate_vector(Vector3::unit_y())
+    }
+
+    //turns the camera towards the target, position and roll stay the same
+    pub fn look_at<P: Into<Point3<f32>>>(&mut self, target: P) {
+        let direction = target.into() - self.position;
+        if direction.magnitude2() == 0.0 {
+            return;
+        }
+        let direction = direction.normalize();
+        self.yaw = Rad(direction.z.atan2(direction.x));
+        self.pitch = Rad(direction.y.asin().clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2));
+    }
+
+    pub fn calc_matrix(&self) -> Matrix4<f32> {
+        Matrix4::look_to_rh(self.position, self.forward(), self.up())
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectionKind {
+    Perspective {
+        fovy: Rad<f32>,
+        znear: f32,
+        zfar: f32,
+    },
+    //height of the visible area in world units, the width follows the aspect ratio
+    Orthographic {
+        height: f32,
+        znear: f32,
+        zfar: f32,
+    },
+    //explicit view volume, ignores the aspect ratio
+    OrthographicBounds {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        znear: f32,
+        zfar: f32,
+    },
+    //no far plane, depth goes from 1 at znear to 0 at infinity
+    //needs a depth buffer cleared to 0 and a "greater" depth compare, the State handles that
+    ReverseZInfinite {
+        fovy: Rad<f32>,
+        znear: f32,
+    },
+}
+
+#[derive(Debug)]
 pub struct Projection {
     aspect: f32,
-    fovy: Rad<f32>,
-    znear: f32,
-    zfar: f32,
+    kind: ProjectionKind,
 }
 
 impl Projection {
     pub fn new<F: Into<Rad<f32>>>(width: u32, height: u32, fovy: F, znear: f32, zfar: f32) -> Self {
+        Self::from_kind(
+            width,
+            height,
+            ProjectionKind::Perspective {
+                fovy: fovy.into(),
+                znear,
+                zfar,
+            },
+        )
+    }
+
+    pub fn orthographic(width: u32, height: u32, view_height: f32, znear: f32, zfar: f32) -> Self {
+        Self::from_kind(
+            width,
+            height,
+            ProjectionKind::Orthographic {
+                height: view_height,
+                znear,
+                zfar,
+            },
+        )
+    }
+
+    pub fn reverse_z_infinite<F: Into<Rad<f32>>>(
+        width: u32,
+        height: u32,
+        fovy: F,
+        znear: f32,
+    ) -> Self {
+        Self::from_kind(
+            width,
+            height,
+            ProjectionKind::ReverseZInfinite {
+                fovy: fovy.into(),
+                znear,
+            },
+        )
+    }
+
+    pub fn from_kind(width: u32, height: u32, kind: ProjectionKind) -> Self {
         Self {
             aspect: width as f32 / height as f32,
-            fovy: fovy.into(),
-            znear,
-            zfar,
+            kind,
         }
     }
 
@@ -70,127 +183,123 @@ impl Projection {
         self.aspect = width as f32 / height as f32;
     }
 
-    pub fn calc_matrix(&self) -> Matrix4<f32> {
-        OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar)
+    pub fn aspect(&self) -> f32 {
+        self.aspect
     }
-}
 
-#[derive(Debug)]
-pub struct CameraController {
-    amount_left: f32,
-    amount_right: f32,
-    amount_forward: f32,
-    amount_backward: f32,
-    amount_up: f32,
-    amount_down: f32,
-    rotate_horizontal: f32,
-    rotate_vertical: f32,
-    scroll: f32,
-    speed: f32,
-    sensitivity: f32,
-}
+    pub fn kind(&self) -> ProjectionKind {
+        self.kind
+    }
 
-//modify/make usable - TODO
-impl CameraController {
-    pub fn new(speed: f32, sensitivity: f32) -> Self {
-        Self {
-            amount_left: 0.0,
-            amount_right: 0.0,
-            amount_forward: 0.0,
-            amount_backward: 0.0,
-            amount_up: 0.0,
-            amount_down: 0.0,
-            rotate_horizontal: 0.0,
-            rotate_vertical: 0.0,
-            scroll: 0.0,
-            speed,
-            sensitivity,
+    pub fn set_kind(&mut self, kind: ProjectionKind) {
+        self.kind = kind;
+    }
+
+    pub fn is_reverse_z(&self) -> bool {
+        matches!(self.kind, ProjectionKind::ReverseZInfinite { .. })
+    }
+
+    //None for orthographic projections
+    pub fn fovy(&self) -> Option<Rad<f32>> {
+        match self.kind {
+            ProjectionKind::Perspective { fovy, .. }
+            | ProjectionKind::ReverseZInfinite { fovy, .. } => Some(fovy),
+            _ => None,
         }
     }
 
-    pub fn handle_key(&mut self, key: KeyCode, pressed: bool) -> bool {
-        let amount = if pressed { 1.0 } else { 0.0 };
-        match key {
-            KeyCode::KeyW | KeyCode::ArrowUp => {
-                self.amount_forward = amount;
-                true
-            }
-            KeyCode::KeyS | KeyCode::ArrowDown => {
-                self.amount_backward = amount;
-                true
-            }
-            KeyCode::KeyA | KeyCode::ArrowLeft => {
-                self.amount_left = amount;
-                true
-            }
-            KeyCode::KeyD | KeyCode::ArrowRight => {
-                self.amount_right = amount;
-                true
-            }
-            KeyCode::Space => {
-                self.amount_up = amount;
-                true
-            }
-            KeyCode::ShiftLeft => {
-                self.amount_down = amount;
-                true
-            }
-            _ => false,
+    //does nothing for orthographic projections
+    pub fn set_fovy<F: Into<Rad<f32>>>(&mut self, new_fovy: F) {
+        match &mut self.kind {
+            ProjectionKind::Perspective { fovy, .. }
+            | ProjectionKind::ReverseZInfinite { fovy, .. } => *fovy = new_fovy.into(),
+            _ => {}
         }
     }
 
-    pub fn handle_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
-        self.rotate_horizontal = mouse_dx as f32;
-        self.rotate_vertical = mouse_dy as f32;
-    }
-
-    pub fn handle_scroll(&mut self, delta: &MouseScrollDelta) {
-        self.scroll = match delta {
-            // I'm assuming a line is about 100 pixels
-            MouseScrollDelta::LineDelta(_, scroll) => -scroll * 0.5,
-            MouseScrollDelta::PixelDelta(PhysicalPosition { y: scroll, .. }) => -*scroll as f32,
-        };
-    }
-
-    pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
-        let dt = dt.as_secs_f32();
-
-        // Move forward/backward and left/right
-        let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
-        let forward = Vector3::new(yaw_cos, 0.0, yaw_sin).normalize();
-        let right = Vector3::new(-yaw_sin, 0.0, yaw_cos).normalize();
-        camera.position += forward * (self.amount_forward - self.amount_backward) * self.speed * dt;
-        camera.position += right * (self.amount_right - self.amount_left) * self.speed * dt;
-
-        // Move in/out (aka. "zoom")
-        // Note: this isn't an actual zoom. The camera's position
-        // changes when zooming. I've added this to make it easier
-        // to get closer to an object you want to focus on.
-        let (pitch_sin, pitch_cos) = camera.pitch.0.sin_cos();
-        let scrollward =
-            Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize();
-        camera.position += scrollward * self.scroll * self.speed * self.sensitivity * dt;
-        self.scroll = 0.0;
-
-        // Move up/down. Since we don't use roll, we can just
-        // modify the y coordinate directly.
-        camera.position.y += (self.amount_up - self.amount_down) * self.speed * dt;
-
-        // Rotate
-        camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
-        camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity * dt;
-
-        // If process_mouse isn't called every frame, these values
-        // will not get set to zero, and the camera will rotate
-        // when moving in a non cardinal direction.
-        self.rotate_horizontal = 0.0;
-        self.rotate_vertical = 0.0;
-
-        // Keep the camera's angle from going too high/low.
-        if camera.pitch < -Rad(SAFE_FRAC_PI_2) {
-            camera.pitch = -Rad(SAFE_FRAC_PI_2);
-        } else if camera.pitch > Rad(SAFE_FRAC_PI_2) {
-            camera.pitch = Rad(SAFE_FRAC_PI_2);
+    pub fn znear(&self) -> f32 {
+        match self.kind {
+            ProjectionKind::Perspective { znear, .. }
+            | ProjectionKind::Orthographic { znear, .. }
+            | ProjectionKind::OrthographicBounds { znear, .. }
+            | ProjectionKind::ReverseZInfinite { znear, .. } => znear,
+        }
+    }
+
+    //None for the infinite projection
+    pub fn zfar(&self) -> Option<f32> {
+        match self.kind {
+            ProjectionKind::Perspective { zfar, .. }
+            | ProjectionKind::Orthographic { zfar, .. }
+            | ProjectionKind::OrthographicBounds { zfar, .. } => Some(zfar),
+            ProjectionKind::ReverseZInfinite { .. } => None,
+        }
+    }
+
+    pub fn set_znear(&mut self, new_znear: f32) {
+        match &mut self.kind {
+            ProjectionKind::Perspective { znear, .. }
+            | ProjectionKind::Orthographic { znear, .. }
+            | ProjectionKind::OrthographicBounds { znear, .. }
+            | ProjectionKind::ReverseZInfinite { znear, .. } => *znear = new_znear,
+        }
+    }
+
+    //does nothing for the infinite projection
+    pub fn set_zfar(&mut self, new_zfar: f32) {
+        match &mut self.kind {
+            ProjectionKind::Perspective { zfar, .. }
+            | ProjectionKind::Orthographic { zfar, .. }
+            | ProjectionKind::OrthographicBounds { zfar, .. } => *zfar = new_zfar,
+            ProjectionKind::ReverseZInfinite { .. } => {}
+        }
+    }
+
+    pub fn set_clip_planes(&mut self, znear: f32, zfar: f32) {
+        self.set_znear(znear);
+        self.set_zfar(zfar);
+    }
+
+    pub fn calc_matrix(&self) -> Matrix4<f32> {
+        match self.kind {
+            ProjectionKind::Perspective { fovy, znear, zfar } => {
+                OPENGL_TO_WGPU_MATRIX * perspective(fovy, self.aspect, znear, zfar)
+            }
+            ProjectionKind::Orthographic {
+                height,
+                znear,
+                zfar,
+            } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * self.aspect;
+                OPENGL_TO_WGPU_MATRIX
+                    * ortho(
+                        -half_width,
+                        half_width,
+                        -half_height,
+                        half_height,
+                        znear,
+                        zfar,
+                    )
+            }
+            ProjectionKind::OrthographicBounds {
+                left,
+                right,
+                bottom,
+                top,
+                znear,
+                zfar,
+            } => OPENGL_TO_WGPU_MATRIX * ortho(left, right, bottom, top, znear, zfar),
+            //already in wgpu's 0..1 depth range, so no OPENGL_TO_WGPU_MATRIX
+            ProjectionKind::ReverseZInfinite { fovy, znear } => {
+                let f = 1.0 / (fovy / 2.0).tan();
+                Matrix4::from_cols(
+                    Vector4::new(f / self.aspect, 0.0, 0.0, 0.0),
+                    Vector4::new(0.0, f, 0.0, 0.0),
+                    Vector4::new(0.0, 0.0, 0.0, -1.0),
+                    Vector4::new(0.0, 0.0, znear, 0.0),
+                )
+            }
         }
     }
 }
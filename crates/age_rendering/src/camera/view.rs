@@ -0,0 +1,190 @@
+use super::uniform::CameraUniform;
+use super::{Camera, Projection};
+use crate::texture;
+use wgpu::util::DeviceExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CameraId(pub(crate) u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RenderTextureId(pub(crate) u32);
+
+//part of the target a camera draws into, in 0..1 relative to the target size, origin top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub const FULL: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    //x, y, width, height in pixels, clamped to the target
+    pub(crate) fn to_pixels(self, target_width: u32, target_height: u32) -> [f32; 4] {
+        let (target_width, target_height) = (target_width as f32, target_height as f32);
+        let x = (self.x.clamp(0.0, 1.0) * target_width).floor();
+        let y = (self.y.clamp(0.0, 1.0) * target_height).floor();
+        let width = (self.width * target_width)
+            .round()
+            .clamp(1.0, (target_width - x).max(1.0));
+        let height = (self.height * target_height)
+            .round()
+            .clamp(1.0, (target_height - y).max(1.0));
+        [x, y, width, height]
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+//careful: wgpu clears the whole target, not only the viewport
+//so only the first camera drawing into a target should clear the color,
+//State::add_camera already leaves it out for every camera after the first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearSettings {
+    pub color: Option<wgpu::Color>,
+    //clearing the depth is fine for every camera, the colors drawn before stay
+    pub depth: bool,
+}
+
+impl Default for ClearSettings {
+    fn default() -> Self {
+        Self {
+            color: Some(wgpu::Color::BLACK),
+            depth: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderTarget {
+    Surface,
+    Texture(RenderTextureId),
+}
+
+//offscreen target, the color texture can be used as a material texture afterwards
+pub struct RenderTexture {
+    pub color: texture::Texture,
+    pub depth: texture::Texture,
+    width: u32,
+    height: u32,
+}
+
+impl RenderTexture {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        Self {
+            color: texture::Texture::create_render_target(
+                device,
+                width,
+                height,
+                format,
+                "render_texture",
+            ),
+            depth: texture::Texture::create_depth_texture_sized(
+                device,
+                width,
+                height,
+                "render_texture_depth",
+            ),
+            width: width.max(1),
+            height: height.max(1),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+pub struct CameraView {
+    pub camera: Camera,
+    pub projection: Projection,
+    pub viewport: Viewport,
+    //lower renders first, cameras rendering into textures should come before the ones using them
+    pub priority: i32,
+    pub clear: ClearSettings,
+    pub target: RenderTarget,
+    pub active: bool,
+    //draw the State's debug lines with this camera too
+    pub show_debug: bool,
+    uniform: CameraUniform,
+    buffer: wgpu::Buffer,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+impl CameraView {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        camera: Camera,
+        projection: Projection,
+        target: RenderTarget,
+    ) -> Self {
+        let mut uniform = CameraUniform::new();
+        uniform.update_view_proj(&camera, &projection);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("camera_bind_group"),
+        });
+
+        Self {
+            camera,
+            projection,
+            viewport: Viewport::FULL,
+            priority: 0,
+            clear: ClearSettings::default(),
+            target,
+            active: true,
+            show_debug: true,
+            uniform,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub fn uniform(&self) -> &CameraUniform {
+        &self.uniform
+    }
+
+    //keeps the aspect ratio in sync with the viewport and uploads the matrices
+    pub(crate) fn update(&mut self, queue: &wgpu::Queue, target_width: u32, target_height: u32) {
+        let [_, _, width, height] = self.viewport.to_pixels(target_width, target_height);
+        self.projection.resize(width as u32, height as u32);
+        self.uniform
+            .update_view_proj(&self.camera, &self.projection);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+}
@@ -1,5 +1,8 @@
+use crate::camera::ProjectionKind;
+use crate::input::profile::InputProfile;
 use crate::model::Model;
 use std::path::PathBuf;
+use std::sync::Arc;
 use wgpu::naga::FastHashMap;
 
 #[non_exhaustive]
@@ -12,8 +15,15 @@ pub enum Asset {
 #[derive(Debug)]
 pub struct StateConfig {
     pub color: wgpu::Color,
-    pub models: FastHashMap<&'static str, String>,
+    //name -> path, State::new starts loading them in the background
+    pub models: FastHashMap<Arc<str>, String>,
     pub camera_speed: f32,
+    //can be changed later with State::projection_mut
+    pub projection: ProjectionKind,
+    pub input_profile: InputProfile,
+    //where hot reload looks for shader.wgsl and debug.wgsl, e.g. this crate's src/ while working on them
+    //None leaves the built in shaders alone, named shaders (State::insert_shader) are watched either way
+    pub shader_dir: Option<PathBuf>,
 }
 
 impl Default for StateConfig {
@@ -23,6 +33,13 @@ impl Default for StateConfig {
             models: FastHashMap::default(),
             //TODO: sane camera default
             camera_speed: 1.0,
+            projection: ProjectionKind::Perspective {
+                fovy: cgmath::Deg(45.0).into(),
+                znear: 0.1,
+                zfar: 100.0,
+            },
+            input_profile: InputProfile::default(),
+            shader_dir: None,
         }
     }
 }
@@ -0,0 +1,446 @@
+use crate::camera::{Camera, Projection};
+use crate::model::Vertex;
+use crate::texture;
+use cgmath::*;
+use std::f32::consts::TAU;
+
+//immediate mode: everything queued here is drawn once in the next frame and then dropped
+//use this instead of logging positions/matrices every frame
+
+const CIRCLE_SEGMENTS: u32 = 32;
+//start size of the GPU buffer, it grows if needed
+const INITIAL_VERTEX_CAPACITY: u64 = 1024;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl Vertex for DebugVertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DebugDraw {
+    //if false, nothing gets queued at all
+    pub enabled: bool,
+    depth_test: bool,
+    //line list, always two vertices per line
+    depth_tested: Vec<DebugVertex>,
+    overlay: Vec<DebugVertex>,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            depth_test: true,
+            depth_tested: Vec::new(),
+            overlay: Vec::new(),
+        }
+    }
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //applies to everything queued after this call
+    //false = drawn on top of the scene
+    pub fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test;
+    }
+
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    pub fn clear(&mut self) {
+        self.depth_tested.clear();
+        self.overlay.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.depth_tested.is_empty() && self.overlay.is_empty()
+    }
+
+    pub fn line<P: Into<Point3<f32>>>(&mut self, start: P, end: P, color: wgpu::Color) {
+        if !self.enabled {
+            return;
+        }
+        let color = [
+            color.r as f32,
+            color.g as f32,
+            color.b as f32,
+            color.a as f32,
+        ];
+        let target = if self.depth_test {
+            &mut self.depth_tested
+        } else {
+            &mut self.overlay
+        };
+        target.push(DebugVertex {
+            position: start.into().into(),
+            color,
+        });
+        target.push(DebugVertex {
+            position: end.into().into(),
+            color,
+        });
+    }
+
+    pub fn aabb<P: Into<Point3<f32>>>(&mut self, min: P, max: P, color: wgpu::Color) {
+        let (min, max) = (min.into(), max.into());
+        let corner = |x: bool, y: bool, z: bool| {
+            Point3::new(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+        for a in [false, true] {
+            for b in [false, true] {
+                //the four edges along each axis
+                self.line(corner(false, a, b), corner(true, a, b), color);
+                self.line(corner(a, false, b), corner(a, true, b), color);
+                self.line(corner(a, b, false), corner(a, b, true), color);
+            }
+        }
+    }
+
+    pub fn circle<P: Into<Point3<f32>>>(
+        &mut self,
+        center: P,
+        normal: Vector3<f32>,
+        radius: f32,
+        color: wgpu::Color,
+    ) {
+        let center = center.into();
+        let normal = normal.normalize();
+        //any vector that isn't parallel to the normal works here
+        let helper = if normal.y.abs() < 0.99 {
+            Vector3::unit_y()
+        } else {
+            Vector3::unit_x()
+        };
+        let u = normal.cross(helper).normalize() * radius;
+        let v = normal.cross(u);
+        let point = |i: u32| {
+            let (sin, cos) = (i as f32 / CIRCLE_SEGMENTS as f32 * TAU).sin_cos();
+            center + u * cos + v * sin
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    //three circles, one per axis
+    pub fn sphere<P: Into<Point3<f32>>>(&mut self, center: P, radius: f32, color: wgpu::Color) {
+        let center = center.into();
+        self.circle(center, Vector3::unit_x(), radius, color);
+        self.circle(center, Vector3::unit_y(), radius, color);
+        self.circle(center, Vector3::unit_z(), radius, color);
+    }
+
+    //x = red, y = green, z = blue
+    pub fn axes<P: Into<Point3<f32>>>(&mut self, origin: P, rotation: Quaternion<f32>, size: f32) {
+        let origin = origin.into();
+        let axes = [
+            (Vector3::unit_x(), wgpu::Color::RED),
+            (Vector3::unit_y(), wgpu::Color::GREEN),
+            (Vector3::unit_z(), wgpu::Color::BLUE),
+        ];
+        for (axis, color) in axes {
+            self.line(origin, origin + rotation.rotate_vector(axis) * size, color);
+        }
+    }
+
+    //grid on the XZ plane, `cells` per side
+    pub fn grid<P: Into<Point3<f32>>>(
+        &mut self,
+        center: P,
+        cells: u32,
+        spacing: f32,
+        color: wgpu::Color,
+    ) {
+        let center = center.into();
+        let half = cells as f32 * spacing / 2.0;
+        for i in 0..=cells {
+            let offset = i as f32 * spacing - half;
+            self.line(
+                center + Vector3::new(offset, 0.0, -half),
+                center + Vector3::new(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vector3::new(-half, 0.0, offset),
+                center + Vector3::new(half, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    //draws the volume a view-projection matrix sees
+    pub fn frustum(&mut self, view_proj: Matrix4<f32>, color: wgpu::Color) {
+        let Some(inverse) = view_proj.invert() else {
+            return;
+        };
+        //wgpu clip space: x and y in -1..1, z in 0..1
+        let corner = |x: f32, y: f32, z: f32| {
+            let mut p = inverse * Vector4::new(x, y, z, 1.0);
+            //reverse-Z infinite projections put depth 0 at infinity, use a far away finite point instead
+            if p.w.abs() < f32::EPSILON {
+                p = inverse * Vector4::new(x, y, 1e-4, 1.0);
+            }
+            Point3::from_homogeneous(p)
+        };
+        let near = [
+            corner(-1.0, -1.0, 0.0),
+            corner(1.0, -1.0, 0.0),
+            corner(1.0, 1.0, 0.0),
+            corner(-1.0, 1.0, 0.0),
+        ];
+        let far = [
+            corner(-1.0, -1.0, 1.0),
+            corner(1.0, -1.0, 1.0),
+            corner(1.0, 1.0, 1.0),
+            corner(-1.0, 1.0, 1.0),
+        ];
+        for i in 0..4 {
+            let next = (i + 1) % 4;
+            self.line(near[i], near[next], color);
+            self.line(far[i], far[next], color);
+            self.line(near[i], far[i], color);
+        }
+    }
+
+    pub fn camera_frustum(&mut self, camera: &Camera, projection: &Projection, color: wgpu::Color) {
+        self.frustum(projection.calc_matrix() * camera.calc_matrix(), color);
+    }
+}
+
+//everything built from debug.wgsl, replaced as a whole when it's hot reloaded
+pub(crate) struct DebugPipelines {
+    pipeline: wgpu::RenderPipeline,
+    reverse_z_pipeline: wgpu::RenderPipeline,
+    overlay_pipeline: wgpu::RenderPipeline,
+}
+
+//GPU side of the debug drawing, owned by the State
+pub(crate) struct DebugRenderer {
+    layout: wgpu::PipelineLayout,
+    pipelines: DebugPipelines,
+    vertex_buffer: wgpu::Buffer,
+    //in vertices
+    capacity: u64,
+    depth_tested_count: u32,
+    overlay_count: u32,
+}
+
+impl DebugRenderer {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("debug.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("debug.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipelines = Self::create_pipelines(device, &layout, &shader, format);
+
+        Self {
+            layout,
+            pipelines,
+            vertex_buffer: Self::create_buffer(device, INITIAL_VERTEX_CAPACITY),
+            capacity: INITIAL_VERTEX_CAPACITY,
+            depth_tested_count: 0,
+            overlay_count: 0,
+        }
+    }
+
+    //for a hot reloaded debug.wgsl, swapped in with set_pipelines once they're known to be valid
+    pub(crate) fn pipelines_for(
+        &self,
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> DebugPipelines {
+        Self::create_pipelines(device, &self.layout, shader, format)
+    }
+
+    pub(crate) fn set_pipelines(&mut self, pipelines: DebugPipelines) {
+        self.pipelines = pipelines;
+    }
+
+    fn create_pipelines(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> DebugPipelines {
+        DebugPipelines {
+            pipeline: Self::create_pipeline(
+                device,
+                layout,
+                shader,
+                format,
+                wgpu::CompareFunction::LessEqual,
+            ),
+            reverse_z_pipeline: Self::create_pipeline(
+                device,
+                layout,
+                shader,
+                format,
+                wgpu::CompareFunction::GreaterEqual,
+            ),
+            overlay_pipeline: Self::create_pipeline(
+                device,
+                layout,
+                shader,
+                format,
+                wgpu::CompareFunction::Always,
+            ),
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, capacity: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Vertex Buffer"),
+            size: capacity * std::mem::size_of::<DebugVertex>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        depth_compare: wgpu::CompareFunction,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Debug Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                buffers: &[DebugVertex::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            //lines never write depth, so they can't hide each other or the scene
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    //uploads everything queued in `draw`, call before the render pass starts
+    pub(crate) fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, draw: &DebugDraw) {
+        self.depth_tested_count = draw.depth_tested.len() as u32;
+        self.overlay_count = draw.overlay.len() as u32;
+        let needed = (draw.depth_tested.len() + draw.overlay.len()) as u64;
+        if needed == 0 {
+            return;
+        }
+        if needed > self.capacity {
+            self.capacity = needed.next_power_of_two();
+            self.vertex_buffer = Self::create_buffer(device, self.capacity);
+        }
+        //depth tested lines first, overlay lines right after them
+        let size = std::mem::size_of::<DebugVertex>() as u64;
+        queue.write_buffer(
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&draw.depth_tested),
+        );
+        queue.write_buffer(
+            &self.vertex_buffer,
+            self.depth_tested_count as u64 * size,
+            bytemuck::cast_slice(&draw.overlay),
+        );
+    }
+
+    pub(crate) fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+        reverse_z: bool,
+    ) {
+        if self.depth_tested_count + self.overlay_count == 0 {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        if self.depth_tested_count > 0 {
+            render_pass.set_pipeline(if reverse_z {
+                &self.pipelines.reverse_z_pipeline
+            } else {
+                &self.pipelines.pipeline
+            });
+            render_pass.draw(0..self.depth_tested_count, 0..1);
+        }
+        if self.overlay_count > 0 {
+            render_pass.set_pipeline(&self.pipelines.overlay_pipeline);
+            let start = self.depth_tested_count;
+            render_pass.draw(start..start + self.overlay_count, 0..1);
+        }
+    }
+}
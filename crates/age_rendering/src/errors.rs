@@ -1,74 +1,179 @@
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum StateCreationError {
+    CreateSurfaceError(wgpu::CreateSurfaceError),
     RequestAdapterError(wgpu::RequestAdapterError),
     RequestDeviceError(wgpu::RequestDeviceError),
     ModelError(ModelError),
 }
 
+//the first path is always the model's OBJ file
 #[derive(Debug)]
 pub enum ModelError {
-    ImageError(image::ImageError),
+    //the OBJ or one of its MTL files
+    IoError(PathBuf, std::io::Error),
+    LoadError(PathBuf, tobj::LoadError),
+    //material name
+    TextureError(PathBuf, String, Box<TextureError>),
+    //material name, it has no map_Kd
+    MissingTexture(PathBuf, String),
+    //mesh name, the material index it uses and how many materials there are
+    MissingMaterial(PathBuf, String, usize, usize),
+    //loaded by the AssetServer, which only keeps the error's description
+    AssetError(PathBuf, String),
+}
+
+#[derive(Debug)]
+pub enum InputProfileError {
     IoError(std::io::Error),
-    LoadError(tobj::LoadError),
-    TextureError(TextureError),
+    ParseError(toml::de::Error),
+    SerializeError(toml::ser::Error),
 }
 
 #[derive(Debug)]
 pub enum TextureError {
-    ImageError(image::ImageError),
-    IoError(std::io::Error),
+    //the texture's label, its path for loaded ones
+    ImageError(String, image::ImageError),
+    IoError(PathBuf, std::io::Error),
+    //a shared texture another load already failed on, with that error's description
+    LoadFailed(PathBuf, String),
 }
 
 impl Error for StateCreationError {
-    fn cause(&self) -> Option<&dyn Error> {
-        Some(match *self {
-            StateCreationError::RequestAdapterError(ref err) => err,
-            StateCreationError::RequestDeviceError(ref err) => err,
-            StateCreationError::ModelError(ref err) => err,
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(match self {
+            StateCreationError::CreateSurfaceError(err) => err,
+            StateCreationError::RequestAdapterError(err) => err,
+            StateCreationError::RequestDeviceError(err) => err,
+            StateCreationError::ModelError(err) => err,
         })
     }
 }
 
 impl Display for StateCreationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        //TODO!: proper Display implementation later
-        write!(f, "{:?}", self)
+        match self {
+            StateCreationError::CreateSurfaceError(_) => {
+                write!(f, "couldn't create a surface for the window")
+            }
+            StateCreationError::RequestAdapterError(_) => {
+                write!(f, "no suitable graphics adapter")
+            }
+            StateCreationError::RequestDeviceError(_) => {
+                write!(f, "couldn't open the graphics device")
+            }
+            StateCreationError::ModelError(_) => write!(f, "couldn't load a model"),
+        }
     }
 }
 
 impl Error for ModelError {
-    fn cause(&self) -> Option<&dyn Error> {
-        Some(match self {
-            ModelError::LoadError(err) => err,
-            ModelError::IoError(err) => err,
-            ModelError::TextureError(err) => err,
-            ModelError::ImageError(err) => err,
-        })
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ModelError::IoError(_, err) => Some(err),
+            ModelError::LoadError(_, err) => Some(err),
+            ModelError::TextureError(_, _, err) => Some(err.as_ref()),
+            ModelError::MissingTexture(_, _)
+            | ModelError::MissingMaterial(_, _, _, _)
+            | ModelError::AssetError(_, _) => None,
+        }
     }
 }
 
 impl Display for ModelError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        //TODO!: proper Display implementation later
-        write!(f, "{:?}", self)
+        match self {
+            ModelError::IoError(path, _) => write!(f, "couldn't read {}", path.display()),
+            ModelError::LoadError(path, _) => {
+                write!(f, "couldn't parse model {}", path.display())
+            }
+            ModelError::TextureError(path, material, _) => write!(
+                f,
+                "couldn't load the texture of material {material:?} in model {}",
+                path.display()
+            ),
+            ModelError::MissingTexture(path, material) => write!(
+                f,
+                "material {material:?} in model {} has no diffuse texture (map_Kd)",
+                path.display()
+            ),
+            ModelError::MissingMaterial(path, mesh, material, count) => write!(
+                f,
+                "mesh {mesh:?} in model {} uses material {material}, but the model only has {count}",
+                path.display()
+            ),
+            ModelError::AssetError(path, err) => {
+                write!(f, "couldn't load model {}: {err}", path.display())
+            }
+        }
     }
 }
 
 impl Error for TextureError {
-    fn cause(&self) -> Option<&dyn Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::ImageError(_, err) => Some(err),
+            TextureError::IoError(_, err) => Some(err),
+            TextureError::LoadFailed(_, _) => None,
+        }
+    }
+}
+
+impl Display for TextureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::ImageError(label, _) => {
+                write!(f, "couldn't decode texture {label}")
+            }
+            TextureError::IoError(path, _) => {
+                write!(f, "couldn't read texture {}", path.display())
+            }
+            TextureError::LoadFailed(path, err) => {
+                write!(
+                    f,
+                    "texture {} failed to load earlier: {err}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl Error for InputProfileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         Some(match self {
-            TextureError::ImageError(err) => err,
-            TextureError::IoError(err) => err,
+            InputProfileError::IoError(err) => err,
+            InputProfileError::ParseError(err) => err,
+            InputProfileError::SerializeError(err) => err,
         })
     }
 }
 
-impl Display for TextureError {
+impl Display for InputProfileError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        //TODO!: proper Display implementation later
-        write!(f, "{:?}", self)
+        match self {
+            InputProfileError::IoError(_) => {
+                write!(f, "couldn't read/write input profile")
+            }
+            InputProfileError::ParseError(_) => write!(f, "invalid input profile"),
+            InputProfileError::SerializeError(_) => {
+                write!(f, "couldn't serialize input profile")
+            }
+        }
+    }
+}
+
+//the error and everything it was caused by on one line, for logs and string-only errors
+pub fn describe(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
     }
+    message
 }
@@ -0,0 +1,114 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use wgpu::naga;
+
+//the file names State looks for in StateConfig::shader_dir
+pub const MAIN_SHADER: &str = "shader.wgsl";
+pub const DEBUG_SHADER: &str = "debug.wgsl";
+
+type Watched = Arc<Mutex<HashMap<PathBuf, Option<SystemTime>>>>;
+
+//polls the modification times of the watched files on its own thread
+//there's no OS notification here, so keep the list to the files that are actually in use
+pub struct FileWatcher {
+    watched: Watched,
+    changes: Receiver<PathBuf>,
+    stop: Arc<AtomicBool>,
+}
+
+impl FileWatcher {
+    pub fn new(interval: Duration) -> Self {
+        let watched = Watched::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, changes) = channel();
+        let thread_watched = watched.clone();
+        let thread_stop = stop.clone();
+        if let Err(error) = std::thread::Builder::new()
+            .name("age file watcher".to_string())
+            .spawn(move || poll(thread_watched, thread_stop, sender, interval))
+        {
+            log::error!("unable to spawn the file watcher thread: {error}");
+        }
+        Self {
+            watched,
+            changes,
+            stop,
+        }
+    }
+
+    //changes before this call aren't reported
+    pub fn watch<P: AsRef<Path>>(&self, path: P) {
+        let mut watched = self.watched.lock().expect("Only panics if poisoned");
+        let path = path.as_ref();
+        if !watched.contains_key(path) {
+            watched.insert(path.to_path_buf(), modified(path));
+        }
+    }
+
+    pub fn unwatch<P: AsRef<Path>>(&self, path: P) {
+        let mut watched = self.watched.lock().expect("Only panics if poisoned");
+        watched.remove(path.as_ref());
+    }
+
+    pub fn is_watched<P: AsRef<Path>>(&self, path: P) -> bool {
+        let watched = self.watched.lock().expect("Only panics if poisoned");
+        watched.contains_key(path.as_ref())
+    }
+
+    //files that changed since the last call, each one once
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        self.changes
+            .try_iter()
+            .filter(|path| seen.insert(path.clone()))
+            .collect()
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn poll(watched: Watched, stop: Arc<AtomicBool>, sender: Sender<PathBuf>, interval: Duration) {
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(interval);
+        let mut watched = watched.lock().expect("Only panics if poisoned");
+        for (path, last) in watched.iter_mut() {
+            //a missing file (e.g. while an editor saves) is reported once it's back
+            let Some(now) = modified(path) else {
+                continue;
+            };
+            if *last != Some(now) {
+                *last = Some(now);
+                if sender.send(path.clone()).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+//parses and validates WGSL the way wgpu would, the error is naga's report
+pub fn validate_wgsl(source: &str, path: &Path) -> Result<(), String> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|error| error.emit_to_string_with_path(source, path))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|error| error.emit_to_string_with_path(source, path))?;
+    Ok(())
+}
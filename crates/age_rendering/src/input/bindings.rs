@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+//there's no gamepad backend in here, feed these through Input::handle_gamepad_* from whatever you use (gilrs etc.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MouseAxis {
+    X,
+    Y,
+    Wheel,
+}
+
+//anything that is either pressed or not
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Button {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl From<KeyCode> for Button {
+    fn from(key: KeyCode) -> Self {
+        Button::Key(key)
+    }
+}
+
+impl From<MouseButton> for Button {
+    fn from(button: MouseButton) -> Self {
+        Button::Mouse(button)
+    }
+}
+
+impl From<GamepadButton> for Button {
+    fn from(button: GamepadButton) -> Self {
+        Button::Gamepad(button)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    //positive pressed = 1, negative pressed = -1, both = 0
+    Buttons { positive: Button, negative: Button },
+    //per frame delta in pixels (or scroll lines) times the scale
+    Mouse { axis: MouseAxis, scale: f32 },
+    Gamepad { axis: GamepadAxis, scale: f32 },
+}
+
+impl AxisBinding {
+    pub fn keys(positive: KeyCode, negative: KeyCode) -> Self {
+        AxisBinding::Buttons {
+            positive: Button::Key(positive),
+            negative: Button::Key(negative),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+pub mod bindings;
+pub mod profile;
+
+use bindings::{AxisBinding, Button, GamepadAxis, GamepadButton, MouseAxis};
+use profile::InputProfile;
+use std::collections::{HashMap, HashSet};
+use winit::dpi::PhysicalPosition;
+use winit::event::{MouseButton, MouseScrollDelta};
+use winit::keyboard::KeyCode;
+
+//names used by the default profile and the built-in camera controllers
+pub mod actions {
+    pub const EXIT: &str = "exit";
+    //held to turn the camera with look_x/look_y
+    pub const LOOK: &str = "look";
+    pub const MOVE_FORWARD: &str = "move_forward";
+    pub const MOVE_RIGHT: &str = "move_right";
+    pub const MOVE_UP: &str = "move_up";
+    pub const LOOK_X: &str = "look_x";
+    pub const LOOK_Y: &str = "look_y";
+    pub const ZOOM: &str = "zoom";
+}
+
+//raw device state + the profile that maps it to named actions and axes
+//the just_* and mouse deltas only last for one frame, see end_frame
+#[cfg_attr(feature = "ecs", derive(bevy_ecs::resource::Resource))]
+#[derive(Debug)]
+pub struct Input {
+    profile: InputProfile,
+    pressed: HashSet<Button>,
+    just_pressed: HashSet<Button>,
+    just_released: HashSet<Button>,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    mouse_delta: (f32, f32),
+    //in lines
+    wheel_delta: f32,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new(InputProfile::default())
+    }
+}
+
+impl Input {
+    pub fn new(profile: InputProfile) -> Self {
+        Self {
+            profile,
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+            gamepad_axes: HashMap::new(),
+            mouse_delta: (0.0, 0.0),
+            wheel_delta: 0.0,
+        }
+    }
+
+    pub fn profile(&self) -> &InputProfile {
+        &self.profile
+    }
+
+    //rebinding at runtime, what is currently held stays held
+    pub fn profile_mut(&mut self) -> &mut InputProfile {
+        &mut self.profile
+    }
+
+    pub fn set_profile(&mut self, profile: InputProfile) -> InputProfile {
+        std::mem::replace(&mut self.profile, profile)
+    }
+
+    fn set_button(&mut self, button: Button, pressed: bool) {
+        if pressed {
+            //key repeat sends "pressed" again, that's not a new press
+            if self.pressed.insert(button) {
+                self.just_pressed.insert(button);
+            }
+        } else if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode, pressed: bool) {
+        self.set_button(Button::Key(key), pressed);
+    }
+
+    pub fn handle_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        self.set_button(Button::Mouse(button), pressed);
+    }
+
+    pub fn handle_mouse_motion(&mut self, dx: f64, dy: f64) {
+        self.mouse_delta.0 += dx as f32;
+        self.mouse_delta.1 += dy as f32;
+    }
+
+    pub fn handle_mouse_wheel(&mut self, delta: &MouseScrollDelta) {
+        self.wheel_delta += match delta {
+            MouseScrollDelta::LineDelta(_, lines) => *lines,
+            // I'm assuming a line is about 100 pixels
+            MouseScrollDelta::PixelDelta(PhysicalPosition { y, .. }) => *y as f32 / 100.0,
+        };
+    }
+
+    pub fn handle_gamepad_button(&mut self, button: GamepadButton, pressed: bool) {
+        self.set_button(Button::Gamepad(button), pressed);
+    }
+
+    //-1..1 for sticks, 0..1 for triggers
+    pub fn handle_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.gamepad_axes.insert(axis, value);
+    }
+
+    //call once per frame after everything read the input
+    pub fn end_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.wheel_delta = 0.0;
+    }
+
+    pub fn is_button_pressed<B: Into<Button>>(&self, button: B) -> bool {
+        self.pressed.contains(&button.into())
+    }
+
+    fn buttons(&self, action: &str) -> &[Button] {
+        self.profile
+            .actions
+            .get(action)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    //unknown actions are never pressed
+    pub fn pressed(&self, action: &str) -> bool {
+        self.buttons(action)
+            .iter()
+            .any(|b| self.pressed.contains(b))
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.buttons(action)
+            .iter()
+            .any(|b| self.just_pressed.contains(b))
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.buttons(action)
+            .iter()
+            .any(|b| self.just_released.contains(b))
+            && !self.pressed(action)
+    }
+
+    fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        let value = self.gamepad_axes.get(&axis).copied().unwrap_or(0.0);
+        if value.abs() < self.profile.deadzone {
+            0.0
+        } else {
+            value
+        }
+    }
+
+    //buttons and gamepad axes together stay in -1..1, mouse deltas are added on top unclamped
+    pub fn axis(&self, axis: &str) -> f32 {
+        let Some(bindings) = self.profile.axes.get(axis) else {
+            return 0.0;
+        };
+        let mut digital = 0.0;
+        let mut mouse = 0.0;
+        for binding in bindings {
+            match *binding {
+                AxisBinding::Buttons { positive, negative } => {
+                    if self.pressed.contains(&positive) {
+                        digital += 1.0;
+                    }
+                    if self.pressed.contains(&negative) {
+                        digital -= 1.0;
+                    }
+                }
+                AxisBinding::Gamepad { axis, scale } => digital += self.gamepad_axis(axis) * scale,
+                AxisBinding::Mouse { axis, scale } => {
+                    mouse += scale
+                        * match axis {
+                            MouseAxis::X => self.mouse_delta.0,
+                            MouseAxis::Y => self.mouse_delta.1,
+                            MouseAxis::Wheel => self.wheel_delta,
+                        }
+                }
+            }
+        }
+        f32::clamp(digital, -1.0, 1.0) + mouse
+    }
+}
@@ -0,0 +1,149 @@
+use super::actions;
+use super::bindings::{AxisBinding, Button, GamepadAxis, GamepadButton, MouseAxis};
+use crate::errors::InputProfileError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+//named actions/axes and what triggers them, (de)serialized as TOML:
+//
+//  [actions]
+//  exit = [{ Key = "Escape" }, { Gamepad = "Start" }]
+//
+//  [axes]
+//  move_forward = [{ Buttons = { positive = { Key = "KeyW" }, negative = { Key = "KeyS" } } }]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputProfile {
+    #[serde(default)]
+    pub actions: BTreeMap<String, Vec<Button>>,
+    #[serde(default)]
+    pub axes: BTreeMap<String, Vec<AxisBinding>>,
+    //gamepad axis values below this count as 0
+    #[serde(default = "default_deadzone")]
+    pub deadzone: f32,
+}
+
+fn default_deadzone() -> f32 {
+    0.15
+}
+
+impl InputProfile {
+    pub fn empty() -> Self {
+        Self {
+            actions: BTreeMap::new(),
+            axes: BTreeMap::new(),
+            deadzone: default_deadzone(),
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, InputProfileError> {
+        toml::from_str(text).map_err(InputProfileError::ParseError)
+    }
+
+    pub fn to_toml(&self) -> Result<String, InputProfileError> {
+        toml::to_string_pretty(self).map_err(InputProfileError::SerializeError)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, InputProfileError> {
+        let text = std::fs::read_to_string(path).map_err(InputProfileError::IoError)?;
+        Self::from_toml(&text)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), InputProfileError> {
+        std::fs::write(path, self.to_toml()?).map_err(InputProfileError::IoError)
+    }
+
+    pub fn bind_action<B: Into<Button>>(&mut self, action: &str, button: B) {
+        self.actions
+            .entry(action.to_string())
+            .or_default()
+            .push(button.into());
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.entry(axis.to_string()).or_default().push(binding);
+    }
+
+    //drops every binding of that action/axis
+    pub fn unbind(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+}
+
+//what the engine used to hardcode: WASD/arrows + Space/ShiftLeft for the fly camera, the mouse
+//with the left button held to look around, Escape to exit
+impl Default for InputProfile {
+    fn default() -> Self {
+        let mut profile = Self::empty();
+        profile.bind_action(actions::EXIT, KeyCode::Escape);
+        profile.bind_action(actions::LOOK, MouseButton::Left);
+
+        profile.bind_axis(
+            actions::MOVE_FORWARD,
+            AxisBinding::keys(KeyCode::KeyW, KeyCode::KeyS),
+        );
+        profile.bind_axis(
+            actions::MOVE_FORWARD,
+            AxisBinding::keys(KeyCode::ArrowUp, KeyCode::ArrowDown),
+        );
+        profile.bind_axis(
+            actions::MOVE_FORWARD,
+            AxisBinding::Gamepad {
+                axis: GamepadAxis::LeftStickY,
+                scale: 1.0,
+            },
+        );
+        profile.bind_axis(
+            actions::MOVE_RIGHT,
+            AxisBinding::keys(KeyCode::KeyD, KeyCode::KeyA),
+        );
+        profile.bind_axis(
+            actions::MOVE_RIGHT,
+            AxisBinding::keys(KeyCode::ArrowRight, KeyCode::ArrowLeft),
+        );
+        profile.bind_axis(
+            actions::MOVE_RIGHT,
+            AxisBinding::Gamepad {
+                axis: GamepadAxis::LeftStickX,
+                scale: 1.0,
+            },
+        );
+        profile.bind_axis(
+            actions::MOVE_UP,
+            AxisBinding::keys(KeyCode::Space, KeyCode::ShiftLeft),
+        );
+        profile.bind_axis(
+            actions::MOVE_UP,
+            AxisBinding::Buttons {
+                positive: Button::Gamepad(GamepadButton::RightBumper),
+                negative: Button::Gamepad(GamepadButton::LeftBumper),
+            },
+        );
+
+        profile.bind_axis(
+            actions::LOOK_X,
+            AxisBinding::Mouse {
+                axis: MouseAxis::X,
+                scale: 1.0,
+            },
+        );
+        profile.bind_axis(
+            actions::LOOK_Y,
+            AxisBinding::Mouse {
+                axis: MouseAxis::Y,
+                scale: 1.0,
+            },
+        );
+        profile.bind_axis(
+            actions::ZOOM,
+            AxisBinding::Mouse {
+                axis: MouseAxis::Wheel,
+                scale: 1.0,
+            },
+        );
+        profile
+    }
+}
@@ -1,8 +1,14 @@
+pub mod assets;
 pub mod camera;
 pub mod config;
+pub mod debug_draw;
 pub mod errors;
+pub mod hot_reload;
+pub mod input;
 pub mod instance;
+pub mod mesh;
 pub mod model;
 pub mod resources;
 pub mod state;
 pub mod texture;
+pub mod vfs;
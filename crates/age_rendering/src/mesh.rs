@@ -0,0 +1,526 @@
+//meshes made in code instead of loaded from OBJ files, e.g. for blocking out levels and tests
+//like loaded models front faces are counter clockwise and v = 0 is the top of a texture
+//primitives are centered on the origin with u = 0.5 facing +z, round ones have their seam at -z
+use crate::errors::TextureError;
+use crate::model::{Mesh, Model, ModelVertex};
+use cgmath::{InnerSpace, Vector3};
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+#[derive(Clone, Debug, Default)]
+pub struct MeshBuilder {
+    vertices: Vec<ModelVertex>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //returns the index triangle and quad take
+    pub fn vertex(&mut self, position: [f32; 3], tex_coords: [f32; 2], normal: [f32; 3]) -> u32 {
+        self.vertices.push(ModelVertex {
+            position,
+            tex_coords,
+            normal,
+        });
+        self.vertices.len() as u32 - 1
+    }
+
+    //counter clockwise seen from the front
+    pub fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend([a, b, c]);
+    }
+
+    //counter clockwise seen from the front, split along a-c
+    pub fn quad(&mut self, a: u32, b: u32, c: u32, d: u32) {
+        self.triangle(a, b, c);
+        self.triangle(a, c, d);
+    }
+
+    //other's triangles become part of this mesh
+    pub fn append(&mut self, other: &MeshBuilder) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices
+            .extend(other.indices.iter().map(|index| index + offset));
+    }
+
+    //e.g. to append several primitives next to each other
+    pub fn translate(mut self, offset: [f32; 3]) -> Self {
+        for vertex in &mut self.vertices {
+            for (coordinate, offset) in vertex.position.iter_mut().zip(offset) {
+                *coordinate += offset;
+            }
+        }
+        self
+    }
+
+    pub fn vertices(&self) -> &[ModelVertex] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    //material is an index into the materials of the model the mesh ends up in
+    pub fn build(&self, device: &wgpu::Device, name: &str, material: usize) -> Mesh {
+        Mesh::new(device, name, &self.vertices, &self.indices, material)
+    }
+
+    //a whole model in one color, ready for State::insert_model
+    pub fn build_model(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        color: [u8; 4],
+    ) -> Result<Model, TextureError> {
+        Model::from_mesh_data(
+            device,
+            queue,
+            layout,
+            name,
+            &self.vertices,
+            &self.indices,
+            color,
+        )
+    }
+
+    //width along x, depth along z, facing +y, split into subdivisions x subdivisions quads
+    pub fn plane(width: f32, depth: f32, subdivisions: u32) -> Self {
+        let subdivisions = subdivisions.max(1);
+        let row = subdivisions + 1;
+        let mut builder = Self::new();
+        for z in 0..=subdivisions {
+            for x in 0..=subdivisions {
+                let u = x as f32 / subdivisions as f32;
+                let v = z as f32 / subdivisions as f32;
+                builder.vertex(
+                    [(u - 0.5) * width, 0.0, (v - 0.5) * depth],
+                    [u, v],
+                    [0.0, 1.0, 0.0],
+                );
+            }
+        }
+        for z in 0..subdivisions {
+            for x in 0..subdivisions {
+                let a = z * row + x;
+                builder.quad(a, a + row, a + row + 1, a + 1);
+            }
+        }
+        builder
+    }
+
+    //every face shows the whole texture
+    pub fn cube(size: f32) -> Self {
+        //normal, right and up with right x up = normal, so the corners go counter clockwise
+        const FACES: [[[f32; 3]; 3]; 6] = [
+            [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+            [[-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+            [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+            [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        ];
+        let half = size / 2.0;
+        let mut builder = Self::new();
+        for [normal, right, up] in FACES {
+            let corner = |x: f32, y: f32| {
+                let position =
+                    Vector3::from(normal) + Vector3::from(right) * x + Vector3::from(up) * y;
+                (position * half).into()
+            };
+            let top_left = builder.vertex(corner(-1.0, 1.0), [0.0, 0.0], normal);
+            let bottom_left = builder.vertex(corner(-1.0, -1.0), [0.0, 1.0], normal);
+            let bottom_right = builder.vertex(corner(1.0, -1.0), [1.0, 1.0], normal);
+            let top_right = builder.vertex(corner(1.0, 1.0), [1.0, 0.0], normal);
+            builder.quad(top_left, bottom_left, bottom_right, top_right);
+        }
+        builder
+    }
+
+    //segments around the y axis, rings from pole to pole
+    pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        let rings = rings.max(2);
+        let profile = (0..=rings)
+            .map(|ring| {
+                let v = ring as f32 / rings as f32;
+                let (sin, cos) = (v * PI).sin_cos();
+                //exactly on the axis, so lathe sees the poles
+                let sin = if ring == 0 || ring == rings { 0.0 } else { sin };
+                ProfilePoint {
+                    radius: sin * radius,
+                    y: cos * radius,
+                    normal: [sin, cos],
+                    v,
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut builder = Self::new();
+        builder.lathe(&profile, segments);
+        builder
+    }
+
+    //evenly sized triangles, 0 subdivisions is an icosahedron and each one splits every triangle in 4
+    //the texture is mapped like on uv_sphere, triangles crossing the seam are cut there
+    pub fn icosphere(radius: f32, subdivisions: u32) -> Self {
+        let (positions, triangles) = icosahedron(subdivisions);
+        let mut builder = Self::new();
+        let mut added = HashMap::new();
+        for triangle in triangles {
+            let mut corners = triangle.map(|index| {
+                let position = positions[index as usize];
+                (position, sphere_uv(position))
+            });
+            //u jumps from 1 back to 0 at the seam, the poles have no u of their own
+            let on_axis =
+                corners.map(|(position, _)| position.x.abs() < 1e-6 && position.z.abs() < 1e-6);
+            let around = || (0..3).filter(|&corner| !on_axis[corner]);
+            let min = around()
+                .map(|corner| corners[corner].1[0])
+                .fold(1.0, f32::min);
+            let max = around()
+                .map(|corner| corners[corner].1[0])
+                .fold(0.0, f32::max);
+            if max - min > 0.5 {
+                for corner in around() {
+                    if corners[corner].1[0] < 0.5 {
+                        corners[corner].1[0] += 1.0;
+                    }
+                }
+            }
+            let pole_u =
+                around().map(|corner| corners[corner].1[0]).sum::<f32>() / around().count() as f32;
+            for corner in 0..3 {
+                if on_axis[corner] {
+                    corners[corner].1[0] = pole_u;
+                }
+            }
+
+            let mut below = clip_at_seam(&corners, true);
+            let mut above = clip_at_seam(&corners, false);
+            for (_, uv) in &mut above {
+                uv[0] -= 1.0;
+            }
+            for polygon in [&mut below, &mut above] {
+                let indices = polygon
+                    .iter()
+                    .map(|&(position, uv)| builder.shared_vertex(&mut added, position, uv, radius))
+                    .collect::<Vec<_>>();
+                for corner in 1..indices.len().saturating_sub(1) {
+                    builder.triangle(indices[0], indices[corner], indices[corner + 1]);
+                }
+            }
+        }
+        builder
+    }
+
+    //along the y axis, with caps
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+        let half = height / 2.0;
+        let mut builder = Self::new();
+        builder.lathe(
+            &[
+                ProfilePoint {
+                    radius,
+                    y: half,
+                    normal: [1.0, 0.0],
+                    v: 0.0,
+                },
+                ProfilePoint {
+                    radius,
+                    y: -half,
+                    normal: [1.0, 0.0],
+                    v: 1.0,
+                },
+            ],
+            segments,
+        );
+        builder.disc(half, radius, segments, true);
+        builder.disc(-half, radius, segments, false);
+        builder
+    }
+
+    //height includes both half spheres, rings is per half sphere
+    pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        let rings = rings.max(1);
+        //half of the straight part in the middle
+        let half = (height / 2.0 - radius).max(0.0);
+        //v follows the outline from top to bottom
+        let length = PI * radius + 2.0 * half;
+        let point = |angle: f32, y: f32, on_axis: bool, along: f32| {
+            let (sin, cos) = angle.sin_cos();
+            let sin = if on_axis { 0.0 } else { sin };
+            ProfilePoint {
+                radius: sin * radius,
+                y: y + cos * radius,
+                normal: [sin, cos],
+                v: along / length,
+            }
+        };
+        let mut profile = Vec::new();
+        for ring in 0..=rings {
+            let angle = ring as f32 / rings as f32 * FRAC_PI_2;
+            profile.push(point(angle, half, ring == 0, angle * radius));
+        }
+        //without a straight part both half spheres share their widest ring
+        let first = if half > 0.0 { 0 } else { 1 };
+        for ring in first..=rings {
+            let angle = FRAC_PI_2 + ring as f32 / rings as f32 * FRAC_PI_2;
+            profile.push(point(
+                angle,
+                -half,
+                ring == rings,
+                angle * radius + 2.0 * half,
+            ));
+        }
+        let mut builder = Self::new();
+        builder.lathe(&profile, segments);
+        builder
+    }
+
+    //tip up, with a cap at the bottom
+    pub fn cone(radius: f32, height: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+        let half = height / 2.0;
+        let slant = (height * height + radius * radius).sqrt();
+        let normal = [height / slant, radius / slant];
+        let mut builder = Self::new();
+        builder.lathe(
+            &[
+                ProfilePoint {
+                    radius: 0.0,
+                    y: half,
+                    normal,
+                    v: 0.0,
+                },
+                ProfilePoint {
+                    radius,
+                    y: -half,
+                    normal,
+                    v: 1.0,
+                },
+            ],
+            segments,
+        );
+        builder.disc(-half, radius, segments, false);
+        builder
+    }
+
+    //lying flat around the y axis, major_radius is the distance to the middle of the tube
+    //v goes around the tube starting at its top
+    pub fn torus(
+        major_radius: f32,
+        minor_radius: f32,
+        major_segments: u32,
+        minor_segments: u32,
+    ) -> Self {
+        let minor_segments = minor_segments.max(3);
+        let profile = (0..=minor_segments)
+            .map(|segment| {
+                let v = segment as f32 / minor_segments as f32;
+                //down the outside first, so the outline goes around clockwise
+                let (sin, cos) = (FRAC_PI_2 - v * TAU).sin_cos();
+                ProfilePoint {
+                    radius: major_radius + cos * minor_radius,
+                    y: sin * minor_radius,
+                    normal: [cos, sin],
+                    v,
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut builder = Self::new();
+        builder.lathe(&profile, major_segments);
+        builder
+    }
+
+    //spins the outline around the y axis, it has to run down the outside so the faces point out
+    fn lathe(&mut self, profile: &[ProfilePoint], segments: u32) {
+        let segments = segments.max(3);
+        let mut rows = Vec::with_capacity(profile.len());
+        for point in profile {
+            rows.push(self.vertices.len() as u32);
+            //a point on the axis gets one vertex per segment, halfway around it
+            let (offset, count) = if point.on_axis() {
+                (0.5, segments)
+            } else {
+                (0.0, segments + 1)
+            };
+            for segment in 0..count {
+                let u = (segment as f32 + offset) / segments as f32;
+                let (sin, cos) = (u * TAU).sin_cos();
+                let (x, z) = (-sin, -cos);
+                self.vertex(
+                    [x * point.radius, point.y, z * point.radius],
+                    [u, point.v],
+                    [x * point.normal[0], point.normal[1], z * point.normal[0]],
+                );
+            }
+        }
+        for (index, pair) in profile.windows(2).enumerate() {
+            let (upper, lower) = (rows[index], rows[index + 1]);
+            for segment in 0..segments {
+                let top_left = upper + segment;
+                let bottom_left = lower + segment;
+                let bottom_right = bottom_left + 1;
+                let top_right = top_left + 1;
+                if pair[0].on_axis() {
+                    self.triangle(top_left, bottom_left, bottom_right);
+                } else if pair[1].on_axis() {
+                    self.triangle(top_left, bottom_left, top_right);
+                } else {
+                    self.quad(top_left, bottom_left, bottom_right, top_right);
+                }
+            }
+        }
+    }
+
+    //a flat cap, the texture is seen from outside with -z (or +z below) at its top
+    fn disc(&mut self, y: f32, radius: f32, segments: u32, up: bool) {
+        let normal = [0.0, if up { 1.0 } else { -1.0 }, 0.0];
+        let center = self.vertex([0.0, y, 0.0], [0.5, 0.5], normal);
+        for segment in 0..segments {
+            let (sin, cos) = (segment as f32 / segments as f32 * TAU).sin_cos();
+            let (x, z) = (-sin, -cos);
+            let v = if up { 0.5 + z * 0.5 } else { 0.5 - z * 0.5 };
+            self.vertex([x * radius, y, z * radius], [0.5 + x * 0.5, v], normal);
+        }
+        for segment in 0..segments {
+            let current = center + 1 + segment;
+            let next = center + 1 + (segment + 1) % segments;
+            if up {
+                self.triangle(center, current, next);
+            } else {
+                self.triangle(center, next, current);
+            }
+        }
+    }
+
+    //corners of neighbouring triangles share a vertex if they agree on the uv
+    fn shared_vertex(
+        &mut self,
+        added: &mut HashMap<[u32; 5], u32>,
+        position: Vector3<f32>,
+        uv: [f32; 2],
+        radius: f32,
+    ) -> u32 {
+        let key = [position.x, position.y, position.z, uv[0], uv[1]].map(f32::to_bits);
+        *added
+            .entry(key)
+            .or_insert_with(|| self.vertex((position * radius).into(), uv, position.into()))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ProfilePoint {
+    //distance to the y axis
+    radius: f32,
+    y: f32,
+    //away from the axis and up, normalized
+    normal: [f32; 2],
+    v: f32,
+}
+
+impl ProfilePoint {
+    fn on_axis(&self) -> bool {
+        self.radius.abs() < f32::EPSILON
+    }
+}
+
+//of a point on the unit sphere, matching lathe's
+fn sphere_uv(position: Vector3<f32>) -> [f32; 2] {
+    [
+        0.5 + position.x.atan2(position.z) / TAU,
+        position.y.clamp(-1.0, 1.0).acos() / PI,
+    ]
+}
+
+//positions on the unit sphere and counter clockwise triangles
+fn icosahedron(subdivisions: u32) -> (Vec<Vector3<f32>>, Vec<[u32; 3]>) {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut positions = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .into_iter()
+    .map(|position| Vector3::from(position).normalize())
+    .collect::<Vec<_>>();
+    let mut triangles = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push((positions[a as usize] + positions[b as usize]).normalize());
+                positions.len() as u32 - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+    (positions, triangles)
+}
+
+//the part of a triangle with u up to 1 (below) or from 1 on, still counter clockwise
+fn clip_at_seam(
+    corners: &[(Vector3<f32>, [f32; 2])],
+    below: bool,
+) -> Vec<(Vector3<f32>, [f32; 2])> {
+    let inside = |uv: [f32; 2]| if below { uv[0] <= 1.0 } else { uv[0] >= 1.0 };
+    let mut polygon = Vec::new();
+    for (index, &(position, uv)) in corners.iter().enumerate() {
+        let (next_position, next_uv) = corners[(index + 1) % corners.len()];
+        if inside(uv) {
+            polygon.push((position, uv));
+        }
+        if inside(uv) != inside(next_uv) {
+            let t = (1.0 - uv[0]) / (next_uv[0] - uv[0]);
+            let crossing = (position + (next_position - position) * t).normalize();
+            polygon.push((crossing, [1.0, sphere_uv(crossing)[1]]));
+        }
+    }
+    if polygon.len() < 3 {
+        polygon.clear();
+    }
+    polygon
+}
@@ -1,7 +1,10 @@
+use super::assets::Handle;
 use super::texture;
+use crate::errors::TextureError;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::Range;
 use std::path::Path;
+use wgpu::util::DeviceExt;
 
 //all the vertex stuff down here is from Sotrh's tutorial
 //TODO!: proper attribution!
@@ -49,6 +52,50 @@ pub struct Material {
     pub name: String,
     pub diffuse_texture: texture::Texture,
     pub bind_group: wgpu::BindGroup,
+    //keeps a texture shared through the AssetServer alive while the material uses it
+    pub(crate) shared_texture: Option<Handle<texture::Texture>>,
+}
+
+impl Material {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        name: String,
+        diffuse_texture: texture::Texture,
+    ) -> Self {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                },
+            ],
+            label: Some(&name),
+        });
+        Self {
+            name,
+            diffuse_texture,
+            bind_group,
+            shared_texture: None,
+        }
+    }
+
+    //a plain colored material, for meshes made in code
+    pub fn from_color(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        name: String,
+        color: [u8; 4],
+    ) -> Result<Self, TextureError> {
+        let texture = texture::Texture::from_color(device, queue, color, &name)?;
+        Ok(Self::new(device, layout, name, texture))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -60,12 +107,60 @@ pub struct Mesh {
     pub material: usize,
 }
 
+impl Mesh {
+    //material is an index into the model's materials
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        vertices: &[ModelVertex],
+        indices: &[u32],
+        material: usize,
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{:?} Vertex Buffer", name)),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{:?} Index Buffer", name)),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self {
+            name: name.to_string(),
+            vertex_buffer,
+            index_buffer,
+            num_elements: indices.len() as u32,
+            material,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Model {
     pub meshes: Vec<Mesh>,
     pub materials: Vec<Material>,
 }
 
+impl Model {
+    //one mesh in one color, State::insert_model draws it
+    pub fn from_mesh_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        vertices: &[ModelVertex],
+        indices: &[u32],
+        color: [u8; 4],
+    ) -> Result<Self, TextureError> {
+        let material = Material::from_color(device, queue, layout, name.to_string(), color)?;
+        Ok(Self {
+            meshes: vec![Mesh::new(device, name, vertices, indices, 0)],
+            materials: vec![material],
+        })
+    }
+}
+
 pub trait DrawModel<'a> {
     fn draw_mesh(
         &mut self,
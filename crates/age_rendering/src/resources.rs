@@ -1,29 +1,21 @@
+use super::assets::Handle;
 use super::texture;
+use super::vfs;
 use crate::errors::{ModelError, TextureError};
+use std::cell::RefCell;
 use std::io::{BufReader, Cursor};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tobj::tokio as tobj_tokio;
-use wgpu::util::DeviceExt;
 
 use super::model;
 
+//both go through the vfs, so mounted archives are read before loose files
 pub async fn load_string(path: &Path) -> std::io::Result<String> {
-    let txt = {
-        //let path = Path::new("res").join(path);
-        //assert!(path.exists(), "{} does not exist", path.display());
-        std::fs::read_to_string(path)?
-    };
-    Ok(txt)
+    vfs::read_to_string(path)
 }
 
 pub async fn load_binary(path: &Path) -> std::io::Result<Vec<u8>> {
-    let data = {
-        //let path = Path::new("res").join(path);
-        //assert!(path.exists(), "{} does not exist", path.display());
-        std::fs::read(path)?
-    };
-
-    Ok(data)
+    vfs::read(path)
 }
 
 pub async fn load_texture(
@@ -31,14 +23,17 @@ pub async fn load_texture(
     device: &wgpu::Device,
     queue: &wgpu::Queue,
 ) -> Result<texture::Texture, TextureError> {
-    let data = load_binary(path).await.map_err(TextureError::IoError)?;
-    //TODO!: no .unwrap()
-    texture::Texture::from_bytes(
-        device,
-        queue,
-        &data,
-        path.file_name().unwrap().to_str().unwrap(),
-    )
+    read_texture(path, device, queue)
+}
+
+//for the load_model_with callbacks, which can't await
+pub(crate) fn read_texture(
+    path: &Path,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Result<texture::Texture, TextureError> {
+    let data = vfs::read(path).map_err(|error| TextureError::IoError(path.to_path_buf(), error))?;
+    texture::Texture::from_bytes(device, queue, &data, &path.to_string_lossy())
 }
 
 pub async fn load_model(
@@ -47,11 +42,36 @@ pub async fn load_model(
     queue: &wgpu::Queue,
     layout: &wgpu::BindGroupLayout,
 ) -> Result<model::Model, ModelError> {
+    load_model_with(file_name, device, layout, |path| {
+        read_texture(path, device, queue).map(|texture| (texture, None))
+    })
+    .await
+    .map(|(model, _)| model)
+}
+
+//diffuse_texture gets the full path of every material's texture, the AssetServer shares them through it
+//also returns the MTL files that were read, for hot reloading
+pub(crate) async fn load_model_with<F>(
+    file_name: &str,
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    mut diffuse_texture: F,
+) -> Result<(model::Model, Vec<PathBuf>), ModelError>
+where
+    F: FnMut(&Path) -> Result<(texture::Texture, Option<Handle<texture::Texture>>), TextureError>,
+{
     let path = Path::new(file_name);
-    let obj_text = load_string(path).await.map_err(ModelError::IoError)?;
+    //paths in the OBJ and MTL files are relative to the OBJ
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let obj_text = load_string(path)
+        .await
+        .map_err(|error| ModelError::IoError(path.to_path_buf(), error))?;
     let obj_cursor = Cursor::new(obj_text);
     let mut obj_reader = tokio::io::BufReader::new(obj_cursor);
 
+    let material_files = RefCell::new(Vec::new());
+    //tobj only takes a LoadError from the MTL loader, this keeps the actual reason
+    let material_error = RefCell::new(None);
     let (models, obj_materials) = tobj_tokio::load_obj_buf(
         &mut obj_reader,
         &tobj::LoadOptions {
@@ -59,114 +79,104 @@ pub async fn load_model(
             single_index: true,
             ..Default::default()
         },
-        |p| async move {
-            let mat_text =
+        |p| {
+            let mtl_path = directory.join(&p);
+            material_files.borrow_mut().push(mtl_path.clone());
+            let material_error = &material_error;
+            async move {
                 //make this more efficient later
-                load_string(Path::new(path.parent().expect("TODO")).join(&p).as_ref())
-                    .await
-                    .map_err(|_e| tobj::LoadError::ReadError)?;
-            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
+                let mat_text = load_string(&mtl_path).await.map_err(|error| {
+                    *material_error.borrow_mut() = Some(ModelError::IoError(mtl_path, error));
+                    tobj::LoadError::ReadError
+                })?;
+                tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
+            }
         },
     )
     .await
-    .map_err(ModelError::LoadError)?;
-
-    dbg!(&obj_materials.as_ref().unwrap().len());
+    .map_err(|error| {
+        material_error
+            .borrow_mut()
+            .take()
+            .unwrap_or_else(|| ModelError::LoadError(path.to_path_buf(), error))
+    })?;
+    let obj_materials = match obj_materials {
+        Ok(materials) => materials,
+        Err(error) => {
+            return Err(material_error
+                .into_inner()
+                .unwrap_or_else(|| ModelError::LoadError(path.to_path_buf(), error)));
+        }
+    };
 
     let mut materials = Vec::new();
-    for m in obj_materials.map_err(ModelError::LoadError)? {
-        //TODO!: remove .unwrap()
-        let diffuse_texture = load_texture(
-            Path::new(path.parent().expect("TODO"))
-                .join(m.diffuse_texture.unwrap())
-                .as_ref(),
-            device,
-            queue,
-        )
-        .await
-        .map_err(ModelError::TextureError)?;
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: None,
-        });
+    for m in obj_materials {
+        let Some(texture_name) = &m.diffuse_texture else {
+            return Err(ModelError::MissingTexture(path.to_path_buf(), m.name));
+        };
+        let (texture, shared_texture) = match diffuse_texture(&directory.join(texture_name)) {
+            Ok(texture) => texture,
+            Err(error) => {
+                return Err(ModelError::TextureError(
+                    path.to_path_buf(),
+                    m.name,
+                    Box::new(error),
+                ));
+            }
+        };
+        let mut material = model::Material::new(device, layout, m.name, texture);
+        material.shared_texture = shared_texture;
+        materials.push(material);
+    }
 
-        materials.push(model::Material {
-            name: m.name,
-            diffuse_texture,
-            bind_group,
-        })
+    //drawing a mesh looks its material up by index
+    for m in &models {
+        let material = m.mesh.material_id.unwrap_or(0);
+        if material >= materials.len() {
+            return Err(ModelError::MissingMaterial(
+                path.to_path_buf(),
+                m.name.clone(),
+                material,
+                materials.len(),
+            ));
+        }
     }
 
     let meshes = models
         .into_iter()
         .map(|m| {
+            //OBJs without texture coordinates or normals get zeroes
             let vertices = (0..m.mesh.positions.len() / 3)
-                .map(|i| {
-                    if m.mesh.normals.is_empty() {
-                        model::ModelVertex {
-                            position: [
-                                m.mesh.positions[i * 3],
-                                m.mesh.positions[i * 3 + 1],
-                                m.mesh.positions[i * 3 + 2],
-                            ],
-                            tex_coords: [
-                                m.mesh.texcoords[i * 2],
-                                1.0 - m.mesh.texcoords[i * 2 + 1],
-                            ],
-                            normal: [0.0, 0.0, 0.0],
-                        }
-                    } else {
-                        model::ModelVertex {
-                            position: [
-                                m.mesh.positions[i * 3],
-                                m.mesh.positions[i * 3 + 1],
-                                m.mesh.positions[i * 3 + 2],
-                            ],
-                            tex_coords: [
-                                m.mesh.texcoords[i * 2],
-                                1.0 - m.mesh.texcoords[i * 2 + 1],
-                            ],
-                            normal: [
-                                m.mesh.normals[i * 3],
-                                m.mesh.normals[i * 3 + 1],
-                                m.mesh.normals[i * 3 + 2],
-                            ],
-                        }
-                    }
+                .map(|i| model::ModelVertex {
+                    position: [
+                        m.mesh.positions[i * 3],
+                        m.mesh.positions[i * 3 + 1],
+                        m.mesh.positions[i * 3 + 2],
+                    ],
+                    tex_coords: match m.mesh.texcoords.get(i * 2..i * 2 + 2) {
+                        Some(&[u, v]) => [u, 1.0 - v],
+                        _ => [0.0, 0.0],
+                    },
+                    normal: match m.mesh.normals.get(i * 3..i * 3 + 3) {
+                        Some(&[x, y, z]) => [x, y, z],
+                        _ => [0.0, 0.0, 0.0],
+                    },
                 })
                 .collect::<Vec<_>>();
 
-            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:?} Vertex Buffer", file_name)),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:?} Index Buffer", file_name)),
-                contents: bytemuck::cast_slice(&m.mesh.indices),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-
             log::info!("Mesh: {}", m.name);
-            model::Mesh {
-                name: file_name.to_string(),
-                vertex_buffer,
-                index_buffer,
-                num_elements: m.mesh.indices.len() as u32,
-                material: m.mesh.material_id.unwrap_or(0),
-            }
+            model::Mesh::new(
+                device,
+                file_name,
+                &vertices,
+                &m.mesh.indices,
+                m.mesh.material_id.unwrap_or(0),
+            )
         })
         .collect::<Vec<_>>();
 
-    Ok(model::Model { meshes, materials })
+    Ok((
+        model::Model { meshes, materials },
+        material_files.into_inner(),
+    ))
 }
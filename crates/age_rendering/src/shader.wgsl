@@ -1,6 +1,7 @@
 // Vertex shader
 
 struct Camera {
+    view_position: vec4<f32>,
     view_proj: mat4x4<f32>,
 }
 @group(1) @binding(0)
@@ -1,4 +1,8 @@
-use super::camera::{Camera, CameraController, Projection, uniform::CameraUniform};
+use super::camera::controller::{CameraControl, FlyController};
+use super::camera::view::{CameraId, CameraView, RenderTarget, RenderTexture, RenderTextureId};
+use super::camera::{Camera, Projection};
+use super::debug_draw::{DebugDraw, DebugRenderer};
+use super::input::{Input, actions};
 use super::instance::{Instance, InstanceRaw};
 use super::model::{DrawModel, Model, Vertex};
 
@@ -22,13 +26,15 @@ use winit::window::Window;
 pub struct State {
     pub mouse_pressed: bool,
 
-    pub camera_controller: CameraController,
+    pub camera_controller: Box<dyn CameraControl>,
+    //fed by the handle_* functions, rebind through input.profile_mut()
+    pub input: Input,
     //for model loading
     pub texture_bind_group_layout: wgpu::BindGroupLayout,
-    //"new"
-    projection: Projection,
-    //new!
-    pub clear_color: wgpu::Color,
+    //loads models and textures on background threads
+    assets: AssetServer,
+    //needed to create new cameras later
+    camera_bind_group_layout: wgpu::BindGroupLayout,
     //stays
     pub surface: wgpu::Surface<'static>,
     //stays
@@ -41,53 +47,84 @@ pub struct State {
     pub is_surface_configured: bool,
     //stays
     pub render_pipeline: wgpu::RenderPipeline,
-    //stays
-    pub models: FastHashMap<&'static str, Model>,
-    //stays very likely
-    pub camera: Camera,
-    //idk
-    pub camera_uniform: CameraUniform,
-    //we'll see
-    pub camera_buffer: wgpu::Buffer,
-    //same as above
-    pub camera_bind_group: wgpu::BindGroup,
+    pub reverse_z_render_pipeline: wgpu::RenderPipeline,
+    //to rebuild the pipelines when the shader changes
+    render_pipeline_layout: wgpu::PipelineLayout,
+    //set by enable_hot_reload
+    file_watcher: Option<FileWatcher>,
+    //from StateConfig, watched for MAIN_SHADER and DEBUG_SHADER
+    shader_dir: Option<PathBuf>,
+    //the files of the named shaders, see insert_shader
+    shader_files: FastHashMap<PathBuf, &'static str>,
+    //models that aren't loaded yet are skipped when rendering
+    pub models: FastHashMap<Arc<str>, Handle<Model>>,
+    //from load_model, moved into models by update() once they're loaded
+    pending_models: Vec<(Arc<str>, Handle<Model>)>,
+    //loose textures and extra shaders by name, e.g. from an asset manifest
+    pub textures: FastHashMap<&'static str, Handle<texture::Texture>>,
+    pub shaders: FastHashMap<&'static str, wgpu::ShaderModule>,
+    //every camera renders each frame, see CameraView
+    cameras: FastHashMap<CameraId, CameraView>,
+    //the one the camera controller moves, can't be removed
+    main_camera: CameraId,
+    render_textures: FastHashMap<RenderTextureId, RenderTexture>,
+    //shared by cameras and render textures
+    next_id: u32,
     //stays
     pub instances: Vec<Instance>,
     //for the vertex buffer, remove
     pub instance_buffer: wgpu::Buffer,
+    //set through set_instances, models without one draw once with instance_buffer
+    model_instances: FastHashMap<Arc<str>, InstanceBuffer>,
     //remove? - yes
     pub depth_texture: texture::Texture,
     // /\ replaces, only depth texture for now for easier usage
     pub depth_textures: Vec<texture::Texture>,
+    //queue lines/shapes here every frame, cleared after rendering
+    pub debug_draw: DebugDraw,
+    debug_renderer: DebugRenderer,
     //stays
     pub window: Arc<Window>,
     //pub obj_model: Model,
 }
 
+use crate::assets::{AssetServer, Handle, LoadState};
 use crate::config::StateConfig;
-use crate::errors::StateCreationError;
-use crate::resources::load_model;
+use crate::errors::{ModelError, StateCreationError};
+use crate::hot_reload::{DEBUG_SHADER, FileWatcher, MAIN_SHADER, validate_wgsl};
+use std::path::{Path, PathBuf};
 
 impl State {
     pub fn handle_key(&mut self, event_loop: &ActiveEventLoop, key: KeyCode, pressed: bool) {
-        if !self.camera_controller.handle_key(key, pressed) {
-            match (key, pressed) {
-                (KeyCode::Escape, true) => event_loop.exit(),
-                _ => {}
-            }
+        self.input.handle_key(key, pressed);
+        if self.input.just_pressed(actions::EXIT) {
+            event_loop.exit();
         }
     }
 
     pub fn handle_mouse_button(&mut self, button: MouseButton, pressed: bool) {
-        match button {
-            MouseButton::Left => self.mouse_pressed = pressed,
-            _ => {}
+        self.input.handle_mouse_button(button, pressed);
+        if button == MouseButton::Left {
+            self.mouse_pressed = pressed;
         }
     }
 
-    // NEW!
+    //raw mouse movement (DeviceEvent::MouseMotion), the camera controllers read it through the look axes
+    pub fn handle_mouse_motion(&mut self, dx: f64, dy: f64) {
+        self.input.handle_mouse_motion(dx, dy);
+    }
+
+    //the camera controllers read it through the zoom axis
     pub fn handle_mouse_scroll(&mut self, delta: &MouseScrollDelta) {
-        self.camera_controller.handle_scroll(delta);
+        self.input.handle_mouse_wheel(delta);
+    }
+
+    //returns the old controller so it can be swapped back in later
+    pub fn set_camera_controller<C: CameraControl + 'static>(
+        &mut self,
+        controller: C,
+    ) -> Box<dyn CameraControl> {
+        std::mem::replace(&mut self.camera_controller, Box::new(controller))
     }
 
     pub async fn new(
@@ -104,7 +141,9 @@ impl State {
             ..Default::default()
         });
 
-        let surface = instance.create_surface(window.clone()).unwrap();
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(StateCreationError::CreateSurfaceError)?;
 
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
@@ -192,7 +231,11 @@ impl State {
                         cgmath::Quaternion::from_axis_angle(position.normalize(), cgmath::Deg(45.0))
                     };
 
-                    Instance { position, rotation }
+                    Instance {
+                        position,
+                        rotation,
+                        scale: cgmath::Vector3::new(1.0, 1.0, 1.0),
+                    }
                 })
             })
             .collect::<Vec<_>>();
@@ -245,132 +288,484 @@ impl State {
                 push_constant_ranges: &[],
             });
 
-        //I only want to preset the capacity
-        let mut models =
-            FastHashMap::with_capacity_and_hasher(general_config.models.len(), Default::default());
-
-        for (name, path) in general_config.models.into_iter() {
-            let loaded = load_model(&path, &device, &queue, &texture_bind_group_layout)
-                .await
-                .map_err(StateCreationError::ModelError)?;
-            models.insert(name, loaded);
-        }
+        let assets = AssetServer::new(
+            device.clone(),
+            queue.clone(),
+            texture_bind_group_layout.clone(),
+        );
+        //they show up once they're loaded, failures are logged by the loader
+        let models = general_config
+            .models
+            .into_iter()
+            .map(|(name, path)| (name, assets.load_model(path)))
+            .collect::<FastHashMap<_, _>>();
 
         //TODO!: configs!
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[model::ModelVertex::desc(), InstanceRaw::desc()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
-                // or Features::POLYGON_MODE_POINT
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::Texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            // If the pipeline will be used with a multiview render pass, this
-            // indicates how many array layers the attachments will have.
-            multiview: None,
-            // Useful for optimizing shader compilation on Android
-            cache: None,
-        });
+        let render_pipeline = create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            config.format,
+            wgpu::CompareFunction::Less,
+        );
+        //only used while the projection is reverse-Z
+        let reverse_z_render_pipeline = create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            config.format,
+            wgpu::CompareFunction::Greater,
+        );
+        let debug_renderer = DebugRenderer::new(&device, &camera_bind_group_layout, config.format);
+
         let camera = Camera::new((0.0, 5.0, 10.0), cgmath::Deg(-90.0), cgmath::Deg(-20.0));
 
         let projection =
-            Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 100.0);
-
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&camera, &projection);
-        //TODO make configurable
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        let clear_color = general_config.color;
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
+            Projection::from_kind(config.width, config.height, general_config.projection);
 
-        camera_uniform.update_view_proj(&camera, &projection);
+        let mut main_view = CameraView::new(
+            &device,
+            &camera_bind_group_layout,
+            camera,
+            projection,
+            RenderTarget::Surface,
+        );
+        main_view.clear.color = Some(general_config.color);
+        let main_camera = CameraId(0);
+        let mut cameras = FastHashMap::default();
+        cameras.insert(main_camera, main_view);
 
         Ok(Self {
             mouse_pressed: false,
-            camera_controller: CameraController::new(4., 0.4),
+            camera_controller: Box::new(FlyController::new(4., 0.4)),
+            input: Input::new(general_config.input_profile),
             texture_bind_group_layout,
-            projection,
+            assets,
+            camera_bind_group_layout,
             //TODO: temp
             depth_textures: Vec::new(),
+            debug_draw: DebugDraw::new(),
+            debug_renderer,
             //obj_model: obj_model.unwrap(),
             //TODO
             surface,
             device,
             queue,
             config,
-            clear_color,
             is_surface_configured: false,
             render_pipeline,
+            reverse_z_render_pipeline,
+            render_pipeline_layout,
+            file_watcher: None,
+            shader_dir: general_config.shader_dir,
+            shader_files: FastHashMap::default(),
             //TODO!
             models,
-            camera,
-            camera_buffer,
-            camera_bind_group,
-            camera_uniform,
+            pending_models: Vec::new(),
+            textures: FastHashMap::default(),
+            shaders: FastHashMap::default(),
+            cameras,
+            main_camera,
+            render_textures: FastHashMap::default(),
+            next_id: 1,
             instances,
             instance_buffer,
+            model_instances: FastHashMap::default(),
             depth_texture,
             window,
         })
     }
 
+    pub fn assets(&self) -> &AssetServer {
+        &self.assets
+    }
+
+    //from now on changed models, materials, textures and shaders (the named ones and the built in ones
+    //in StateConfig::shader_dir) are reloaded during update()
+    //a broken shader keeps the old one and logs naga's error
+    pub fn enable_hot_reload(&mut self) {
+        if self.file_watcher.is_none() {
+            let watcher = FileWatcher::new(Duration::from_millis(250));
+            if let Some(dir) = &self.shader_dir {
+                watcher.watch(dir.join(MAIN_SHADER));
+                watcher.watch(dir.join(DEBUG_SHADER));
+            }
+            self.file_watcher = Some(watcher);
+        }
+    }
+
+    pub fn disable_hot_reload(&mut self) {
+        self.file_watcher = None;
+    }
+
+    pub fn is_hot_reloading(&self) -> bool {
+        self.file_watcher.is_some()
+    }
+
+    fn poll_hot_reload(&mut self) {
+        let Some(watcher) = &self.file_watcher else {
+            return;
+        };
+        //picks up the assets loaded since the last frame
+        for file in self.assets.files() {
+            watcher.watch(file);
+        }
+        for file in self.shader_files.keys() {
+            watcher.watch(file);
+        }
+        for file in watcher.changed() {
+            let built_in = self.shader_dir.as_ref().and_then(|dir| {
+                let name = file.strip_prefix(dir).ok()?;
+                [MAIN_SHADER, DEBUG_SHADER]
+                    .into_iter()
+                    .find(|shader| name == Path::new(shader))
+            });
+            if let Some(&name) = self.shader_files.get(&file) {
+                self.reload_named_shader(name, &file);
+            } else if built_in == Some(MAIN_SHADER) {
+                self.reload_main_shader(&file);
+            } else if built_in == Some(DEBUG_SHADER) {
+                self.reload_debug_shader(&file);
+            } else {
+                self.assets.reload(&file);
+            }
+        }
+    }
+
+    //like shaders.insert, but with hot reload on the module is replaced when the file changes
+    //pipelines made from the old module have to be rebuilt by whoever made them
+    pub fn insert_shader<P: Into<PathBuf>>(
+        &mut self,
+        name: &'static str,
+        path: P,
+        shader: wgpu::ShaderModule,
+    ) {
+        self.shader_files.retain(|_, existing| *existing != name);
+        self.shader_files.insert(path.into(), name);
+        self.shaders.insert(name, shader);
+    }
+
+    fn reload_named_shader(&mut self, name: &'static str, path: &Path) {
+        if let Some(shader) = self.build_shader(path, |_, shader| shader.clone()) {
+            self.shaders.insert(name, shader);
+            log::info!("reloaded shader {}", path.display());
+        }
+    }
+
+    fn reload_main_shader(&mut self, path: &Path) {
+        let pipelines = self.build_shader(path, |state, shader| {
+            let render_pipeline = create_render_pipeline(
+                &state.device,
+                &state.render_pipeline_layout,
+                shader,
+                state.config.format,
+                wgpu::CompareFunction::Less,
+            );
+            let reverse_z_render_pipeline = create_render_pipeline(
+                &state.device,
+                &state.render_pipeline_layout,
+                shader,
+                state.config.format,
+                wgpu::CompareFunction::Greater,
+            );
+            (render_pipeline, reverse_z_render_pipeline)
+        });
+        if let Some((render_pipeline, reverse_z_render_pipeline)) = pipelines {
+            self.render_pipeline = render_pipeline;
+            self.reverse_z_render_pipeline = reverse_z_render_pipeline;
+            log::info!("reloaded shader {}", path.display());
+        }
+    }
+
+    fn reload_debug_shader(&mut self, path: &Path) {
+        let pipelines = self.build_shader(path, |state, shader| {
+            state
+                .debug_renderer
+                .pipelines_for(&state.device, shader, state.config.format)
+        });
+        if let Some(pipelines) = pipelines {
+            self.debug_renderer.set_pipelines(pipelines);
+            log::info!("reloaded shader {}", path.display());
+        }
+    }
+
+    //reads and validates the shader and builds what it's used for, None (and logged) if any of it fails
+    fn build_shader<T>(
+        &self,
+        path: &Path,
+        build: impl FnOnce(&Self, &wgpu::ShaderModule) -> T,
+    ) -> Option<T> {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                log::error!("couldn't read shader {}: {error}", path.display());
+                return None;
+            }
+        };
+        if let Err(error) = validate_wgsl(&source, path) {
+            log::error!(
+                "shader {} not reloaded, keeping the old one:\n{error}",
+                path.display()
+            );
+            return None;
+        }
+        //naga is happy, but the pipelines could still not match the shader
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: path.file_name().and_then(|name| name.to_str()),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+        let built = build(self, &shader);
+        if let Some(error) = block_on(self.device.pop_error_scope()) {
+            log::error!(
+                "shader {} not reloaded, keeping the old one:\n{error}",
+                path.display()
+            );
+            return None;
+        }
+        Some(built)
+    }
+
+    fn main_view(&self) -> &CameraView {
+        self.cameras
+            .get(&self.main_camera)
+            .expect("the main camera can't be removed")
+    }
+
+    fn main_view_mut(&mut self) -> &mut CameraView {
+        self.cameras
+            .get_mut(&self.main_camera)
+            .expect("the main camera can't be removed")
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.main_view().camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.main_view_mut().camera
+    }
+
+    pub fn projection(&self) -> &Projection {
+        &self.main_view().projection
+    }
+
+    //switching to/from reverse-Z is fine here, the pipeline gets picked per frame
+    pub fn projection_mut(&mut self) -> &mut Projection {
+        &mut self.main_view_mut().projection
+    }
+
+    fn next_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    //new cameras draw to the full target with priority 0, change that with camera_view_mut
+    //only the first camera of a target clears its color, the clear would wipe the ones drawn before
+    pub fn add_camera(
+        &mut self,
+        camera: Camera,
+        projection: Projection,
+        target: RenderTarget,
+    ) -> CameraId {
+        let id = CameraId(self.next_id());
+        let mut view = CameraView::new(
+            &self.device,
+            &self.camera_bind_group_layout,
+            camera,
+            projection,
+            target,
+        );
+        if self.cameras.values().any(|other| other.target == target) {
+            view.clear.color = None;
+        }
+        self.cameras.insert(id, view);
+        id
+    }
+
+    //the main camera stays, returns None for it
+    pub fn remove_camera(&mut self, id: CameraId) -> Option<CameraView> {
+        if id == self.main_camera {
+            return None;
+        }
+        self.cameras.remove(&id)
+    }
+
+    pub fn camera_view(&self, id: CameraId) -> Option<&CameraView> {
+        self.cameras.get(&id)
+    }
+
+    pub fn camera_view_mut(&mut self, id: CameraId) -> Option<&mut CameraView> {
+        self.cameras.get_mut(&id)
+    }
+
+    pub fn camera_ids(&self) -> impl Iterator<Item = CameraId> + '_ {
+        self.cameras.keys().copied()
+    }
+
+    pub fn main_camera(&self) -> CameraId {
+        self.main_camera
+    }
+
+    //the camera controller moves this camera from now on, false if it doesn't exist
+    pub fn set_main_camera(&mut self, id: CameraId) -> bool {
+        if !self.cameras.contains_key(&id) {
+            return false;
+        }
+        self.main_camera = id;
+        true
+    }
+
+    //draws the model once per instance from now on, an empty slice hides it
+    //false if no model with that name is loaded
+    pub fn set_instances(&mut self, model: &str, instances: &[Instance]) -> bool {
+        let Some((name, _)) = self.models.get_key_value(model) else {
+            return false;
+        };
+        let raw = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        match self.model_instances.get_mut(model) {
+            Some(buffer) if buffer.capacity >= raw.len() => {
+                self.queue
+                    .write_buffer(&buffer.buffer, 0, bytemuck::cast_slice(&raw));
+                buffer.count = raw.len() as u32;
+            }
+            _ => {
+                let buffer = InstanceBuffer::new(&self.device, &raw);
+                self.model_instances.insert(name.clone(), buffer);
+            }
+        }
+        true
+    }
+
+    //models with at least one instance from set_instances
+    pub fn instanced_models(&self) -> impl Iterator<Item = Arc<str>> + '_ {
+        self.model_instances
+            .iter()
+            .filter(|(_, buffer)| buffer.count > 0)
+            .map(|(name, _)| name.clone())
+    }
+
+    //loads on a background thread, the returned future resolves once it's done
+    //a model already drawn under the name stays until the new one is loaded (in update()),
+    //so this also swaps models at runtime, a failed load keeps the old one
+    pub fn load_model<N: Into<Arc<str>>, P: AsRef<Path>>(
+        &mut self,
+        name: N,
+        path: P,
+    ) -> impl Future<Output = Result<Handle<Model>, ModelError>> + Send + 'static {
+        let handle = self.assets.load_model(path);
+        self.pending_models.push((name.into(), handle.clone()));
+        async move {
+            match handle.ready().await {
+                Ok(_) => Ok(handle),
+                Err(error) => Err(ModelError::AssetError(handle.path().to_path_buf(), error)),
+            }
+        }
+    }
+
+    //a model made in code, e.g. with Model::from_mesh_data
+    pub fn insert_model<N: Into<Arc<str>>>(&mut self, name: N, model: Model) -> Handle<Model> {
+        let name = name.into();
+        let handle = Handle::loaded(&*name, model);
+        self.replace_model(name, handle.clone());
+        handle
+    }
+
+    //draws handle under the name right away, instances from set_instances are kept
+    //returns the model it replaced
+    pub fn replace_model<N: Into<Arc<str>>>(
+        &mut self,
+        name: N,
+        handle: Handle<Model>,
+    ) -> Option<Handle<Model>> {
+        let name = name.into();
+        self.pending_models.retain(|(pending, _)| *pending != name);
+        self.models.insert(name, handle)
+    }
+
+    //stops drawing the model and forgets its instances, the GPU buffers are freed
+    //once the last handle to it is dropped
+    pub fn unload_model(&mut self, name: &str) -> Option<Handle<Model>> {
+        self.pending_models
+            .retain(|(pending, _)| &**pending != name);
+        self.model_instances.remove(name);
+        self.models.remove(name)
+    }
+
+    pub fn model(&self, name: &str) -> Option<&Handle<Model>> {
+        self.models.get(name)
+    }
+
+    fn promote_loaded_models(&mut self) {
+        let mut index = 0;
+        while index < self.pending_models.len() {
+            let (name, handle) = &self.pending_models[index];
+            match handle.load_state() {
+                LoadState::Loading => index += 1,
+                LoadState::Loaded => {
+                    let (name, handle) = self.pending_models.remove(index);
+                    self.models.insert(name, handle);
+                }
+                //the loader already logged it
+                LoadState::Failed(_) => {
+                    log::warn!(
+                        "keeping the previous {name:?} model, {:?} failed to load",
+                        handle.path()
+                    );
+                    self.pending_models.remove(index);
+                }
+            }
+        }
+    }
+
+    pub fn create_render_texture(&mut self, width: u32, height: u32) -> RenderTextureId {
+        let id = RenderTextureId(self.next_id());
+        let texture = RenderTexture::new(&self.device, width, height, self.config.format);
+        self.render_textures.insert(id, texture);
+        id
+    }
+
+    //cameras still pointing at it just stop rendering
+    pub fn remove_render_texture(&mut self, id: RenderTextureId) -> Option<RenderTexture> {
+        self.render_textures.remove(&id)
+    }
+
+    pub fn render_texture(&self, id: RenderTextureId) -> Option<&RenderTexture> {
+        self.render_textures.get(&id)
+    }
+
+    //material showing what a camera rendered into the texture, e.g. for a security camera screen
+    //don't use it on a model the same camera renders, a texture can't be read and written in one pass
+    pub fn render_texture_material(
+        &self,
+        id: RenderTextureId,
+        name: &str,
+    ) -> Option<model::Material> {
+        let texture = self.render_textures.get(&id)?;
+        Some(model::Material::new(
+            &self.device,
+            &self.texture_bind_group_layout,
+            name.to_string(),
+            texture.color.clone(),
+        ))
+    }
+
+    fn target_size(&self, target: RenderTarget) -> Option<(u32, u32)> {
+        match target {
+            RenderTarget::Surface => Some((self.config.width, self.config.height)),
+            RenderTarget::Texture(id) => self.render_textures.get(&id).map(RenderTexture::size),
+        }
+    }
+
     pub fn window(&self) -> &Window {
         &self.window
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         if width > 0 && height > 0 {
-            self.projection.resize(width, height);
             self.is_surface_configured = true;
             self.config.width = width;
             self.config.height = height;
@@ -382,24 +777,33 @@ impl State {
 
     //TODO!: refactor or remove and replace
     pub fn update(&mut self, dt: Duration) {
-        self.camera_controller.update_camera(&mut self.camera, dt);
-        //log::info!("{:?}", self.camera);
-        self.camera_uniform
-            .update_view_proj(&self.camera, &self.projection);
-        log::info!("{:?}", self.camera_uniform);
-        self.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
-        );
+        self.poll_hot_reload();
+        self.promote_loaded_models();
+        let main_camera = self.main_camera;
+        self.camera_controller.handle_input(&self.input);
+        if let Some(view) = self.cameras.get_mut(&main_camera) {
+            self.camera_controller.update_camera(&mut view.camera, dt);
+        }
+        let sizes = self
+            .cameras
+            .iter()
+            .map(|(id, view)| (*id, self.target_size(view.target)))
+            .collect::<Vec<_>>();
+        for (id, size) in sizes {
+            if let (Some(view), Some((width, height))) = (self.cameras.get_mut(&id), size) {
+                view.update(&self.queue, width, height);
+            }
+        }
     }
 
     //TODO!: refactor!
-    pub fn render(
+    pub fn render<S: AsRef<str>>(
         &mut self,
-        model_ids: impl Iterator<Item = &'static str>,
+        model_ids: impl Iterator<Item = S>,
     ) -> Result<(), wgpu::SurfaceError> {
         self.window.request_redraw();
+        //everything had its chance to read the last frame's presses by now
+        self.input.end_frame();
 
         // We can't render unless the surface is configured
         if !self.is_surface_configured {
@@ -411,58 +815,110 @@ impl State {
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        self.debug_renderer
+            .prepare(&self.device, &self.queue, &self.debug_draw);
+
+        //models can be drawn by several cameras, unknown and unloaded ones are skipped
+        let models = model_ids
+            .filter_map(|id| {
+                let model = self.models.get(id.as_ref())?.get()?;
+                Some((id, model))
+            })
+            .collect::<Vec<_>>();
+
+        //the id breaks ties, so cameras with the same priority keep their order between frames
+        let mut views = self
+            .cameras
+            .iter()
+            .filter(|(_, view)| view.active)
+            .collect::<Vec<_>>();
+        views.sort_by_key(|(id, view)| (view.priority, **id));
+        let views = views.into_iter().map(|(_, view)| view).collect::<Vec<_>>();
+
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.1,
-                        g: 0.2,
-                        b: 0.3,
-                        a: 1.0,
-                    }),
-                    store: wgpu::StoreOp::Store,
+        for camera_view in views {
+            let (color_view, depth_view, (width, height)) = match camera_view.target {
+                RenderTarget::Surface => (
+                    &view,
+                    &self.depth_texture.view,
+                    (self.config.width, self.config.height),
+                ),
+                RenderTarget::Texture(id) => match self.render_textures.get(&id) {
+                    Some(texture) => (&texture.color.view, &texture.depth.view, texture.size()),
+                    None => continue,
                 },
-                depth_slice: None,
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_texture.view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store,
+            };
+            let reverse_z = camera_view.projection.is_reverse_z();
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: color_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: match camera_view.clear.color {
+                            Some(color) => wgpu::LoadOp::Clear(color),
+                            None => wgpu::LoadOp::Load,
+                        },
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: if camera_view.clear.depth {
+                            wgpu::LoadOp::Clear(if reverse_z { 0.0 } else { 1.0 })
+                        } else {
+                            wgpu::LoadOp::Load
+                        },
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
                 }),
-                stencil_ops: None,
-            }),
-            occlusion_query_set: None,
-            timestamp_writes: None,
-        });
-
-        model_ids.for_each(|model_id| {
-            //println!("Model ID: {}", model_id);
-            //log::info!("rendering {:?}", model_id);
-
-            //TODO: model existence guarantees?!
-
-            //render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-
-            let model = &mut self.models.get(model_id).expect("???");
-
-            render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
-
-            render_pass.set_pipeline(&self.render_pipeline);
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
 
-            render_pass.draw_model(model, &self.camera_bind_group);
-        });
+            let [x, y, viewport_width, viewport_height] =
+                camera_view.viewport.to_pixels(width, height);
+            render_pass.set_viewport(x, y, viewport_width, viewport_height, 0.0, 1.0);
+
+            for (model_id, model) in models.iter() {
+                render_pass.set_pipeline(if reverse_z {
+                    &self.reverse_z_render_pipeline
+                } else {
+                    &self.render_pipeline
+                });
+
+                let model = model.as_ref();
+                match self.model_instances.get(model_id.as_ref()) {
+                    Some(instances) => {
+                        render_pass.set_vertex_buffer(0, instances.buffer.slice(..));
+                        render_pass.draw_model_instanced(
+                            model,
+                            0..instances.count,
+                            &camera_view.bind_group,
+                        );
+                    }
+                    None => {
+                        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+                        render_pass.draw_model(model, &camera_view.bind_group);
+                    }
+                }
+            }
 
-        drop(render_pass);
+            if camera_view.show_debug {
+                self.debug_renderer
+                    .draw(&mut render_pass, &camera_view.bind_group, reverse_z);
+            }
+        }
+        self.debug_draw.clear();
 
         self.queue.submit(iter::once(encoder.finish()));
         output.present();
@@ -470,3 +926,93 @@ impl State {
         Ok(())
     }
 }
+
+struct InstanceBuffer {
+    buffer: wgpu::Buffer,
+    //in instances
+    capacity: usize,
+    count: u32,
+}
+
+impl InstanceBuffer {
+    fn new(device: &wgpu::Device, raw: &[InstanceRaw]) -> Self {
+        //wgpu doesn't like empty buffers
+        let contents = if raw.is_empty() {
+            vec![bytemuck::Zeroable::zeroed()]
+        } else {
+            raw.to_vec()
+        };
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Model Instance Buffer"),
+            contents: bytemuck::cast_slice(&contents),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            buffer,
+            capacity: contents.len(),
+            count: raw.len() as u32,
+        }
+    }
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    depth_compare: wgpu::CompareFunction,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[model::ModelVertex::desc(), InstanceRaw::desc()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent::REPLACE,
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
+            // or Features::POLYGON_MODE_POINT
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        // If the pipeline will be used with a multiview render pass, this
+        // indicates how many array layers the attachments will have.
+        multiview: None,
+        // Useful for optimizing shader compilation on Android
+        cache: None,
+    })
+}
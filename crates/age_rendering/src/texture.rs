@@ -15,10 +15,19 @@ impl Texture {
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
         label: &str,
+    ) -> Self {
+        Self::create_depth_texture_sized(device, config.width, config.height, label)
+    }
+
+    pub fn create_depth_texture_sized(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        label: &str,
     ) -> Self {
         let size = wgpu::Extent3d {
-            width: config.width.max(1),
-            height: config.height.max(1),
+            width: width.max(1),
+            height: height.max(1),
             depth_or_array_layers: 1,
         };
         let desc = wgpu::TextureDescriptor {
@@ -54,16 +63,73 @@ impl Texture {
         }
     }
 
+    //color texture that can be rendered into and sampled afterwards
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         bytes: &[u8],
         label: &str,
     ) -> Result<Self, TextureError> {
-        let img = image::load_from_memory(bytes).map_err(TextureError::ImageError)?;
+        let img = image::load_from_memory(bytes)
+            .map_err(|error| TextureError::ImageError(label.to_string(), error))?;
         Self::from_image(device, queue, &img, Some(label))
     }
 
+    //a 1x1 texture, e.g. for plain colored materials
+    pub fn from_color(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color: [u8; 4],
+        label: &str,
+    ) -> Result<Self, TextureError> {
+        let img = image::RgbaImage::from_pixel(1, 1, image::Rgba(color));
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some(label),
+        )
+    }
+
     //change the return type later maybe?
     pub fn from_image(
         device: &wgpu::Device,
@@ -0,0 +1,416 @@
+//what resources::load_string/load_binary read through
+//mounts are tried newest first, loose files on disk are always the fallback
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+pub const ARCHIVE_MAGIC: &[u8; 8] = b"AGEPAK\0\x01";
+//u16 path length, u64 offset, u64 size, u64 original size, u8 compressed, without the path
+const ENTRY_SIZE: u64 = 2 + 8 * 3 + 1;
+//deflate can't do better than about 1032:1, anything claiming more is corrupt
+const MAX_DEFLATE_RATIO: u64 = 1032;
+//sizes come from the file, only trust them this far before actually having the data
+const MAX_PREALLOCATION: usize = 16 * 1024 * 1024;
+
+//somewhere files can be read from, paths are relative to the mount point and use '/'
+pub trait FileSource: Send + Sync {
+    //io::ErrorKind::NotFound if the source doesn't have it, the next mount is tried then
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+    fn contains(&self, path: &str) -> bool;
+}
+
+//loose files below a directory
+pub struct Directory {
+    root: PathBuf,
+}
+
+impl Directory {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl FileSource for Directory {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        std::fs::read(self.root.join(path))
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.root.join(path).is_file()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    offset: u64,
+    //as stored in the archive
+    size: u64,
+    //after decompression
+    original_size: u64,
+    compressed: bool,
+}
+
+trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+//a packed archive made by ArchiveBuilder (or the age_pack tool)
+//layout: magic, u32 entry count, entries (u16 path length, path, u64 offset, u64 size,
+//u64 original size, u8 compressed), then the blobs, all little endian
+pub struct Archive {
+    data: Mutex<Box<dyn ReadSeek>>,
+    index: HashMap<String, Entry>,
+}
+
+impl Archive {
+    //only the index is read here, the files are read when they're needed
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_reader(Box::new(BufReader::new(File::open(path)?)))
+    }
+
+    //e.g. for an archive from include_bytes!
+    pub fn from_bytes<B: Into<Vec<u8>>>(bytes: B) -> io::Result<Self> {
+        Self::from_reader(Box::new(Cursor::new(bytes.into())))
+    }
+
+    //every entry is checked against the length of the data, so a corrupt or truncated
+    //archive fails here instead of on some later read (or with a huge allocation)
+    fn from_reader(mut data: Box<dyn ReadSeek>) -> io::Result<Self> {
+        let length = data.seek(SeekFrom::End(0))?;
+        data.seek(SeekFrom::Start(0))?;
+        let mut magic = [0; 8];
+        data.read_exact(&mut magic)?;
+        if &magic != ARCHIVE_MAGIC {
+            return Err(invalid_data("not an AGE archive"));
+        }
+        let count = read_u32(&mut data)?;
+        if count as u64 * ENTRY_SIZE > length {
+            return Err(invalid_data("archive is shorter than its index"));
+        }
+        let mut index = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let path_length = read_u16(&mut data)? as usize;
+            let mut path = vec![0; path_length];
+            data.read_exact(&mut path)?;
+            let path = String::from_utf8(path).map_err(|_| invalid_data("path isn't UTF-8"))?;
+            let entry = Entry {
+                offset: read_u64(&mut data)?,
+                size: read_u64(&mut data)?,
+                original_size: read_u64(&mut data)?,
+                compressed: read_u8(&mut data)? != 0,
+            };
+            if entry
+                .offset
+                .checked_add(entry.size)
+                .is_none_or(|end| end > length)
+            {
+                return Err(invalid_data(&format!(
+                    "{path} points past the end of the archive"
+                )));
+            }
+            let plausible = if entry.compressed {
+                entry.original_size <= entry.size.saturating_mul(MAX_DEFLATE_RATIO)
+            } else {
+                entry.original_size == entry.size
+            };
+            if !plausible {
+                return Err(invalid_data(&format!("{path} has an impossible size")));
+            }
+            index.insert(path, entry);
+        }
+        Ok(Self {
+            data: Mutex::new(data),
+            index,
+        })
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+impl FileSource for Archive {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let Some(entry) = self.index.get(path) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{path} isn't in the archive"),
+            ));
+        };
+        let mut stored = vec![0; entry.size as usize];
+        {
+            let mut data = self.data.lock().expect("Only panics if poisoned");
+            data.seek(SeekFrom::Start(entry.offset))?;
+            data.read_exact(&mut stored)?;
+        }
+        if !entry.compressed {
+            return Ok(stored);
+        }
+        let mut original =
+            Vec::with_capacity((entry.original_size as usize).min(MAX_PREALLOCATION));
+        DeflateDecoder::new(stored.as_slice())
+            .take(entry.original_size)
+            .read_to_end(&mut original)?;
+        if original.len() as u64 != entry.original_size {
+            return Err(invalid_data(&format!(
+                "{path} doesn't decompress to its original size"
+            )));
+        }
+        Ok(original)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.index.contains_key(path)
+    }
+}
+
+//collects files and writes them as an Archive
+pub struct ArchiveBuilder {
+    files: Vec<(String, Vec<u8>)>,
+    compress: bool,
+}
+
+impl ArchiveBuilder {
+    pub fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            compress: true,
+        }
+    }
+
+    //files are only stored compressed if that makes them smaller
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    //path is what the file is read as, relative to the mount point
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P, data: Vec<u8>) -> io::Result<()> {
+        let path = path.as_ref();
+        if path.to_str().is_none() {
+            return Err(invalid_data(&format!(
+                "{} isn't valid UTF-8, archive paths must be",
+                path.display()
+            )));
+        }
+        let path = normalize(path)
+            .ok_or_else(|| invalid_data("archive paths must be relative and stay inside it"))?;
+        if path.len() > u16::MAX as usize {
+            return Err(invalid_data("path too long"));
+        }
+        self.files.retain(|(existing, _)| *existing != path);
+        self.files.push((path, data));
+        Ok(())
+    }
+
+    //every file below root, with paths relative to it
+    //symlinks are skipped, they could point outside root or loop
+    //files are added sorted, so the same directory always packs into the same archive
+    pub fn add_dir<P: AsRef<Path>>(&mut self, root: P) -> io::Result<()> {
+        let root = root.as_ref();
+        let mut directories = vec![root.to_path_buf()];
+        while let Some(directory) = directories.pop() {
+            let mut entries = std::fs::read_dir(&directory)?.collect::<io::Result<Vec<_>>>()?;
+            entries.sort_by_key(|entry| entry.file_name());
+            let mut subdirectories = Vec::new();
+            for entry in entries {
+                let file_type = entry.file_type()?;
+                let path = entry.path();
+                if file_type.is_symlink() {
+                    log::warn!("not packing symlink {}", path.display());
+                } else if file_type.is_dir() {
+                    subdirectories.push(path);
+                } else {
+                    let relative = path.strip_prefix(root).map_err(io::Error::other)?;
+                    self.add_file(relative, std::fs::read(&path)?)?;
+                }
+            }
+            //popped first to last
+            directories.extend(subdirectories.into_iter().rev());
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut blobs = Vec::with_capacity(self.files.len());
+        for (path, data) in &self.files {
+            let compressed = if self.compress {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                Some(encoder.finish()?).filter(|compressed| compressed.len() < data.len())
+            } else {
+                None
+            };
+            blobs.push((path, data, compressed));
+        }
+
+        let header_size = ARCHIVE_MAGIC.len()
+            + 4
+            + blobs
+                .iter()
+                .map(|(path, _, _)| 2 + path.len() + 8 * 3 + 1)
+                .sum::<usize>();
+        out.write_all(ARCHIVE_MAGIC)?;
+        out.write_all(&(blobs.len() as u32).to_le_bytes())?;
+        let mut offset = header_size as u64;
+        for (path, data, compressed) in &blobs {
+            let size = compressed.as_ref().map_or(data.len(), Vec::len) as u64;
+            out.write_all(&(path.len() as u16).to_le_bytes())?;
+            out.write_all(path.as_bytes())?;
+            out.write_all(&offset.to_le_bytes())?;
+            out.write_all(&size.to_le_bytes())?;
+            out.write_all(&(data.len() as u64).to_le_bytes())?;
+            out.write_all(&[compressed.is_some() as u8])?;
+            offset += size;
+        }
+        for (_, data, compressed) in &blobs {
+            out.write_all(compressed.as_deref().unwrap_or(data))?;
+        }
+        out.flush()
+    }
+}
+
+impl Default for ArchiveBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Mount {
+    //normalized, "" for the working directory
+    at: String,
+    source: Arc<dyn FileSource>,
+}
+
+impl Mount {
+    fn relative<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if self.at.is_empty() {
+            return Some(path);
+        }
+        path.strip_prefix(self.at.as_str())?.strip_prefix('/')
+    }
+}
+
+static MOUNTS: RwLock<Vec<Mount>> = RwLock::new(Vec::new());
+
+//files below `at` (relative to the working directory, like every other path) are looked up in the source
+//e.g. mount("res", Archive::open("res.pak")?) serves "res/cube/cube.obj" from the archive's "cube/cube.obj"
+pub fn mount<P: AsRef<Path>, S: FileSource + 'static>(at: P, source: S) -> io::Result<()> {
+    let at = normalize(at.as_ref())
+        .ok_or_else(|| invalid_data("mount points must be relative paths"))?;
+    MOUNTS
+        .write()
+        .expect("Only panics if poisoned")
+        .push(Mount {
+            at,
+            source: Arc::new(source),
+        });
+    Ok(())
+}
+
+//back to loose files only
+pub fn unmount_all() {
+    MOUNTS.write().expect("Only panics if poisoned").clear();
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let path = path.as_ref();
+    if let Some(normalized) = normalize(path) {
+        let mounts = MOUNTS.read().expect("Only panics if poisoned");
+        for mount in mounts.iter().rev() {
+            let Some(relative) = mount.relative(&normalized) else {
+                continue;
+            };
+            match mount.source.read(relative) {
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                result => return result,
+            }
+        }
+    }
+    std::fs::read(path)
+}
+
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|_| invalid_data("file isn't UTF-8"))
+}
+
+pub fn exists<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    if let Some(normalized) = normalize(path) {
+        let mounts = MOUNTS.read().expect("Only panics if poisoned");
+        let mounted = mounts.iter().any(|mount| {
+            mount
+                .relative(&normalized)
+                .is_some_and(|relative| mount.source.contains(relative))
+        });
+        if mounted {
+            return true;
+        }
+    }
+    path.is_file()
+}
+
+//"a/./b/../c" -> "a/c", None for absolute paths and ones that leave the root
+//so "cube/cube.obj" and the texture "cube/../cube/tex.png" an MTL points at both resolve
+pub fn normalize(path: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u8<R: Read + ?Sized>(data: &mut R) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    data.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16<R: Read + ?Sized>(data: &mut R) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    data.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32<R: Read + ?Sized>(data: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    data.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read + ?Sized>(data: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    data.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
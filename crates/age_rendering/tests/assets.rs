@@ -0,0 +1,87 @@
+use age_rendering::assets::{AssetCache, Handle, LoadState};
+use std::sync::Arc;
+
+#[test]
+fn same_path_same_handle() {
+    let cache = AssetCache::<u32>::new();
+    let (first, pending) = cache.get_or_insert("a.obj");
+    let (second, again) = cache.get_or_insert("a.obj");
+    assert!(pending.is_some());
+    assert!(again.is_none());
+    assert_eq!(first, second);
+    assert_eq!(first.handle_count(), 2);
+}
+
+#[test]
+fn load_states() {
+    let (handle, pending) = Handle::<u32>::pending("a.obj");
+    assert_eq!(handle.load_state(), LoadState::Loading);
+    assert!(handle.get().is_none());
+    pending.finish(Ok(7));
+    assert_eq!(handle.load_state(), LoadState::Loaded);
+    assert_eq!(handle.get().as_deref(), Some(&7));
+
+    let (handle, pending) = Handle::<u32>::pending("b.obj");
+    pending.finish(Err("broken".to_string()));
+    assert_eq!(handle.load_state(), LoadState::Failed("broken".to_string()));
+}
+
+#[test]
+fn dropped_loader_fails_the_handle() {
+    let (handle, pending) = Handle::<u32>::pending("a.obj");
+    drop(pending);
+    assert!(matches!(handle.load_state(), LoadState::Failed(_)));
+}
+
+#[test]
+fn wait_for_another_thread() {
+    let (handle, pending) = Handle::<u32>::pending("a.obj");
+    let loader = std::thread::spawn(move || pending.finish(Ok(3)));
+    assert_eq!(handle.wait().as_deref().copied(), Ok(3));
+    loader.join().unwrap();
+}
+
+#[test]
+fn asset_is_freed_with_the_last_handle() {
+    let cache = AssetCache::<Arc<()>>::new();
+    let asset = Arc::new(());
+    let (handle, pending) = cache.get_or_insert("a.obj");
+    pending.unwrap().finish(Ok(asset.clone()));
+    let copy = handle.clone();
+    drop(handle);
+    assert_eq!(Arc::strong_count(&asset), 2);
+    assert_eq!(cache.len(), 1);
+    drop(copy);
+    assert_eq!(Arc::strong_count(&asset), 1);
+    assert!(cache.is_empty());
+    //loaded again from scratch
+    let (_, pending) = cache.get_or_insert("a.obj");
+    assert!(pending.is_some());
+}
+
+#[test]
+fn replaced_assets_keep_their_handles() {
+    let cache = AssetCache::<u32>::new();
+    let (handle, pending) = cache.get_or_insert("a.obj");
+    pending.unwrap().finish(Ok(1));
+    let copy = cache.get("a.obj").unwrap();
+    handle.replace(2);
+    assert_eq!(copy.get().as_deref(), Some(&2));
+    assert_eq!(cache.paths(), vec![std::path::PathBuf::from("a.obj")]);
+}
+
+#[test]
+fn ready_resolves_when_another_thread_finishes() {
+    let (handle, pending) = Handle::<u32>::pending("a.obj");
+    let ready = handle.ready();
+    let loader = std::thread::spawn(move || pending.finish(Ok(5)));
+    assert_eq!(pollster::block_on(ready).as_deref().copied(), Ok(5));
+    loader.join().unwrap();
+
+    let (handle, pending) = Handle::<u32>::pending("b.obj");
+    pending.finish(Err("broken".to_string()));
+    assert_eq!(
+        pollster::block_on(handle.ready()).map(|_| ()),
+        Err("broken".to_string())
+    );
+}
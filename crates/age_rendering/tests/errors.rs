@@ -0,0 +1,52 @@
+use age_rendering::errors::{ModelError, TextureError, describe};
+use std::error::Error;
+use std::io;
+use std::path::PathBuf;
+
+#[test]
+fn messages_name_the_file_and_material() {
+    let error = ModelError::TextureError(
+        PathBuf::from("res/cube/cube.obj"),
+        "Material.001".to_string(),
+        Box::new(TextureError::IoError(
+            PathBuf::from("res/cube/cube-diffuse.jpg"),
+            io::Error::new(io::ErrorKind::NotFound, "missing"),
+        )),
+    );
+    let message = error.to_string();
+    assert!(message.contains("res/cube/cube.obj"), "{message}");
+    assert!(message.contains("Material.001"), "{message}");
+    //the texture's own message is left to source()
+    assert!(!message.contains("res/cube/cube-diffuse.jpg"), "{message}");
+    assert!(!message.contains("missing"), "{message}");
+
+    let description = describe(&error);
+    assert!(
+        description.ends_with("res/cube/cube-diffuse.jpg: missing"),
+        "{description}"
+    );
+}
+
+#[test]
+fn sources_chain_down_to_the_io_error() {
+    let error = ModelError::TextureError(
+        PathBuf::from("a.obj"),
+        "material".to_string(),
+        Box::new(TextureError::IoError(
+            PathBuf::from("a.png"),
+            io::Error::new(io::ErrorKind::NotFound, "missing"),
+        )),
+    );
+    let texture = error.source().unwrap();
+    assert!(texture.downcast_ref::<TextureError>().is_some());
+    let io = texture.source().unwrap();
+    assert_eq!(
+        io.downcast_ref::<io::Error>().unwrap().kind(),
+        io::ErrorKind::NotFound
+    );
+    assert!(
+        ModelError::MissingTexture(PathBuf::from("a.obj"), "material".to_string())
+            .source()
+            .is_none()
+    );
+}
@@ -0,0 +1,56 @@
+use age_rendering::hot_reload::{DEBUG_SHADER, FileWatcher, MAIN_SHADER, validate_wgsl};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+fn wait_for_change(watcher: &FileWatcher) -> Vec<std::path::PathBuf> {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        let changed = watcher.changed();
+        if !changed.is_empty() {
+            return changed;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Vec::new()
+}
+
+#[test]
+fn reports_changed_files() {
+    let path = std::env::temp_dir().join(format!("age_hot_reload_{}.txt", std::process::id()));
+    std::fs::write(&path, "a").unwrap();
+    let watcher = FileWatcher::new(Duration::from_millis(10));
+    watcher.watch(&path);
+    assert!(watcher.is_watched(&path));
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(watcher.changed().is_empty());
+
+    //some file systems only have second precision
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(modified + Duration::from_secs(2))
+        .unwrap();
+    assert_eq!(wait_for_change(&watcher), vec![path.clone()]);
+
+    watcher.unwatch(&path);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn the_built_in_shaders_are_valid() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    for shader in [MAIN_SHADER, DEBUG_SHADER] {
+        let path = dir.join(shader);
+        let source = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(validate_wgsl(&source, &path), Ok(()));
+    }
+}
+
+#[test]
+fn broken_shaders_report_naga_errors() {
+    let path = Path::new("broken.wgsl");
+    let error = validate_wgsl("fn f( {", path).unwrap_err();
+    assert!(error.contains("broken.wgsl"), "{error}");
+    //parses, but isn't valid
+    let error = validate_wgsl("@vertex fn vs_main() -> f32 { return 1.0; }", path).unwrap_err();
+    assert!(error.contains("vs_main"), "{error}");
+}
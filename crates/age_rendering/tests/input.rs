@@ -0,0 +1,158 @@
+use age_rendering::camera::Camera;
+use age_rendering::camera::controller::{CameraControl, OrbitController};
+use age_rendering::errors::InputProfileError;
+use age_rendering::input::bindings::{AxisBinding, GamepadAxis, MouseAxis};
+use age_rendering::input::profile::InputProfile;
+use age_rendering::input::{Input, actions};
+use cgmath::Deg;
+use std::time::Duration;
+use winit::event::{MouseButton, MouseScrollDelta};
+use winit::keyboard::KeyCode;
+
+#[test]
+fn profiles_round_trip_through_toml() {
+    let mut profile = InputProfile::default();
+    profile.bind_action("jump", KeyCode::Space);
+    profile.deadzone = 0.25;
+    assert_eq!(
+        InputProfile::from_toml(&profile.to_toml().unwrap()).unwrap(),
+        profile
+    );
+
+    let path = std::env::temp_dir().join(format!("age_input_{}.toml", std::process::id()));
+    profile.save(&path).unwrap();
+    assert_eq!(InputProfile::load(&path).unwrap(), profile);
+    std::fs::remove_file(path).unwrap();
+
+    //everything is optional
+    let profile = InputProfile::from_toml("[actions]\nexit = [{ Key = \"KeyQ\" }]").unwrap();
+    assert_eq!(profile.deadzone, InputProfile::empty().deadzone);
+    assert!(profile.axes.is_empty());
+}
+
+#[test]
+fn profile_errors_say_what_went_wrong() {
+    assert!(matches!(
+        InputProfile::load("does/not/exist.toml"),
+        Err(InputProfileError::IoError(_))
+    ));
+    assert!(matches!(
+        InputProfile::default().save(std::env::temp_dir()),
+        Err(InputProfileError::IoError(_))
+    ));
+    assert!(matches!(
+        InputProfile::from_toml("[actions]\nexit = [{ Key = \"NotAKey\" }]"),
+        Err(InputProfileError::ParseError(_))
+    ));
+    assert!(matches!(
+        InputProfile::from_toml("deadzone = \"big\""),
+        Err(InputProfileError::ParseError(_))
+    ));
+}
+
+#[test]
+fn axes_combine_their_bindings() {
+    let mut input = Input::default();
+    input.handle_key(KeyCode::KeyW, true);
+    assert_eq!(input.axis(actions::MOVE_FORWARD), 1.0);
+    //both directions cancel out
+    input.handle_key(KeyCode::KeyS, true);
+    assert_eq!(input.axis(actions::MOVE_FORWARD), 0.0);
+    input.handle_key(KeyCode::KeyS, false);
+    //keys and sticks together stay in -1..1
+    input.handle_gamepad_axis(GamepadAxis::LeftStickY, 0.5);
+    assert_eq!(input.axis(actions::MOVE_FORWARD), 1.0);
+    input.handle_key(KeyCode::KeyW, false);
+    assert_eq!(input.axis(actions::MOVE_FORWARD), 0.5);
+    //inside the deadzone
+    input.handle_gamepad_axis(GamepadAxis::LeftStickY, 0.1);
+    assert_eq!(input.axis(actions::MOVE_FORWARD), 0.0);
+    assert_eq!(input.axis("unknown"), 0.0);
+
+    //mouse deltas are added on top, unclamped
+    input.profile_mut().bind_axis(
+        actions::LOOK_X,
+        AxisBinding::Gamepad {
+            axis: GamepadAxis::RightStickX,
+            scale: 2.0,
+        },
+    );
+    input.handle_gamepad_axis(GamepadAxis::RightStickX, 1.0);
+    input.handle_mouse_motion(10.0, 0.0);
+    input.handle_mouse_motion(5.0, 0.0);
+    assert_eq!(input.axis(actions::LOOK_X), 16.0);
+
+    input.profile_mut().bind_axis(
+        actions::ZOOM,
+        AxisBinding::Mouse {
+            axis: MouseAxis::Wheel,
+            scale: -1.0,
+        },
+    );
+    input.handle_mouse_wheel(&MouseScrollDelta::LineDelta(0.0, 2.0));
+    assert_eq!(input.axis(actions::ZOOM), 0.0);
+}
+
+#[test]
+fn presses_and_releases_last_one_frame() {
+    let mut input = Input::default();
+    input.handle_key(KeyCode::Escape, true);
+    assert!(input.just_pressed(actions::EXIT));
+    assert!(input.pressed(actions::EXIT));
+    input.end_frame();
+    assert!(!input.just_pressed(actions::EXIT));
+    assert!(input.pressed(actions::EXIT));
+    //key repeat isn't a new press
+    input.handle_key(KeyCode::Escape, true);
+    assert!(!input.just_pressed(actions::EXIT));
+
+    input.handle_key(KeyCode::Escape, false);
+    assert!(input.just_released(actions::EXIT));
+    assert!(!input.pressed(actions::EXIT));
+    input.end_frame();
+    assert!(!input.just_released(actions::EXIT));
+
+    //still held through another binding, so not released
+    input
+        .profile_mut()
+        .bind_action(actions::EXIT, KeyCode::KeyQ);
+    input.handle_key(KeyCode::Escape, true);
+    input.handle_key(KeyCode::KeyQ, true);
+    input.end_frame();
+    input.handle_key(KeyCode::Escape, false);
+    assert!(!input.just_released(actions::EXIT));
+
+    input.handle_mouse_motion(3.0, 4.0);
+    input.handle_mouse_wheel(&MouseScrollDelta::LineDelta(0.0, 1.0));
+    input.end_frame();
+    assert_eq!(input.axis(actions::LOOK_X), 0.0);
+    assert_eq!(input.axis(actions::ZOOM), 0.0);
+}
+
+#[test]
+fn controllers_read_the_look_and_zoom_axes() {
+    let camera_after = |input: &Input| {
+        let mut controller = OrbitController::new((0.0, 0.0, 0.0), 10.0, 1.0);
+        let mut camera = Camera::new((0.0, 0.0, 10.0), Deg(0.0), Deg(0.0));
+        controller.handle_input(input);
+        controller.update_camera(&mut camera, Duration::from_secs(1));
+        (camera.yaw().0, controller.distance)
+    };
+    let (yaw, distance) = camera_after(&Input::default());
+
+    let mut input = Input::default();
+    input.handle_mouse_motion(0.5, 0.0);
+    input.handle_mouse_wheel(&MouseScrollDelta::LineDelta(0.0, 2.0));
+    //the mouse only looks while the look action (left button) is held
+    let (unheld_yaw, zoomed) = camera_after(&input);
+    assert_eq!(unheld_yaw, yaw);
+    assert_eq!(zoomed, distance - 1.0);
+
+    input.handle_mouse_button(MouseButton::Left, true);
+    assert_eq!(camera_after(&input).0, yaw + 0.5);
+
+    //without a look action the axes always count
+    input.handle_mouse_button(MouseButton::Left, false);
+    input.profile_mut().unbind(actions::LOOK);
+    assert_eq!(camera_after(&input).0, yaw + 0.5);
+}
@@ -0,0 +1,121 @@
+use age_rendering::mesh::MeshBuilder;
+use cgmath::{InnerSpace, Vector3};
+use std::f32::consts::PI;
+
+fn primitives() -> Vec<(&'static str, MeshBuilder)> {
+    vec![
+        ("plane", MeshBuilder::plane(2.0, 3.0, 4)),
+        ("cube", MeshBuilder::cube(2.0)),
+        ("uv_sphere", MeshBuilder::uv_sphere(1.0, 32, 16)),
+        ("icosphere", MeshBuilder::icosphere(1.0, 3)),
+        ("cylinder", MeshBuilder::cylinder(1.0, 2.0, 32)),
+        ("capsule", MeshBuilder::capsule(0.5, 2.0, 32, 8)),
+        ("cone", MeshBuilder::cone(1.0, 2.0, 32)),
+        ("torus", MeshBuilder::torus(1.0, 0.25, 32, 16)),
+    ]
+}
+
+fn triangles(mesh: &MeshBuilder) -> impl Iterator<Item = [Vector3<f32>; 3]> + '_ {
+    mesh.indices().chunks(3).map(|triangle| {
+        [0, 1, 2].map(|corner| Vector3::from(mesh.vertices()[triangle[corner] as usize].position))
+    })
+}
+
+//by the divergence theorem, negative if the faces point inwards
+fn volume(mesh: &MeshBuilder) -> f32 {
+    triangles(mesh)
+        .map(|[a, b, c]| a.dot(b.cross(c)) / 6.0)
+        .sum()
+}
+
+#[test]
+fn vertices_are_valid() {
+    for (name, mesh) in primitives() {
+        assert!(!mesh.indices().is_empty(), "{name}");
+        assert_eq!(mesh.indices().len() % 3, 0, "{name}");
+        assert!(
+            mesh.indices()
+                .iter()
+                .all(|&index| (index as usize) < mesh.vertices().len()),
+            "{name}"
+        );
+        for vertex in mesh.vertices() {
+            let length = Vector3::from(vertex.normal).magnitude();
+            assert!((length - 1.0).abs() < 1e-4, "{name}: {vertex:?}");
+            assert!(
+                vertex.tex_coords.iter().all(|uv| (0.0..=1.0).contains(uv)),
+                "{name}: {vertex:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn faces_agree_with_normals() {
+    for (name, mesh) in primitives() {
+        for (triangle, corners) in mesh.indices().chunks(3).zip(triangles(&mesh)) {
+            let [a, b, c] = corners;
+            let face = (b - a).cross(c - a);
+            if face.magnitude() < 1e-6 {
+                continue;
+            }
+            for &index in triangle {
+                let normal = Vector3::from(mesh.vertices()[index as usize].normal);
+                assert!(face.dot(normal) > 0.0, "{name}: {triangle:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn closed_meshes_have_their_volume() {
+    let expected = [
+        ("cube", 8.0),
+        ("uv_sphere", 4.0 / 3.0 * PI),
+        ("icosphere", 4.0 / 3.0 * PI),
+        ("cylinder", 2.0 * PI),
+        ("capsule", 4.0 / 3.0 * PI * 0.125 + PI * 0.25),
+        ("cone", 2.0 / 3.0 * PI),
+        ("torus", 2.0 * PI * PI * 0.0625),
+    ];
+    let primitives = primitives();
+    for (name, expected) in expected {
+        let (_, mesh) = primitives.iter().find(|(other, _)| *other == name).unwrap();
+        let volume = volume(mesh);
+        assert!(
+            (volume - expected).abs() / expected < 0.05,
+            "{name}: {volume} instead of {expected}"
+        );
+    }
+}
+
+#[test]
+fn icosphere_triangles_dont_span_the_seam() {
+    let mesh = MeshBuilder::icosphere(2.0, 2);
+    for triangle in mesh.indices().chunks(3) {
+        let us = triangle
+            .iter()
+            .map(|&index| mesh.vertices()[index as usize].tex_coords[0])
+            .collect::<Vec<_>>();
+        let span = us.iter().copied().fold(f32::MIN, f32::max)
+            - us.iter().copied().fold(f32::MAX, f32::min);
+        assert!(span < 0.5, "{us:?}");
+    }
+    for vertex in mesh.vertices() {
+        let length = Vector3::from(vertex.position).magnitude();
+        assert!((length - 2.0).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn append_offsets_indices() {
+    let mut mesh = MeshBuilder::cube(1.0);
+    let count = mesh.vertices().len();
+    mesh.append(&MeshBuilder::cube(1.0).translate([3.0, 0.0, 0.0]));
+    assert_eq!(mesh.vertices().len(), count * 2);
+    assert_eq!(
+        *mesh.indices().iter().max().unwrap() as usize,
+        count * 2 - 1
+    );
+    assert!((volume(&mesh) - 2.0).abs() < 1e-4);
+}
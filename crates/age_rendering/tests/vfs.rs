@@ -0,0 +1,153 @@
+use age_rendering::resources::{load_binary, load_string};
+use age_rendering::vfs::{self, Archive, ArchiveBuilder, FileSource};
+use std::path::Path;
+
+fn archive(compress: bool) -> Archive {
+    let mut builder = ArchiveBuilder::new().with_compression(compress);
+    builder
+        .add_file("cube/cube.obj", b"mtllib cube.mtl\n".repeat(64))
+        .unwrap();
+    builder
+        .add_file("cube/cube.mtl", b"map_Kd ../textures/cube.png\n".to_vec())
+        .unwrap();
+    builder
+        .add_file("textures/cube.png", vec![1, 2, 3])
+        .unwrap();
+    let mut bytes = Vec::new();
+    builder.write(&mut bytes).unwrap();
+    Archive::from_bytes(bytes).unwrap()
+}
+
+#[test]
+fn archives_round_trip() {
+    for compress in [true, false] {
+        let archive = archive(compress);
+        assert_eq!(archive.len(), 3);
+        assert_eq!(
+            archive.read("cube/cube.obj").unwrap(),
+            b"mtllib cube.mtl\n".repeat(64)
+        );
+        assert_eq!(archive.read("textures/cube.png").unwrap(), vec![1, 2, 3]);
+        assert!(!archive.contains("cube/missing.obj"));
+    }
+}
+
+#[test]
+fn rejects_other_files() {
+    assert!(Archive::from_bytes(b"not an archive".to_vec()).is_err());
+    assert!(
+        ArchiveBuilder::new()
+            .add_file("../outside", Vec::new())
+            .is_err()
+    );
+}
+
+#[test]
+fn rejects_corrupted_archives() {
+    let mut builder = ArchiveBuilder::new().with_compression(false);
+    builder.add_file("a", vec![7; 100]).unwrap();
+    let mut bytes = Vec::new();
+    builder.write(&mut bytes).unwrap();
+    //magic, count, path length, "a", then the entry's offset, size, original size
+    let count = 8;
+    let offset = count + 4 + 2 + 1;
+    let size = offset + 8;
+    let original_size = size + 8;
+
+    let truncated = bytes[..bytes.len() - 1].to_vec();
+    assert!(Archive::from_bytes(truncated).is_err());
+
+    let mut huge_count = bytes.clone();
+    huge_count[count..count + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(Archive::from_bytes(huge_count).is_err());
+
+    for field in [offset, size, original_size] {
+        let mut corrupted = bytes.clone();
+        corrupted[field..field + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Archive::from_bytes(corrupted).is_err());
+    }
+
+    //a compressed entry that claims more than it decompresses to
+    let mut builder = ArchiveBuilder::new();
+    builder.add_file("a", vec![7; 100]).unwrap();
+    let mut bytes = Vec::new();
+    builder.write(&mut bytes).unwrap();
+    bytes[original_size..original_size + 8].copy_from_slice(&200u64.to_le_bytes());
+    let archive = Archive::from_bytes(bytes).unwrap();
+    assert_eq!(
+        archive.read("a").unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn directories_pack_sorted_without_symlinks() {
+    let root = std::env::temp_dir().join(format!("age_vfs_pack_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    for name in ["b.txt", "a.txt", "sub/c.txt"] {
+        std::fs::write(root.join(name), name).unwrap();
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&root, root.join("loop")).unwrap();
+
+    let pack = || {
+        let mut builder = ArchiveBuilder::new();
+        builder.add_dir(&root).unwrap();
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+        bytes
+    };
+    let bytes = pack();
+    let position = |name: &str| {
+        bytes
+            .windows(name.len())
+            .position(|window| window == name.as_bytes())
+            .unwrap()
+    };
+    assert!(position("a.txt") < position("b.txt"));
+    assert!(position("b.txt") < position("sub/c.txt"));
+    assert_eq!(pack(), bytes);
+    let archive = Archive::from_bytes(bytes).unwrap();
+    assert_eq!(archive.len(), 3);
+    assert_eq!(archive.read("sub/c.txt").unwrap(), b"sub/c.txt");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let name = std::ffi::OsStr::from_bytes(b"bad\xff.txt");
+        std::fs::write(root.join(name), "").unwrap();
+        let error = ArchiveBuilder::new().add_dir(&root).unwrap_err();
+        assert!(error.to_string().contains("UTF-8"), "{error}");
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn normalizes_paths() {
+    assert_eq!(
+        vfs::normalize(Path::new("res/cube/../textures/./cube.png")).as_deref(),
+        Some("res/textures/cube.png")
+    );
+    assert_eq!(vfs::normalize(Path::new("../cube.obj")), None);
+}
+
+//the only test touching the global mounts, they're shared by every test in this file
+#[test]
+fn mounted_archives_serve_relative_paths() {
+    vfs::mount("packed", archive(true)).unwrap();
+    let obj = pollster::block_on(load_string(Path::new("packed/cube/cube.obj"))).unwrap();
+    assert!(obj.starts_with("mtllib cube.mtl"));
+    //how load_model resolves what the MTL points at
+    let texture = Path::new("packed/cube").join("../textures/cube.png");
+    assert_eq!(
+        pollster::block_on(load_binary(&texture)).unwrap(),
+        vec![1, 2, 3]
+    );
+    assert!(vfs::exists("packed/cube/cube.mtl"));
+    assert!(pollster::block_on(load_binary(Path::new("packed/missing.png"))).is_err());
+    //loose files still work
+    assert!(vfs::exists("Cargo.toml"));
+    vfs::unmount_all();
+    assert!(!vfs::exists("packed/cube/cube.mtl"));
+}
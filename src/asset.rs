@@ -1,6 +1,326 @@
-//#[macro_export]
+use age_audio::channel::Channel;
+use age_audio::errors::{AudioError, describe};
+use age_audio::output_handle::OutputHandle;
+use age_audio::output_handle::output_markers::OutputEnabled;
+use age_audio::sound::SoundHandle;
+use age_audio::source::{LoadMode, SoundData};
+use age_audio::traits::marker::OutputHandlerState;
+use age_rendering::assets::{AssetCache, Handle};
+use age_rendering::model::Model;
+use age_rendering::resources::load_string;
+use age_rendering::state::State;
+use age_rendering::texture::Texture;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+
+//shaders are plain wgpu::ShaderModules
+pub type Sound = SoundData;
+pub type Shader = wgpu::ShaderModule;
+
+//the AssetServer for sounds, State::assets has the models and textures
+//files are read (or decoded) on background threads, the same path gives the same handle while it's alive
+#[derive(Clone, Default)]
+pub struct SoundAssets {
+    cache: Arc<AssetCache<Sound>>,
+}
+
+impl SoundAssets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Handle<Sound> {
+        self.load_with(path, LoadMode::Encoded)
+    }
+
+    //the mode of the first load wins while its handles are alive
+    pub fn load_with<P: AsRef<Path>>(&self, path: P, mode: LoadMode) -> Handle<Sound> {
+        let (handle, pending) = self.cache.get_or_insert(path);
+        if let Some(pending) = pending {
+            let path = handle.path().to_path_buf();
+            std::thread::spawn(move || {
+                if pending.is_wanted() {
+                    pending.finish(SoundData::load(&path, mode).map_err(|error| {
+                        let error = describe(&error);
+                        log::error!("couldn't load sound {}: {error}", path.display());
+                        error
+                    }));
+                }
+            });
+        }
+        handle
+    }
+}
+
+//FileNotLoaded while the sound is still loading or failed to
+pub fn play_sound(
+    output: &mut OutputHandle<OutputEnabled>,
+    channel: Channel,
+    sound: &Handle<Sound>,
+) -> Result<SoundHandle, AudioError> {
+    match sound.get() {
+        Some(data) => output.play_data_on(channel, &data),
+        None => Err(AudioError::FileNotLoaded(
+            sound.path().to_string_lossy().into_owned(),
+        )),
+    }
+}
+
+//a name from a manifest!, typed by what it was declared as
+pub struct AssetId<T> {
+    name: &'static str,
+    path: &'static str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> AssetId<T> {
+    pub const fn new(name: &'static str, path: &'static str) -> Self {
+        Self {
+            name,
+            path,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+}
+
+impl<T> Clone for AssetId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetId<T> {}
+
+impl<T> Debug for AssetId<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssetId")
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl AssetId<Model> {
+    pub fn handle(&self, state: &State) -> Option<Handle<Model>> {
+        state.models.get(self.name).cloned()
+    }
+}
+
+impl AssetId<Texture> {
+    pub fn handle(&self, state: &State) -> Option<Handle<Texture>> {
+        state.textures.get(self.name).cloned()
+    }
+}
+
+impl AssetId<Shader> {
+    pub fn get<'a>(&self, state: &'a State) -> Option<&'a Shader> {
+        state.shaders.get(self.name)
+    }
+}
+
+impl AssetId<Sound> {
+    pub fn play(
+        &self,
+        output: &mut OutputHandle<OutputEnabled>,
+    ) -> Result<SoundHandle, AudioError> {
+        output.play_loaded(self.name.to_string())
+    }
+
+    pub fn play_on(
+        &self,
+        output: &mut OutputHandle<OutputEnabled>,
+        channel: Channel,
+    ) -> Result<SoundHandle, AudioError> {
+        output.play_loaded_on(channel, self.name.to_string())
+    }
+}
+
+//which asset of a manifest failed and why
+#[derive(Debug)]
+pub enum AssetError {
+    //the error's description, from the asset server
+    ModelError(&'static str, String),
+    TextureError(&'static str, String),
+    ShaderError(&'static str, std::io::Error),
+    SoundError(&'static str, AudioError),
+}
+
+impl Error for AssetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AssetError::ShaderError(_, err) => Some(err),
+            AssetError::SoundError(_, err) => Some(err),
+            AssetError::ModelError(_, _) | AssetError::TextureError(_, _) => None,
+        }
+    }
+}
+
+impl Display for AssetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetError::ModelError(name, err) => {
+                write!(f, "couldn't load model asset {name:?}: {err}")
+            }
+            AssetError::TextureError(name, err) => {
+                write!(f, "couldn't load texture asset {name:?}: {err}")
+            }
+            AssetError::ShaderError(name, _) => {
+                write!(f, "couldn't read shader asset {name:?}")
+            }
+            AssetError::SoundError(name, _) => write!(f, "couldn't load sound asset {name:?}"),
+        }
+    }
+}
+
+//used by manifest!, replaces whatever was loaded under the same name
+//only starts loading, wait for it with wait_model_asset
+pub fn load_model_asset(state: &mut State, id: AssetId<Model>) {
+    let handle = state.assets().load_model(id.path);
+    state.models.insert(id.name.into(), handle);
+}
+
+pub fn wait_model_asset(state: &State, id: AssetId<Model>) -> Result<(), AssetError> {
+    match state.models.get(id.name) {
+        Some(handle) => handle
+            .wait()
+            .map(|_| ())
+            .map_err(|error| AssetError::ModelError(id.name, error)),
+        None => Ok(()),
+    }
+}
+
+pub fn load_texture_asset(state: &mut State, id: AssetId<Texture>) {
+    let handle = state.assets().load_texture(id.path);
+    state.textures.insert(id.name, handle);
+}
+
+pub fn wait_texture_asset(state: &State, id: AssetId<Texture>) -> Result<(), AssetError> {
+    match state.textures.get(id.name) {
+        Some(handle) => handle
+            .wait()
+            .map(|_| ())
+            .map_err(|error| AssetError::TextureError(id.name, error)),
+        None => Ok(()),
+    }
+}
+
+//WGSL only
+pub async fn load_shader_asset(state: &mut State, id: AssetId<Shader>) -> Result<(), AssetError> {
+    let source = load_string(Path::new(id.path))
+        .await
+        .map_err(|error| AssetError::ShaderError(id.name, error))?;
+    let shader = state
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(id.name),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+    state.insert_shader(id.name, id.path, shader);
+    Ok(())
+}
+
+pub fn load_sound_asset<O: OutputHandlerState>(
+    output: &mut OutputHandle<O>,
+    id: AssetId<Sound>,
+) -> Result<(), AssetError> {
+    output
+        .load_file(id.path, id.name.to_string())
+        .map_err(|error| AssetError::SoundError(id.name, error))
+}
+
+//declares every asset of the game in one place:
+//manifest! {
+//    pub struct Assets {
+//        models { cube: "res/cube/cube.obj" }
+//        textures { grass: "res/grass.png" }
+//        sounds { jump: "res/jump.wav" }
+//        shaders { water: "res/water.wgsl" }
+//    }
+//}
+//every block is optional but they have to come in this order
+//Assets::new() has an AssetId field per name, Assets::load() loads them all into the State and OutputHandle
+//names are fields, so a name used twice (in any block) doesn't compile
+//put #[check_files] before the struct to fail the build when a file doesn't exist, paths are then
+//also checked relative to the crate root, so only use it when the game runs from there
+#[macro_export]
 macro_rules! manifest {
-    () => {
-        
+    (@check $($category:ident { $($asset:ident: $path:literal),* $(,)? })*) => {
+        $($(
+            const _: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path));
+        )*)*
     };
-}
\ No newline at end of file
+    (
+        #[check_files]
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident { $($body:tt)* }
+    ) => {
+        $crate::manifest!(@check $($body)*);
+        $crate::manifest! {
+            $(#[$meta])*
+            $vis struct $name { $($body)* }
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(models { $($model:ident: $model_path:literal),* $(,)? })?
+            $(textures { $($texture:ident: $texture_path:literal),* $(,)? })?
+            $(sounds { $($sound:ident: $sound_path:literal),* $(,)? })?
+            $(shaders { $($shader:ident: $shader_path:literal),* $(,)? })?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy)]
+        $vis struct $name {
+            $($(pub $model: $crate::asset::AssetId<$crate::age_rendering::model::Model>,)*)?
+            $($(pub $texture: $crate::asset::AssetId<$crate::age_rendering::texture::Texture>,)*)?
+            $($(pub $sound: $crate::asset::AssetId<$crate::asset::Sound>,)*)?
+            $($(pub $shader: $crate::asset::AssetId<$crate::asset::Shader>,)*)?
+        }
+
+        impl $name {
+            pub const fn new() -> Self {
+                Self {
+                    $($($model: $crate::asset::AssetId::new(stringify!($model), $model_path),)*)?
+                    $($($texture: $crate::asset::AssetId::new(stringify!($texture), $texture_path),)*)?
+                    $($($sound: $crate::asset::AssetId::new(stringify!($sound), $sound_path),)*)?
+                    $($($shader: $crate::asset::AssetId::new(stringify!($shader), $shader_path),)*)?
+                }
+            }
+
+            //stops at the first asset that fails
+            #[allow(unused_variables)]
+            pub async fn load<O: $crate::age_audio::traits::marker::OutputHandlerState>(
+                &self,
+                state: &mut $crate::age_rendering::state::State,
+                output: &mut $crate::age_audio::output_handle::OutputHandle<O>,
+            ) -> Result<(), $crate::asset::AssetError> {
+                //models and textures load in the background meanwhile
+                $($($crate::asset::load_model_asset(state, self.$model);)*)?
+                $($($crate::asset::load_texture_asset(state, self.$texture);)*)?
+                $($($crate::asset::load_sound_asset(output, self.$sound)?;)*)?
+                $($($crate::asset::load_shader_asset(state, self.$shader).await?;)*)?
+                $($($crate::asset::wait_model_asset(state, self.$model)?;)*)?
+                $($($crate::asset::wait_texture_asset(state, self.$texture)?;)*)?
+                Ok(())
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
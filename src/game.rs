@@ -1,16 +1,81 @@
+use crate::runner::{RunError, run_window};
+use crate::scene::{MainCamera, pull_scene, push_scene};
+use age_audio::ecs::{AudioSet, add_audio_systems};
+use age_audio::errors::AudioError;
+use age_audio::output::OutputBackend;
+use age_audio::output_handle::OutputHandle;
+use age_rendering::config::StateConfig;
+use age_rendering::input::Input;
+use age_rendering::state::State;
+use bevy_ecs::resource::Resource;
+use bevy_ecs::schedule::{IntoScheduleConfigs, Schedule};
+use bevy_ecs::system::ScheduleSystem;
 use bevy_ecs::world::World;
+use std::time::Duration;
+
+//updated by step() before the schedule runs
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct FrameTime {
+    pub delta: Duration,
+    pub elapsed: Duration,
+}
 
 pub struct Game {
     pub world: World,
+    //runs once per update(), add gameplay systems through add_systems so they run before AudioSet
+    pub schedule: Schedule,
 }
 
 impl Game {
     pub fn new() -> Game {
+        let mut world = World::new();
+        //while the window is open this is the State's Input, see scene::swap_input
+        //rebind with world.resource_mut::<Input>().profile_mut()
+        world.insert_resource(Input::default());
+        world.init_resource::<FrameTime>();
+        world.init_resource::<MainCamera>();
+        let mut schedule = Schedule::default();
+        //AudioSource etc. do nothing until enable_audio was called
+        add_audio_systems(&mut schedule);
         Game {
-            world: World::new()
+            world,
+            schedule
         }
     }
-    pub fn run(self) {
-       
+    pub fn enable_audio(&mut self, backend: OutputBackend) -> Result<(), AudioError> {
+        let output = OutputHandle::new().activate_with(backend)?;
+        self.world.insert_resource(output);
+        Ok(())
+    }
+    //ordered before the audio systems, so sources spawned or moved here are heard this frame
+    pub fn add_systems<M>(&mut self, systems: impl IntoScheduleConfigs<ScheduleSystem, M>) {
+        self.schedule.add_systems(systems.before(AudioSet));
+    }
+    pub fn update(&mut self) {
+        self.schedule.run(&mut self.world);
+    }
+    //advances FrameTime, then update()
+    pub fn step(&mut self, dt: Duration) {
+        let mut time = self.world.resource_mut::<FrameTime>();
+        time.delta = dt;
+        time.elapsed += dt;
+        self.update();
     }
-}
\ No newline at end of file
+    //before the frame, picks up the input and what the camera controller did
+    pub fn sync_from(&mut self, state: &mut State) {
+        pull_scene(&mut self.world, state);
+    }
+    //after the frame, hands the input, ModelInstances and the MainCamera back to the renderer
+    pub fn sync_to(&mut self, state: &mut State) {
+        push_scene(&mut self.world, state);
+    }
+    //blocks until the window is closed, the models in config are loaded when it opens
+    pub fn run(mut self, config: StateConfig) -> Result<(), RunError> {
+        run_window(config, |state, dt| {
+            self.sync_from(state);
+            self.step(dt);
+            self.sync_to(state);
+            true
+        })
+    }
+}
@@ -1,2 +1,20 @@
 pub mod game;
-pub mod asset;
\ No newline at end of file
+pub mod asset;
+pub mod runner;
+pub mod scene;
+//for manifest! and users that don't want to depend on them directly
+pub use age_audio;
+pub use age_rendering;
+#[cfg(feature = "python")]
+mod python;
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+//the module maturin builds from pyproject.toml, `import age_engine`
+#[cfg(feature = "python")]
+#[pymodule]
+fn age_engine(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    age_audio::python::register(module)?;
+    python::register(module)
+}
@@ -0,0 +1,261 @@
+//Game for Python, scenes are scripted there while the window loop and rendering stay in Rust
+use crate::game::{FrameTime, Game};
+use crate::scene::{MainCamera, ModelInstance, Transform};
+use age_rendering::config::StateConfig;
+use age_rendering::errors::describe;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::world::Mut;
+use cgmath::{Deg, Euler, Quaternion, Vector3};
+use pyo3::exceptions::{PyKeyError, PyRuntimeError};
+use pyo3::prelude::*;
+use std::time::Duration;
+
+type Vec3 = (f32, f32, f32);
+
+//rotations are euler angles in degrees on the Python side
+fn to_quaternion((x, y, z): Vec3) -> Quaternion<f32> {
+    Quaternion::from(Euler::new(Deg(x), Deg(y), Deg(z)))
+}
+
+fn to_euler(rotation: Quaternion<f32>) -> Vec3 {
+    let euler = Euler::from(rotation);
+    (
+        Deg::from(euler.x).0,
+        Deg::from(euler.y).0,
+        Deg::from(euler.z).0,
+    )
+}
+
+//entities are passed around as their bits
+fn to_entity(bits: u64) -> PyResult<Entity> {
+    Entity::try_from_bits(bits).ok_or_else(|| PyKeyError::new_err(bits))
+}
+
+#[pyclass(name = "Game", unsendable)]
+pub struct PyGame {
+    game: Game,
+    //taken by run()
+    config: Option<StateConfig>,
+    callbacks: Vec<Py<PyAny>>,
+}
+
+impl PyGame {
+    fn config(&mut self) -> PyResult<&mut StateConfig> {
+        self.config
+            .as_mut()
+            .ok_or_else(|| PyRuntimeError::new_err("the window was already opened"))
+    }
+
+    fn transform_mut(&mut self, entity: u64) -> PyResult<Mut<'_, Transform>> {
+        self.game
+            .world
+            .get_mut::<Transform>(to_entity(entity)?)
+            .ok_or_else(|| PyKeyError::new_err(entity))
+    }
+
+    fn transform(&self, entity: u64) -> PyResult<&Transform> {
+        self.game
+            .world
+            .get::<Transform>(to_entity(entity)?)
+            .ok_or_else(|| PyKeyError::new_err(entity))
+    }
+
+    fn call_callbacks(slf: &Bound<'_, Self>, dt: Duration) -> PyResult<()> {
+        let py = slf.py();
+        //cloned so the callbacks can borrow the game themselves
+        let callbacks = slf
+            .borrow()
+            .callbacks
+            .iter()
+            .map(|callback| callback.clone_ref(py))
+            .collect::<Vec<_>>();
+        for callback in callbacks {
+            callback.call1(py, (slf, dt.as_secs_f32()))?;
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    fn new() -> Self {
+        Self {
+            game: Game::new(),
+            config: Some(StateConfig::default()),
+            callbacks: Vec::new(),
+        }
+    }
+
+    //loaded when run() opens the window, spawn instances of it before or after that
+    fn load_model(&mut self, name: String, path: String) -> PyResult<()> {
+        self.config()?.models.insert(name.into(), path);
+        Ok(())
+    }
+
+    #[setter]
+    fn set_clear_color(&mut self, color: (f64, f64, f64)) -> PyResult<()> {
+        let (r, g, b) = color;
+        self.config()?.color = wgpu::Color { r, g, b, a: 1.0 };
+        Ok(())
+    }
+
+    //returns the entity, an int
+    #[pyo3(signature = (model, position = (0.0, 0.0, 0.0), rotation = (0.0, 0.0, 0.0), scale = (1.0, 1.0, 1.0)))]
+    fn spawn(&mut self, model: String, position: Vec3, rotation: Vec3, scale: Vec3) -> u64 {
+        let transform = Transform::from_position(position)
+            .with_rotation(to_quaternion(rotation))
+            .with_scale(scale);
+        self.game
+            .world
+            .spawn((ModelInstance(model), transform))
+            .id()
+            .to_bits()
+    }
+
+    //false if it was already despawned
+    fn despawn(&mut self, entity: u64) -> PyResult<bool> {
+        Ok(self.game.world.despawn(to_entity(entity)?))
+    }
+
+    fn model(&self, entity: u64) -> PyResult<String> {
+        self.game
+            .world
+            .get::<ModelInstance>(to_entity(entity)?)
+            .map(|model| model.0.clone())
+            .ok_or_else(|| PyKeyError::new_err(entity))
+    }
+
+    fn position(&self, entity: u64) -> PyResult<Vec3> {
+        Ok(self.transform(entity)?.position.into())
+    }
+
+    fn set_position(&mut self, entity: u64, position: Vec3) -> PyResult<()> {
+        self.transform_mut(entity)?.position = position.into();
+        Ok(())
+    }
+
+    fn rotation(&self, entity: u64) -> PyResult<Vec3> {
+        Ok(to_euler(self.transform(entity)?.rotation))
+    }
+
+    fn set_rotation(&mut self, entity: u64, rotation: Vec3) -> PyResult<()> {
+        self.transform_mut(entity)?.rotation = to_quaternion(rotation);
+        Ok(())
+    }
+
+    fn scale(&self, entity: u64) -> PyResult<Vec3> {
+        Ok(self.transform(entity)?.scale.into())
+    }
+
+    fn set_scale(&mut self, entity: u64, scale: Vec3) -> PyResult<()> {
+        self.transform_mut(entity)?.scale = scale.into();
+        Ok(())
+    }
+
+    fn translate(&mut self, entity: u64, offset: Vec3) -> PyResult<()> {
+        self.transform_mut(entity)?.position += Vector3::from(offset);
+        Ok(())
+    }
+
+    #[getter]
+    fn camera_position(&self) -> Vec3 {
+        let position = self.game.world.resource::<MainCamera>().camera().position;
+        (position.x, position.y, position.z)
+    }
+
+    #[setter]
+    fn set_camera_position(&mut self, position: Vec3) {
+        let mut main_camera = self.game.world.resource_mut::<MainCamera>();
+        main_camera.camera_mut().position = position.into();
+    }
+
+    //in degrees
+    #[getter]
+    fn camera_yaw(&self) -> f32 {
+        let camera = self.game.world.resource::<MainCamera>().camera();
+        Deg::from(camera.yaw()).0
+    }
+
+    #[setter]
+    fn set_camera_yaw(&mut self, yaw: f32) {
+        let mut main_camera = self.game.world.resource_mut::<MainCamera>();
+        main_camera.camera_mut().set_yaw(Deg(yaw));
+    }
+
+    #[getter]
+    fn camera_pitch(&self) -> f32 {
+        let camera = self.game.world.resource::<MainCamera>().camera();
+        Deg::from(camera.pitch()).0
+    }
+
+    #[setter]
+    fn set_camera_pitch(&mut self, pitch: f32) {
+        let mut main_camera = self.game.world.resource_mut::<MainCamera>();
+        main_camera.camera_mut().set_pitch(Deg(pitch));
+    }
+
+    fn look_at(&mut self, target: Vec3) {
+        let mut main_camera = self.game.world.resource_mut::<MainCamera>();
+        main_camera.camera_mut().look_at(target);
+    }
+
+    //seconds since the first frame
+    #[getter]
+    fn elapsed(&self) -> f32 {
+        self.game
+            .world
+            .resource::<FrameTime>()
+            .elapsed
+            .as_secs_f32()
+    }
+
+    //callback(game, dt) runs every frame before the schedule, returns the callback so it works as a decorator
+    fn on_update(&mut self, py: Python<'_>, callback: Py<PyAny>) -> Py<PyAny> {
+        self.callbacks.push(callback.clone_ref(py));
+        callback
+    }
+
+    //one frame without a window, for tests and tools
+    #[pyo3(signature = (dt = 1.0 / 60.0))]
+    fn step(slf: &Bound<'_, Self>, dt: f32) -> PyResult<()> {
+        let dt = Duration::from_secs_f32(dt.max(0.0));
+        Self::call_callbacks(slf, dt)?;
+        slf.borrow_mut().game.step(dt);
+        Ok(())
+    }
+
+    //opens the window and blocks until it's closed, an exception in a callback closes it and is raised here
+    fn run(slf: &Bound<'_, Self>) -> PyResult<()> {
+        let py = slf.py();
+        let config = slf
+            .borrow_mut()
+            .config
+            .take()
+            .ok_or_else(|| PyRuntimeError::new_err("run() can only be called once"))?;
+        let mut error = None;
+        let result = crate::runner::run_window(config, |state, dt| {
+            slf.borrow_mut().game.sync_from(state);
+            if let Err(callback_error) = py
+                .check_signals()
+                .and_then(|()| Self::call_callbacks(slf, dt))
+            {
+                error = Some(callback_error);
+                return false;
+            }
+            let mut this = slf.borrow_mut();
+            this.game.step(dt);
+            this.game.sync_to(state);
+            true
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+        result.map_err(|error| PyRuntimeError::new_err(describe(&error)))
+    }
+}
+
+pub(crate) fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGame>()?;
+    Ok(())
+}
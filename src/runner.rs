@@ -0,0 +1,162 @@
+use age_rendering::config::StateConfig;
+use age_rendering::errors::StateCreationError;
+use age_rendering::state::State;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use winit::application::ApplicationHandler;
+use winit::error::{EventLoopError, OsError};
+use winit::event::{DeviceEvent, DeviceId, KeyEvent, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::PhysicalKey;
+use winit::window::{Window, WindowId};
+
+#[derive(Debug)]
+pub enum RunError {
+    EventLoopError(EventLoopError),
+    WindowError(OsError),
+    StateCreationError(StateCreationError),
+}
+
+impl Error for RunError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(match self {
+            RunError::EventLoopError(err) => err,
+            RunError::WindowError(err) => err,
+            RunError::StateCreationError(err) => err,
+        })
+    }
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::EventLoopError(_) => write!(f, "event loop failed"),
+            RunError::WindowError(_) => write!(f, "couldn't create the window"),
+            RunError::StateCreationError(_) => write!(f, "couldn't set up rendering"),
+        }
+    }
+}
+
+//opens the window and calls frame every frame before rendering, until it returns false or the window closes
+pub(crate) fn run_window<F>(config: StateConfig, frame: F) -> Result<(), RunError>
+where
+    F: FnMut(&mut State, Duration) -> bool,
+{
+    let event_loop = EventLoop::new().map_err(RunError::EventLoopError)?;
+    let mut runner = Runner {
+        config: Some(config),
+        state: None,
+        last_frame: Instant::now(),
+        frame,
+        error: None,
+    };
+    event_loop
+        .run_app(&mut runner)
+        .map_err(RunError::EventLoopError)?;
+    match runner.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+struct Runner<F> {
+    //taken once the window exists
+    config: Option<StateConfig>,
+    state: Option<State>,
+    last_frame: Instant,
+    frame: F,
+    error: Option<RunError>,
+}
+
+impl<F> Runner<F> {
+    fn create_state(&mut self, event_loop: &ActiveEventLoop) -> Result<(), RunError> {
+        let Some(config) = self.config.take() else {
+            return Ok(());
+        };
+        let window = event_loop
+            .create_window(Window::default_attributes().with_title("AGE"))
+            .map_err(RunError::WindowError)?;
+        let state = pollster::block_on(State::new(Arc::new(window), config))
+            .map_err(RunError::StateCreationError)?;
+        self.state = Some(state);
+        self.last_frame = Instant::now();
+        Ok(())
+    }
+}
+
+impl<F> ApplicationHandler for Runner<F>
+where
+    F: FnMut(&mut State, Duration) -> bool,
+{
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if let Err(error) = self.create_state(event_loop) {
+            self.error = Some(error);
+            event_loop.exit();
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => state.resize(size.width, size.height),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code),
+                        state: key_state,
+                        ..
+                    },
+                ..
+            } => state.handle_key(event_loop, code, key_state.is_pressed()),
+            WindowEvent::MouseInput {
+                state: button_state,
+                button,
+                ..
+            } => state.handle_mouse_button(button, button_state.is_pressed()),
+            WindowEvent::MouseWheel { delta, .. } => state.handle_mouse_scroll(&delta),
+            WindowEvent::RedrawRequested => {
+                let now = Instant::now();
+                let dt = now - self.last_frame;
+                self.last_frame = now;
+                state.update(dt);
+                if !(self.frame)(state, dt) {
+                    event_loop.exit();
+                    return;
+                }
+                let models = state.instanced_models().collect::<Vec<_>>();
+                match state.render(models.into_iter()) {
+                    Ok(()) => {}
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        let size = state.window().inner_size();
+                        state.resize(size.width, size.height);
+                    }
+                    Err(error) => log::error!("unable to render: {error}"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        if let (Some(state), DeviceEvent::MouseMotion { delta: (dx, dy) }) =
+            (&mut self.state, event)
+        {
+            state.handle_mouse_motion(dx, dy);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use age_rendering::camera::Camera;
+use age_rendering::input::Input;
+use age_rendering::instance::Instance;
+use age_rendering::state::State;
+use bevy_ecs::prelude::*;
+use cgmath::{Deg, Quaternion, Vector3, Zero};
+use std::collections::HashMap;
+
+//where a ModelInstance is drawn
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Transform {
+    pub fn from_position<V: Into<Vector3<f32>>>(position: V) -> Self {
+        Self {
+            position: position.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Quaternion<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale<V: Into<Vector3<f32>>>(mut self, scale: V) -> Self {
+        self.scale = scale.into();
+        self
+    }
+
+    pub fn to_instance(&self) -> Instance {
+        Instance {
+            position: self.position,
+            rotation: self.rotation,
+            scale: self.scale,
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: Vector3::zero(),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+//draws the model loaded under that name at the entity's Transform
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+#[require(Transform)]
+pub struct ModelInstance(pub String);
+
+//mirrors the State's main camera, it's read back every frame unless it was changed through camera_mut
+#[derive(Resource, Debug)]
+pub struct MainCamera {
+    camera: Camera,
+    moved: bool,
+}
+
+impl MainCamera {
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    //the change is applied to the State after the frame
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        self.moved = true;
+        &mut self.camera
+    }
+}
+
+impl Default for MainCamera {
+    //the State's starting camera
+    fn default() -> Self {
+        Self {
+            camera: Camera::new((0.0, 5.0, 10.0), Deg(-90.0), Deg(-20.0)),
+            moved: false,
+        }
+    }
+}
+
+fn copy_camera(from: &Camera, to: &mut Camera) {
+    to.position = from.position;
+    to.set_yaw(from.yaw());
+    to.set_pitch(from.pitch());
+    to.set_roll(from.roll());
+}
+
+//the runner feeds the State's Input, while the schedule runs it's the world's Input resource instead
+//pull_scene moves it in and push_scene moves it back, so there's only ever one Input getting the events
+pub fn swap_input(world: &mut World, input: &mut Input) {
+    std::mem::swap(&mut *world.get_resource_or_init::<Input>(), input);
+}
+
+//the camera controller may have moved the camera
+pub fn pull_scene(world: &mut World, state: &mut State) {
+    swap_input(world, &mut state.input);
+    let mut main_camera = world.get_resource_or_init::<MainCamera>();
+    if !main_camera.moved {
+        copy_camera(
+            state.camera(),
+            &mut main_camera.bypass_change_detection().camera,
+        );
+    }
+}
+
+//hands the Input back, and every ModelInstance and the changed camera to the State
+pub fn push_scene(world: &mut World, state: &mut State) {
+    swap_input(world, &mut state.input);
+    let mut instances = HashMap::<&str, Vec<Instance>>::new();
+    let mut query = world.query::<(&ModelInstance, &Transform)>();
+    for (model, transform) in query.iter(world) {
+        instances
+            .entry(model.0.as_str())
+            .or_default()
+            .push(transform.to_instance());
+    }
+    //instances of models that aren't loaded are skipped
+    let models = state.models.keys().cloned().collect::<Vec<_>>();
+    for model in models {
+        state.set_instances(&model, instances.get(&*model).map_or(&[], Vec::as_slice));
+    }
+
+    if let Some(mut main_camera) = world.get_resource_mut::<MainCamera>()
+        && main_camera.moved
+    {
+        copy_camera(&main_camera.camera, state.camera_mut());
+        main_camera.moved = false;
+    }
+}
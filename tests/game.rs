@@ -0,0 +1,46 @@
+use age_engine::age_rendering::input::{Input, actions};
+use age_engine::game::Game;
+use age_engine::scene::swap_input;
+use bevy_ecs::prelude::*;
+use std::time::Duration;
+use winit::keyboard::KeyCode;
+
+#[derive(Resource, Default)]
+struct Seen {
+    exit: bool,
+    forward: f32,
+}
+
+fn read_input(input: Res<Input>, mut seen: ResMut<Seen>) {
+    seen.exit = input.just_pressed(actions::EXIT);
+    seen.forward = input.axis(actions::MOVE_FORWARD);
+}
+
+//what sync_from/sync_to do with the State's Input around step()
+#[test]
+fn key_events_reach_the_input_resource() {
+    let mut game = Game::new();
+    game.world.init_resource::<Seen>();
+    game.add_systems(read_input);
+
+    //the runner only feeds this one
+    let mut input = Input::default();
+    input.handle_key(KeyCode::Escape, true);
+    input.handle_key(KeyCode::KeyW, true);
+    swap_input(&mut game.world, &mut input);
+    game.step(Duration::from_millis(16));
+    let seen = game.world.resource::<Seen>();
+    assert!(seen.exit);
+    assert_eq!(seen.forward, 1.0);
+
+    //and gets it back, events in between frames land there
+    swap_input(&mut game.world, &mut input);
+    assert!(input.pressed(actions::EXIT));
+    input.end_frame();
+    input.handle_key(KeyCode::KeyW, false);
+    swap_input(&mut game.world, &mut input);
+    game.step(Duration::from_millis(16));
+    let seen = game.world.resource::<Seen>();
+    assert!(!seen.exit);
+    assert_eq!(seen.forward, 0.0);
+}
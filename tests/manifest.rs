@@ -0,0 +1,35 @@
+use age_engine::manifest;
+
+// the models and sounds don't exist, so only the shaders can be checked
+manifest! {
+    #[check_files]
+    pub struct Checked {
+        shaders { main: "crates/age_rendering/src/shader.wgsl", debug: "crates/age_rendering/src/debug.wgsl" }
+    }
+}
+
+manifest! {
+    struct Assets {
+        models { cube: "res/cube/cube.obj" }
+        sounds { jump: "res/jump.wav", music: "res/music.ogg" }
+    }
+}
+
+manifest! {
+    struct Empty {}
+}
+
+const ASSETS: Assets = Assets::new();
+
+#[test]
+fn ids_keep_names_and_paths() {
+    assert_eq!(ASSETS.cube.name(), "cube");
+    assert_eq!(ASSETS.cube.path(), "res/cube/cube.obj");
+    assert_eq!(ASSETS.music.name(), "music");
+    assert_eq!(Assets::default().jump.path(), "res/jump.wav");
+    assert_eq!(
+        Checked::new().main.path(),
+        "crates/age_rendering/src/shader.wgsl"
+    );
+    let _ = Empty::new();
+}
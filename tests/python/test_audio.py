@@ -0,0 +1,137 @@
+# build the module first: `maturin develop`, then run `pytest`
+# everything runs on the null backend, so no sound device is needed
+import struct
+import wave
+
+import pytest
+
+import age_engine
+
+SAMPLE_RATE = 44100
+# what 16384 in a 16 bit file decodes to
+LEVEL = 0.5
+
+
+@pytest.fixture
+def wav_file(tmp_path):
+    # mono, 0.25 seconds at a constant level
+    path = tmp_path / "test.wav"
+    with wave.open(str(path), "wb") as file:
+        file.setnchannels(1)
+        file.setsampwidth(2)
+        file.setframerate(SAMPLE_RATE)
+        file.writeframes(struct.pack("<h", 16384) * (SAMPLE_RATE // 4))
+    return path
+
+
+@pytest.fixture
+def output(wav_file):
+    handle = age_engine.OutputHandle()
+    handle.load_file(wav_file, "test")
+    handle.activate("null", sample_rate=SAMPLE_RATE)
+    return handle
+
+
+def count_near(samples, value):
+    return sum(1 for sample in samples if abs(sample - value) < 0.001)
+
+
+def test_starts_disabled():
+    handle = age_engine.OutputHandle()
+    assert not handle.is_enabled
+    with pytest.raises(age_engine.OutputStateError):
+        handle.play_loaded("test")
+
+
+def test_activate_and_disable(wav_file):
+    handle = age_engine.OutputHandle()
+    handle.load_file(wav_file, "test")
+    handle.activate("null")
+    assert handle.is_enabled
+    assert handle.channel_count == 2
+    with pytest.raises(age_engine.OutputStateError):
+        handle.activate("null")
+
+    handle.disable()
+    assert not handle.is_enabled
+    # loaded files survive switching
+    assert handle.loaded_files() == ["test"]
+
+
+def test_unknown_backend():
+    handle = age_engine.OutputHandle()
+    with pytest.raises(ValueError):
+        handle.activate("speakers")
+    with pytest.raises(ValueError):
+        handle.activate("wav")
+    assert not handle.is_enabled
+
+
+def test_play_loaded_renders_the_file(output):
+    sound = output.play_loaded("test")
+    samples = output.render(SAMPLE_RATE)
+    assert len(samples) == SAMPLE_RATE * 2
+    played = count_near(samples, LEVEL)
+    assert abs(played - SAMPLE_RATE // 4 * 2) < 2048
+    assert all(sample == 0.0 for sample in samples[-1024:])
+    assert sound.is_finished
+
+
+def test_play_file(output, wav_file):
+    output.play_file(wav_file, channel="ui")
+    assert count_near(output.render(4096), LEVEL) > 0
+
+
+def test_volumes(output):
+    output.set_channel_volume("sfx", 0.5)
+    assert output.channel_volume("sfx") == 0.5
+    output.play_loaded("test")
+    samples = output.render(4096)
+    assert count_near(samples, LEVEL * 0.5) > 0
+    assert count_near(samples, LEVEL) == 0
+
+
+def test_pause_and_play(output):
+    output.play_loaded("test")
+    output.pause()
+    assert output.is_paused
+    assert all(sample == 0.0 for sample in output.render(4096)[1024:])
+    output.play()
+    assert count_near(output.render(4096), LEVEL) > 0
+
+
+def test_sound_handle(output):
+    sound = output.play_loaded("test")
+    sound.looping = True
+    assert sound.looping
+    output.render(SAMPLE_RATE)
+    assert not sound.is_finished
+    sound.stop()
+    output.render(1024)
+    assert sound.is_finished
+
+
+def test_unload(output):
+    output.unload_file("test")
+    assert output.loaded_files() == []
+    with pytest.raises(age_engine.FileNotLoadedError):
+        output.play_loaded("test")
+    with pytest.raises(age_engine.FileNotLoadedError):
+        output.unload_file("test")
+
+
+def test_missing_file_is_an_os_error(output, tmp_path):
+    with pytest.raises(OSError) as error:
+        output.load_file(tmp_path / "missing.wav", "missing")
+    assert error.value.filename == str(tmp_path / "missing.wav")
+
+
+def test_exceptions_share_a_base():
+    assert issubclass(age_engine.FileNotLoadedError, age_engine.AudioException)
+    assert issubclass(age_engine.OutputStateError, age_engine.AudioException)
+
+
+def test_render_needs_an_enabled_output():
+    handle = age_engine.OutputHandle()
+    with pytest.raises(age_engine.OutputStateError):
+        handle.render(1024)
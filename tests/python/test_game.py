@@ -0,0 +1,79 @@
+# the Game is stepped without a window, so no GPU is needed
+import math
+
+import pytest
+
+import age_engine
+
+
+def close(a, b):
+    return all(math.isclose(x, y, abs_tol=1e-4) for x, y in zip(a, b))
+
+
+@pytest.fixture
+def game():
+    return age_engine.Game()
+
+
+def test_spawn_with_transform(game):
+    entity = game.spawn("cube", position=(1, 2, 3), rotation=(0, 90, 0), scale=(2, 2, 2))
+    assert game.model(entity) == "cube"
+    assert close(game.position(entity), (1, 2, 3))
+    assert close(game.rotation(entity), (0, 90, 0))
+    assert close(game.scale(entity), (2, 2, 2))
+
+
+def test_move_instances(game):
+    entity = game.spawn("cube")
+    game.set_position(entity, (0, 1, 0))
+    game.translate(entity, (1, 0, 0))
+    assert close(game.position(entity), (1, 1, 0))
+    game.set_scale(entity, (1, 3, 1))
+    assert close(game.scale(entity), (1, 3, 1))
+
+
+def test_despawn(game):
+    entity = game.spawn("cube")
+    assert game.despawn(entity)
+    with pytest.raises(KeyError):
+        game.position(entity)
+
+
+def test_update_callbacks(game):
+    entity = game.spawn("cube")
+    frames = []
+
+    @game.on_update
+    def move(game, dt):
+        frames.append(dt)
+        game.translate(entity, (dt, 0, 0))
+
+    for _ in range(4):
+        game.step(0.25)
+    assert frames == [0.25] * 4
+    assert close(game.position(entity), (1, 0, 0))
+    assert math.isclose(game.elapsed, 1.0)
+
+
+def test_callback_errors_are_raised(game):
+    @game.on_update
+    def broken(game, dt):
+        raise ValueError("broken")
+
+    with pytest.raises(ValueError):
+        game.step()
+
+
+def test_camera(game):
+    game.camera_position = (0, 0, 5)
+    game.look_at((0, 0, 0))
+    assert close(game.camera_position, (0, 0, 5))
+    assert math.isclose(game.camera_pitch, 0, abs_tol=1e-4)
+    assert math.isclose(game.camera_yaw, -90, abs_tol=1e-4)
+    game.camera_pitch = 30
+    assert math.isclose(game.camera_pitch, 30, abs_tol=1e-4)
+
+
+def test_load_model(game):
+    game.load_model("cube", "cube/cube.obj")
+    game.clear_color = (0.1, 0.2, 0.3)